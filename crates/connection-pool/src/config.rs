@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// The configuration of a pool of connections to one address.
+///
+/// In a runtime config file, this is the `pool` table of a database's configuration:
+///
+/// ```toml
+/// [outbound_pg.pool]
+/// max_size = 16
+/// idle_timeout_secs = 300
+/// checkout_timeout_secs = 30
+/// health_check = true
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "TomlPoolConfig")]
+pub struct PoolConfig {
+    /// The maximum number of open connections, idle or checked out.
+    pub max_size: usize,
+    /// How long a connection may sit idle in the pool before it is closed.
+    pub idle_timeout: Duration,
+    /// How long to wait for a connection to be released when the pool is full.
+    pub checkout_timeout: Duration,
+    /// Whether to check that an idle connection is still usable before checking it out.
+    pub health_check: bool,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 16,
+            idle_timeout: Duration::from_secs(300),
+            checkout_timeout: Duration::from_secs(30),
+            health_check: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlPoolConfig {
+    max_size: Option<usize>,
+    idle_timeout_secs: Option<u64>,
+    checkout_timeout_secs: Option<u64>,
+    health_check: Option<bool>,
+}
+
+impl TryFrom<TomlPoolConfig> for PoolConfig {
+    type Error = String;
+
+    fn try_from(config: TomlPoolConfig) -> Result<Self, String> {
+        let default = PoolConfig::default();
+        let max_size = config.max_size.unwrap_or(default.max_size);
+        if max_size == 0 {
+            return Err("pool 'max_size' must be at least 1".into());
+        }
+        Ok(Self {
+            max_size,
+            idle_timeout: config
+                .idle_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.idle_timeout),
+            checkout_timeout: config
+                .checkout_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.checkout_timeout),
+            health_check: config.health_check.unwrap_or(default.health_check),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(table: toml::Table) -> Result<PoolConfig, toml::de::Error> {
+        toml::Value::Table(table).try_into()
+    }
+
+    #[test]
+    fn pool_config_from_toml() {
+        let config = parse(toml::toml! {
+            max_size = 4
+            idle_timeout_secs = 10
+        })
+        .unwrap();
+        assert_eq!(config.max_size, 4);
+        assert_eq!(config.idle_timeout, Duration::from_secs(10));
+        assert!(config.health_check);
+
+        let err = parse(toml::toml! { max_size = 0 }).unwrap_err();
+        assert!(err.to_string().contains("at least 1"), "{err}");
+        assert!(parse(toml::toml! { size = 1 }).is_err());
+    }
+}
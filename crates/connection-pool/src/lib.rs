@@ -0,0 +1,281 @@
+//! App-level pools of database connections.
+//!
+//! Connections are pooled per address. A guest `connection` resource checks out a connection
+//! from the pool for its address and returns it to the pool when the resource is dropped.
+
+mod config;
+
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+pub use config::PoolConfig;
+
+/// Opens, checks, and resets the connections of a pool.
+#[async_trait]
+pub trait ManageConnection: 'static {
+    /// The type of connection.
+    type Connection: Send + Sync + 'static;
+
+    /// Opens a new connection to the given address.
+    async fn connect(address: &str) -> anyhow::Result<Self::Connection>;
+
+    /// Checks that a pooled connection is still usable before it is checked out again.
+    async fn is_healthy(connection: &mut Self::Connection) -> bool;
+
+    /// Discards any session state left by a guest before the connection is returned to its pool.
+    async fn reset(connection: &mut Self::Connection) -> anyhow::Result<()>;
+}
+
+/// Pools of connections, by address.
+pub struct Pools<M: ManageConnection> {
+    config: PoolConfig,
+    pools: Mutex<HashMap<String, Arc<Pool<M>>>>,
+}
+
+impl<M: ManageConnection> Pools<M> {
+    pub fn new(config: PoolConfig) -> Self {
+        Self {
+            config,
+            pools: Default::default(),
+        }
+    }
+
+    /// Checks out a connection to the given address, connecting if no idle connection is
+    /// available.
+    ///
+    /// Waits for a connection to be returned if the pool for the address is full.
+    pub async fn get(&self, address: &str) -> anyhow::Result<PooledConnection<M>> {
+        let pool = self
+            .pools
+            .lock()
+            .unwrap()
+            .entry(address.to_owned())
+            .or_insert_with(|| {
+                Arc::new(Pool {
+                    address: address.to_owned(),
+                    config: self.config.clone(),
+                    idle: Default::default(),
+                    permits: Arc::new(Semaphore::new(self.config.max_size)),
+                    returned: Notify::new(),
+                })
+            })
+            .clone();
+        tokio::time::timeout(self.config.checkout_timeout, pool.clone().checkout())
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "timed out waiting for one of the {} pooled connections to be released",
+                    self.config.max_size
+                )
+            })?
+    }
+}
+
+/// The connections to one address.
+struct Pool<M: ManageConnection> {
+    address: String,
+    config: PoolConfig,
+    idle: Mutex<Vec<IdleConnection<M::Connection>>>,
+    /// Limits the number of open connections, idle or checked out.
+    permits: Arc<Semaphore>,
+    /// Notified when a connection is returned to the idle list.
+    returned: Notify,
+}
+
+struct IdleConnection<C> {
+    connection: C,
+    permit: OwnedSemaphorePermit,
+    since: Instant,
+}
+
+impl<M: ManageConnection> Pool<M> {
+    async fn checkout(self: Arc<Self>) -> anyhow::Result<PooledConnection<M>> {
+        loop {
+            let returned = self.returned.notified();
+            tokio::pin!(returned);
+            returned.as_mut().enable();
+
+            while let Some(mut idle) = self.pop_idle() {
+                if !self.config.health_check || M::is_healthy(&mut idle.connection).await {
+                    return Ok(self.pooled(idle.connection, idle.permit));
+                }
+                tracing::debug!("Discarding unhealthy pooled connection");
+            }
+
+            tokio::select! {
+                permit = self.permits.clone().acquire_owned() => {
+                    let permit = permit.context("connection pool closed")?;
+                    let connection = M::connect(&self.address).await?;
+                    return Ok(self.pooled(connection, permit));
+                }
+                _ = returned => continue,
+            }
+        }
+    }
+
+    /// Takes the most recently returned idle connection, discarding any that have expired.
+    fn pop_idle(&self) -> Option<IdleConnection<M::Connection>> {
+        let mut idle = self.idle.lock().unwrap();
+        idle.retain(|idle| idle.since.elapsed() < self.config.idle_timeout);
+        idle.pop()
+    }
+
+    fn pooled(
+        self: &Arc<Self>,
+        connection: M::Connection,
+        permit: OwnedSemaphorePermit,
+    ) -> PooledConnection<M> {
+        PooledConnection {
+            connection: Some(connection),
+            permit: Some(permit),
+            pool: self.clone(),
+        }
+    }
+
+    fn release(&self, connection: M::Connection, permit: OwnedSemaphorePermit) {
+        self.idle.lock().unwrap().push(IdleConnection {
+            connection,
+            permit,
+            since: Instant::now(),
+        });
+        self.returned.notify_one();
+    }
+}
+
+/// A connection checked out from a pool.
+///
+/// Dropping it resets the connection and returns it to the pool.
+pub struct PooledConnection<M: ManageConnection> {
+    connection: Option<M::Connection>,
+    permit: Option<OwnedSemaphorePermit>,
+    pool: Arc<Pool<M>>,
+}
+
+impl<M: ManageConnection> Deref for PooledConnection<M> {
+    type Target = M::Connection;
+
+    fn deref(&self) -> &M::Connection {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl<M: ManageConnection> DerefMut for PooledConnection<M> {
+    fn deref_mut(&mut self) -> &mut M::Connection {
+        self.connection.as_mut().unwrap()
+    }
+}
+
+impl<M: ManageConnection> Drop for PooledConnection<M> {
+    fn drop(&mut self) {
+        let (Some(mut connection), Some(permit)) = (self.connection.take(), self.permit.take())
+        else {
+            return;
+        };
+        // Resetting is async, so it happens in the background. Without a runtime the
+        // connection is simply closed.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let pool = self.pool.clone();
+        runtime.spawn(async move {
+            match M::reset(&mut connection).await {
+                Ok(()) => pool.release(connection, permit),
+                Err(e) => {
+                    tracing::debug!("Discarding pooled connection that failed to reset: {e:?}")
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingConnection {
+        id: usize,
+        healthy: Arc<AtomicBool>,
+    }
+
+    struct CountingManager;
+
+    #[async_trait]
+    impl ManageConnection for CountingManager {
+        type Connection = CountingConnection;
+
+        async fn connect(_address: &str) -> anyhow::Result<CountingConnection> {
+            Ok(CountingConnection {
+                id: CONNECTIONS.fetch_add(1, Ordering::SeqCst),
+                healthy: Arc::new(AtomicBool::new(true)),
+            })
+        }
+
+        async fn is_healthy(connection: &mut CountingConnection) -> bool {
+            connection.healthy.load(Ordering::SeqCst)
+        }
+
+        async fn reset(_connection: &mut CountingConnection) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn pools(max_size: usize) -> Pools<CountingManager> {
+        Pools::new(PoolConfig {
+            max_size,
+            checkout_timeout: Duration::from_millis(100),
+            ..Default::default()
+        })
+    }
+
+    async fn settle() {
+        // Let the background reset of returned connections run.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    #[tokio::test]
+    async fn returned_connections_are_reused() -> anyhow::Result<()> {
+        let pools = pools(4);
+        let first = pools.get("db://a").await?.id;
+        settle().await;
+        assert_eq!(pools.get("db://a").await?.id, first);
+        settle().await;
+        assert_ne!(pools.get("db://b").await?.id, first);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkout_waits_for_full_pool() -> anyhow::Result<()> {
+        let pools = pools(1);
+        let held = pools.get("db://c").await?;
+        let err = pools.get("db://c").await.err().unwrap();
+        assert!(err.to_string().contains("timed out"), "{err}");
+
+        let id = held.id;
+        drop(held);
+        assert_eq!(pools.get("db://c").await?.id, id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unhealthy_connections_are_discarded() -> anyhow::Result<()> {
+        let pools = pools(1);
+        let held = pools.get("db://d").await?;
+        let id = held.id;
+        held.healthy.store(false, Ordering::SeqCst);
+        drop(held);
+        settle().await;
+        assert_ne!(pools.get("db://d").await?.id, id);
+        Ok(())
+    }
+}
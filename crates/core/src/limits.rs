@@ -3,11 +3,14 @@ use async_trait::async_trait;
 use wasmtime::ResourceLimiterAsync;
 
 /// Async implementation of wasmtime's `StoreLimits`: https://github.com/bytecodealliance/wasmtime/blob/main/crates/wasmtime/src/limits.rs
-/// Used to limit the memory use and table size of each Instance
+/// Used to limit the memory use, table size and instance count of each Instance
 #[derive(Default)]
 pub struct StoreLimitsAsync {
     max_memory_size: Option<usize>,
     max_table_elements: Option<u32>,
+    max_instances: Option<usize>,
+    trap_on_grow_failure: bool,
+    on_limit_exceeded: Option<Box<dyn Fn(&'static str) + Send + Sync>>,
     memory_consumed: u64,
 }
 
@@ -27,6 +30,14 @@ impl ResourceLimiterAsync for StoreLimitsAsync {
         if can_grow {
             self.memory_consumed =
                 (self.memory_consumed as i64 + (desired as i64 - current as i64)) as u64;
+        } else {
+            self.limit_exceeded("memory");
+            if self.trap_on_grow_failure {
+                anyhow::bail!(
+                    "memory limit exceeded: attempted to grow memory to {desired} bytes but the limit is {} bytes",
+                    self.max_memory_size.unwrap_or_default()
+                );
+            }
         }
         Ok(can_grow)
     }
@@ -42,8 +53,22 @@ impl ResourceLimiterAsync for StoreLimitsAsync {
         } else {
             true
         };
+        if !can_grow {
+            self.limit_exceeded("table_elements");
+            if self.trap_on_grow_failure {
+                anyhow::bail!(
+                    "table elements limit exceeded: attempted to grow table to {desired} elements but the limit is {} elements",
+                    self.max_table_elements.unwrap_or_default()
+                );
+            }
+        }
         Ok(can_grow)
     }
+
+    fn instances(&self) -> usize {
+        self.max_instances
+            .unwrap_or(wasmtime::DEFAULT_INSTANCE_LIMIT)
+    }
 }
 
 impl StoreLimitsAsync {
@@ -51,7 +76,40 @@ impl StoreLimitsAsync {
         Self {
             max_memory_size,
             max_table_elements,
-            memory_consumed: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the maximum size of each linear memory in bytes.
+    pub fn set_max_memory_size(&mut self, max_memory_size: usize) {
+        self.max_memory_size = Some(max_memory_size);
+    }
+
+    /// Sets the maximum number of elements in each table.
+    pub fn set_max_table_elements(&mut self, max_table_elements: u32) {
+        self.max_table_elements = Some(max_table_elements);
+    }
+
+    /// Sets the maximum number of (core) instances.
+    pub fn set_max_instances(&mut self, max_instances: usize) {
+        self.max_instances = Some(max_instances);
+    }
+
+    /// Sets whether exceeding a memory or table limit traps rather than
+    /// failing the grow operation.
+    pub fn set_trap_on_grow_failure(&mut self, trap_on_grow_failure: bool) {
+        self.trap_on_grow_failure = trap_on_grow_failure;
+    }
+
+    /// Sets a callback invoked with the name of the resource ("memory" or
+    /// "table_elements") whenever a grow operation exceeds its limit.
+    pub fn set_on_limit_exceeded(&mut self, f: impl Fn(&'static str) + Send + Sync + 'static) {
+        self.on_limit_exceeded = Some(Box::new(f));
+    }
+
+    fn limit_exceeded(&self, resource: &'static str) {
+        if let Some(on_limit_exceeded) = &self.on_limit_exceeded {
+            on_limit_exceeded(resource);
         }
     }
 
@@ -86,4 +144,52 @@ mod tests {
         assert!(limits.table_growing(9, 10, None).await.unwrap());
         assert!(!limits.table_growing(10, 11, None).await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_store_limits_trap_on_grow_failure() {
+        let mut limits = StoreLimitsAsync {
+            max_memory_size: Some(65536),
+            max_table_elements: Some(10),
+            trap_on_grow_failure: true,
+            ..Default::default()
+        };
+        assert!(limits.memory_growing(0, 65536, None).await.unwrap());
+        let err = limits
+            .memory_growing(65536, 131072, None)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("memory limit exceeded"),
+            "unexpected error: {err}"
+        );
+        assert_eq!(limits.memory_consumed, 65536);
+
+        let err = limits.table_growing(10, 11, None).await.unwrap_err();
+        assert!(
+            err.to_string().contains("table elements limit exceeded"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_limits_on_limit_exceeded() {
+        let exceeded = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let mut limits = StoreLimitsAsync::new(Some(65536), Some(10));
+        limits.set_on_limit_exceeded({
+            let exceeded = exceeded.clone();
+            move |resource| exceeded.lock().unwrap().push(resource)
+        });
+        assert!(limits.memory_growing(0, 65536, None).await.unwrap());
+        assert!(!limits.memory_growing(65536, 131072, None).await.unwrap());
+        assert!(!limits.table_growing(10, 11, None).await.unwrap());
+        assert_eq!(*exceeded.lock().unwrap(), ["memory", "table_elements"]);
+    }
+
+    #[test]
+    fn test_store_limits_instances() {
+        let mut limits = StoreLimitsAsync::default();
+        assert_eq!(limits.instances(), wasmtime::DEFAULT_INSTANCE_LIMIT);
+        limits.set_max_instances(5);
+        assert_eq!(limits.instances(), 5);
+    }
 }
@@ -83,6 +83,7 @@ pub struct StoreBuilder {
     engine: WasmtimeEngine,
     epoch_tick_interval: Duration,
     store_limits: StoreLimitsAsync,
+    deadline: Option<Instant>,
 }
 
 impl StoreBuilder {
@@ -92,6 +93,7 @@ impl StoreBuilder {
             engine,
             epoch_tick_interval,
             store_limits: StoreLimitsAsync::default(),
+            deadline: None,
         }
     }
 
@@ -100,7 +102,47 @@ impl StoreBuilder {
     /// See [`wasmtime::ResourceLimiter::memory_growing`] (`maximum`) for
     /// details on how this limit is enforced.
     pub fn max_memory_size(&mut self, max_memory_size: usize) {
-        self.store_limits = StoreLimitsAsync::new(Some(max_memory_size), None);
+        self.store_limits.set_max_memory_size(max_memory_size);
+    }
+
+    /// Sets a maximum number of elements for each table.
+    ///
+    /// See [`wasmtime::ResourceLimiter::table_growing`] for details on how
+    /// this limit is enforced.
+    pub fn max_table_elements(&mut self, max_table_elements: u32) {
+        self.store_limits.set_max_table_elements(max_table_elements);
+    }
+
+    /// Sets a maximum number of instances.
+    ///
+    /// See [`wasmtime::ResourceLimiter::instances`] for details on how this
+    /// limit is enforced.
+    pub fn max_instances(&mut self, max_instances: usize) {
+        self.store_limits.set_max_instances(max_instances);
+    }
+
+    /// Sets whether exceeding the memory or table limits traps execution.
+    ///
+    /// By default a failed grow is reported to the guest (e.g. `memory.grow`
+    /// returns -1), which it may or may not handle gracefully. When enabled,
+    /// execution instead traps with an error describing the exceeded limit.
+    pub fn trap_on_grow_failure(&mut self, trap_on_grow_failure: bool) {
+        self.store_limits
+            .set_trap_on_grow_failure(trap_on_grow_failure);
+    }
+
+    /// Sets a callback invoked with the name of the resource ("memory" or
+    /// "table_elements") whenever the guest exceeds a memory or table limit,
+    /// such as to report it in metrics.
+    pub fn on_limit_exceeded(&mut self, f: impl Fn(&'static str) + Send + Sync + 'static) {
+        self.store_limits.set_on_limit_exceeded(f);
+    }
+
+    /// Sets the execution deadline for the built [`Store`].
+    ///
+    /// See [`Store::set_deadline`].
+    pub fn deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
     }
 
     /// Builds a [`Store`] from this builder with given host state data.
@@ -119,10 +161,14 @@ impl StoreBuilder {
         // forever" for any plausible tick interval.
         inner.set_epoch_deadline(u64::MAX / 2);
 
-        Ok(Store {
+        let mut store = Store {
             inner,
             epoch_tick_interval: self.epoch_tick_interval,
-        })
+        };
+        if let Some(deadline) = self.deadline {
+            store.set_deadline(deadline);
+        }
+        Ok(store)
     }
 }
 
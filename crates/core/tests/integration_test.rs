@@ -76,6 +76,21 @@ async fn test_set_deadline_violated() {
     assert_eq!(trap, Trap::Interrupt);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_builder_deadline_violated() {
+    let err = run_test(
+        ["sleep", "100"],
+        |store_builder| {
+            store_builder.deadline(Instant::now() + Duration::from_millis(10));
+        },
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    let trap = err.downcast::<Trap>().expect("trap");
+    assert_eq!(trap, Trap::Interrupt);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_panic() {
     let err = run_test(["panic"], |_| {}, |_| {}).await.unwrap_err();
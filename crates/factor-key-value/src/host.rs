@@ -4,11 +4,18 @@ use spin_core::{async_trait, wasmtime::component::Resource};
 use spin_resource_table::Table;
 use spin_world::v2::key_value;
 use spin_world::wasi::keyvalue as wasi_keyvalue;
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 use tracing::{instrument, Level};
 
 const DEFAULT_STORE_TABLE_CAPACITY: u32 = 256;
 
+/// The number of keys a [`Store`] should aim to return per [`Store::list_keys`] page.
+///
+/// Backends may return fewer keys (e.g. when a prefix filter is applied after
+/// the backend has selected a page of items), or more if the backend does not
+/// support precise page sizes.
+pub const LIST_KEYS_PAGE_SIZE: usize = 1000;
+
 pub use key_value::Error;
 
 #[async_trait]
@@ -29,9 +36,25 @@ pub trait StoreManager: Sync + Send {
 pub trait Store: Sync + Send {
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error>;
+    /// Sets `value` for `key`, such that the entry expires once `ttl` has elapsed.
+    ///
+    /// An expired entry must behave as if it had been deleted, even if the
+    /// backend has not yet physically removed it. A subsequent [`Store::set`]
+    /// of the same key clears the expiry.
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error>;
     async fn delete(&self, key: &str) -> Result<(), Error>;
     async fn exists(&self, key: &str) -> Result<bool, Error>;
     async fn get_keys(&self) -> Result<Vec<String>, Error>;
+    /// Lists one page of keys, optionally restricted to those starting with `prefix`.
+    ///
+    /// `cursor` should be `None` to fetch the first page, or the cursor of the
+    /// previously returned [`KeyPage`] (listed with the same `prefix`) to
+    /// fetch the next page.
+    async fn list_keys(
+        &self,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<KeyPage, Error>;
     async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error>;
     async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error>;
     async fn delete_many(&self, keys: Vec<String>) -> Result<(), Error>;
@@ -40,6 +63,15 @@ pub trait Store: Sync + Send {
         -> Result<Arc<dyn Cas>, Error>;
 }
 
+/// A page of keys returned by [`Store::list_keys`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyPage {
+    /// The keys in this page.
+    pub keys: Vec<String>,
+    /// An opaque cursor for fetching the next page, or `None` if there are no more keys.
+    pub cursor: Option<String>,
+}
+
 pub struct KeyValueDispatch {
     allowed_stores: HashSet<String>,
     manager: Arc<dyn StoreManager>,
@@ -177,6 +209,27 @@ impl key_value::HostStore for KeyValueDispatch {
     }
 }
 
+#[async_trait]
+impl spin_world::spin::key_value::expiry::Host for KeyValueDispatch {
+    #[instrument(name = "spin_key_value.set_with_ttl", skip(self, store, key, value), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn set_with_ttl(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key: String,
+        value: Vec<u8>,
+        ttl_seconds: u32,
+    ) -> Result<Result<(), Error>> {
+        let store = self.get_store(store)?;
+        if ttl_seconds == 0 {
+            return Ok(Err(Error::Other(
+                "ttl must be greater than zero".to_string(),
+            )));
+        }
+        let ttl = Duration::from_secs(ttl_seconds.into());
+        Ok(store.set_with_ttl(&key, &value, ttl).await)
+    }
+}
+
 fn to_wasi_err(e: Error) -> wasi_keyvalue::store::Error {
     match e {
         Error::AccessDenied => wasi_keyvalue::store::Error::AccessDenied,
@@ -256,16 +309,9 @@ impl wasi_keyvalue::store::HostBucket for KeyValueDispatch {
         self_: Resource<Bucket>,
         cursor: Option<String>,
     ) -> Result<wasi_keyvalue::store::KeyResponse, wasi_keyvalue::store::Error> {
-        match cursor {
-            Some(_) => Err(wasi_keyvalue::store::Error::Other(
-                "list_keys: cursor not supported".to_owned(),
-            )),
-            None => {
-                let store = self.get_store_wasi(self_)?;
-                let keys = store.get_keys().await.map_err(to_wasi_err)?;
-                Ok(wasi_keyvalue::store::KeyResponse { keys, cursor: None })
-            }
-        }
+        let store = self.get_store_wasi(self_)?;
+        let KeyPage { keys, cursor } = store.list_keys(None, cursor).await.map_err(to_wasi_err)?;
+        Ok(wasi_keyvalue::store::KeyResponse { keys, cursor })
     }
 
     async fn drop(&mut self, rep: Resource<Bucket>) -> anyhow::Result<()> {
@@ -15,7 +15,10 @@ use spin_locked_app::MetadataKey;
 
 /// Metadata key for key-value stores.
 pub const KEY_VALUE_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("key_value_stores");
-pub use host::{log_cas_error, log_error, Error, KeyValueDispatch, Store, StoreManager};
+pub use host::{
+    log_cas_error, log_error, Error, KeyPage, KeyValueDispatch, Store, StoreManager,
+    LIST_KEYS_PAGE_SIZE,
+};
 pub use runtime_config::RuntimeConfig;
 use spin_core::async_trait;
 pub use util::{CachingStoreManager, DelegatingStoreManager};
@@ -41,6 +44,7 @@ impl Factor for KeyValueFactor {
     fn init<T: Send + 'static>(&mut self, mut ctx: InitContext<T, Self>) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v1::key_value::add_to_linker)?;
         ctx.link_bindings(spin_world::v2::key_value::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::key_value::expiry::add_to_linker)?;
         ctx.link_bindings(spin_world::wasi::keyvalue::store::add_to_linker)?;
         ctx.link_bindings(spin_world::wasi::keyvalue::batch::add_to_linker)?;
         ctx.link_bindings(spin_world::wasi::keyvalue::atomics::add_to_linker)?;
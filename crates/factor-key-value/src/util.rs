@@ -1,4 +1,4 @@
-use crate::{Cas, Error, Store, StoreManager, SwapError};
+use crate::{Cas, Error, KeyPage, Store, StoreManager, SwapError};
 use lru::LruCache;
 use spin_core::async_trait;
 use std::{
@@ -6,6 +6,7 @@ use std::{
     future::Future,
     num::NonZeroUsize,
     sync::Arc,
+    time::{Duration, Instant},
 };
 use tokio::{
     sync::Mutex as AsyncMutex,
@@ -117,7 +118,7 @@ impl<T: StoreManager> StoreManager for CachingStoreManager<T> {
 }
 
 struct CachingStoreState {
-    cache: LruCache<String, Option<Vec<u8>>>,
+    cache: LruCache<String, CachedValue>,
     previous_task: Option<JoinHandle<Result<(), Error>>>,
 }
 
@@ -149,6 +150,32 @@ impl CachingStoreState {
     }
 }
 
+/// A value (or known absence of a value) held by a [`CachingStore`]'s cache.
+#[derive(Clone)]
+struct CachedValue {
+    value: Option<Vec<u8>>,
+    expires_at: Option<Instant>,
+}
+
+impl CachedValue {
+    /// Returns the value, or `None` if there is no value or it has expired.
+    fn current(&self) -> Option<&Vec<u8>> {
+        match self.expires_at {
+            Some(expires_at) if expires_at <= Instant::now() => None,
+            _ => self.value.as_ref(),
+        }
+    }
+}
+
+impl From<Option<Vec<u8>>> for CachedValue {
+    fn from(value: Option<Vec<u8>>) -> Self {
+        Self {
+            value,
+            expires_at: None,
+        }
+    }
+}
+
 struct CachingStore {
     inner: Arc<dyn Store>,
     state: Arc<AsyncMutex<CachingStoreState>>,
@@ -161,8 +188,8 @@ impl Store for CachingStore {
 
         let mut state = self.state.lock().await;
 
-        if let Some(value) = state.cache.get(key).cloned() {
-            return Ok(value);
+        if let Some(cached) = state.cache.get(key) {
+            return Ok(cached.current().cloned());
         }
 
         // Flush any outstanding writes prior to reading from store.  This is necessary because we need to
@@ -172,7 +199,7 @@ impl Store for CachingStore {
 
         let value = self.inner.get(key).await?;
 
-        state.cache.put(key.to_owned(), value.clone());
+        state.cache.put(key.to_owned(), value.clone().into());
 
         Ok(value)
     }
@@ -182,7 +209,9 @@ impl Store for CachingStore {
 
         let mut state = self.state.lock().await;
 
-        state.cache.put(key.to_owned(), Some(value.to_owned()));
+        state
+            .cache
+            .put(key.to_owned(), Some(value.to_owned()).into());
 
         let inner = self.inner.clone();
         let key = key.to_owned();
@@ -192,12 +221,33 @@ impl Store for CachingStore {
         Ok(())
     }
 
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // As with `set`, except that the cached value expires along with the entry in the backing store.
+
+        let mut state = self.state.lock().await;
+
+        state.cache.put(
+            key.to_owned(),
+            CachedValue {
+                value: Some(value.to_owned()),
+                expires_at: Instant::now().checked_add(ttl),
+            },
+        );
+
+        let inner = self.inner.clone();
+        let key = key.to_owned();
+        let value = value.to_owned();
+        state.spawn(async move { inner.set_with_ttl(&key, &value, ttl).await });
+
+        Ok(())
+    }
+
     async fn delete(&self, key: &str) -> Result<(), Error> {
         // Update the cache and spawn a task to update the backing store asynchronously.
 
         let mut state = self.state.lock().await;
 
-        state.cache.put(key.to_owned(), None);
+        state.cache.put(key.to_owned(), None.into());
 
         let inner = self.inner.clone();
         let key = key.to_owned();
@@ -232,20 +282,36 @@ impl Store for CachingStore {
                 state
                     .cache
                     .peek(k)
-                    .map(|v| v.as_ref().is_some())
+                    .map(|v| v.current().is_some())
                     .unwrap_or(true)
             })
             .chain(
                 state
                     .cache
                     .iter()
-                    .filter_map(|(k, v)| v.as_ref().map(|_| k.to_owned())),
+                    .filter_map(|(k, v)| v.current().map(|_| k.to_owned())),
             )
             .collect::<HashSet<_>>()
             .into_iter()
             .collect())
     }
 
+    async fn list_keys(
+        &self,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<KeyPage, Error> {
+        // Unlike `get_keys`, pages can't be reconciled with the cache without listing the whole store, so
+        // instead we flush any outstanding writes and list straight from the backing store, which then
+        // reflects every write made through this cache.
+
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        self.inner.list_keys(prefix, cursor).await
+    }
+
     async fn get_many(
         &self,
         keys: Vec<String>,
@@ -259,9 +325,9 @@ impl Store for CachingStore {
         let mut found: Vec<(String, Option<Vec<u8>>)> = Vec::new();
         let mut not_found: Vec<String> = Vec::new();
         for key in keys {
-            match state.cache.get(key.as_str()) {
-                Some(Some(value)) => found.push((key, Some(value.clone()))),
-                _ => not_found.push(key),
+            match state.cache.get(key.as_str()).and_then(CachedValue::current) {
+                Some(value) => found.push((key, Some(value.clone()))),
+                None => not_found.push(key),
             }
         }
 
@@ -269,7 +335,7 @@ impl Store for CachingStore {
             let keys_and_values = self.inner.get_many(not_found).await?;
             for (key, value) in keys_and_values {
                 found.push((key.clone(), value.clone()));
-                state.cache.put(key, value);
+                state.cache.put(key, value.into());
             }
         }
 
@@ -280,7 +346,7 @@ impl Store for CachingStore {
         let mut state = self.state.lock().await;
 
         for (key, value) in key_values.clone() {
-            state.cache.put(key, Some(value));
+            state.cache.put(key, Some(value).into());
         }
 
         self.inner.set_many(key_values).await
@@ -290,7 +356,7 @@ impl Store for CachingStore {
         let mut state = self.state.lock().await;
 
         for key in keys.clone() {
-            state.cache.put(key, None);
+            state.cache.put(key, None.into());
         }
 
         self.inner.delete_many(keys).await
@@ -301,7 +367,7 @@ impl Store for CachingStore {
         let counter = self.inner.increment(key.clone(), delta).await?;
         state
             .cache
-            .put(key, Some(i64::to_le_bytes(counter).to_vec()));
+            .put(key, Some(i64::to_le_bytes(counter).to_vec()).into());
         Ok(counter)
     }
 
@@ -335,7 +401,7 @@ impl Cas for CompareAndSwap {
         let res = self.inner_cas.current().await;
         match res.clone() {
             Ok(value) => {
-                state.cache.put(self.key.clone(), value.clone());
+                state.cache.put(self.key.clone(), value.clone().into());
                 state.flush().await?;
                 Ok(value)
             }
@@ -353,7 +419,7 @@ impl Cas for CompareAndSwap {
         let res = self.inner_cas.swap(value.clone()).await;
         match res {
             Ok(()) => {
-                state.cache.put(self.key.clone(), Some(value));
+                state.cache.put(self.key.clone(), Some(value).into());
                 state
                     .flush()
                     .await
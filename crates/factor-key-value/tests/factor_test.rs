@@ -1,10 +1,10 @@
 use anyhow::bail;
 use spin_core::async_trait;
-use spin_factor_key_value::{Cas, KeyValueFactor, RuntimeConfig, Store, StoreManager};
+use spin_factor_key_value::{Cas, KeyPage, KeyValueFactor, RuntimeConfig, Store, StoreManager};
 use spin_factors::RuntimeFactors;
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::v2::key_value::{Error, HostStore};
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 #[derive(RuntimeFactors)]
 struct TestFactors {
@@ -129,6 +129,10 @@ impl Store for MockStore {
         let _ = (key, value);
         todo!()
     }
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let _ = (key, value, ttl);
+        todo!()
+    }
     async fn delete(&self, key: &str) -> Result<(), Error> {
         let _ = key;
         todo!()
@@ -141,6 +145,15 @@ impl Store for MockStore {
         todo!()
     }
 
+    async fn list_keys(
+        &self,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<KeyPage, Error> {
+        let _ = (prefix, cursor);
+        todo!()
+    }
+
     async fn get_many(
         &self,
         keys: Vec<String>,
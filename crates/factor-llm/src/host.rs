@@ -18,22 +18,17 @@ impl v2::Host for InstanceState {
         if !self.allowed_models.contains(&model) {
             return Err(access_denied_error(&model));
         }
-        let mut engine = self.engine.lock().await;
-        tracing::Span::current().record("llm.backend", engine.summary());
-        engine
-            .infer(
-                model,
-                prompt,
-                params.unwrap_or(v2::InferencingParams {
-                    max_tokens: 100,
-                    repeat_penalty: 1.1,
-                    repeat_penalty_last_n_token_count: 64,
-                    temperature: 0.8,
-                    top_k: 40,
-                    top_p: 0.9,
-                }),
-            )
-            .await
+        let params = params.unwrap_or_else(default_params);
+        let mut result = Err(v2::Error::ModelNotSupported);
+        for engine in self.engines.for_model(&model) {
+            let mut engine = engine.lock().await;
+            tracing::Span::current().record("llm.backend", engine.summary());
+            result = engine.infer(model.clone(), prompt.clone(), params).await;
+            if !matches!(result, Err(v2::Error::ModelNotSupported)) {
+                break;
+            }
+        }
+        result
     }
 
     #[instrument(name = "spin_llm.generate_embeddings", skip(self, data), err(level = Level::INFO), fields(otel.kind = "client", llm.backend = Empty))]
@@ -45,9 +40,18 @@ impl v2::Host for InstanceState {
         if !self.allowed_models.contains(&model) {
             return Err(access_denied_error(&model));
         }
-        let mut engine = self.engine.lock().await;
-        tracing::Span::current().record("llm.backend", engine.summary());
-        engine.generate_embeddings(model, data).await
+        let mut result = Err(v2::Error::ModelNotSupported);
+        for engine in self.engines.for_model(&model) {
+            let mut engine = engine.lock().await;
+            tracing::Span::current().record("llm.backend", engine.summary());
+            result = engine
+                .generate_embeddings(model.clone(), data.clone())
+                .await;
+            if !matches!(result, Err(v2::Error::ModelNotSupported)) {
+                break;
+            }
+        }
+        result
     }
 
     fn convert_error(&mut self, error: v2::Error) -> anyhow::Result<v2::Error> {
@@ -85,7 +89,19 @@ impl v1::Host for InstanceState {
     }
 }
 
-fn access_denied_error(model: &str) -> v2::Error {
+/// The inferencing parameters used when the guest does not provide any.
+pub(crate) fn default_params() -> v2::InferencingParams {
+    v2::InferencingParams {
+        max_tokens: 100,
+        repeat_penalty: 1.1,
+        repeat_penalty_last_n_token_count: 64,
+        temperature: 0.8,
+        top_k: 40,
+        top_p: 0.9,
+    }
+}
+
+pub(crate) fn access_denied_error(model: &str) -> v2::Error {
     v2::Error::InvalidInput(format!(
         "The component does not have access to use '{model}'. To give the component access, add '{model}' to the 'ai_models' key for the component in your spin.toml manifest"
     ))
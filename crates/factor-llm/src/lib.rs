@@ -1,5 +1,6 @@
 mod host;
 pub mod spin;
+mod streaming;
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -42,6 +43,7 @@ impl Factor for LlmFactor {
     ) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v1::llm::add_to_linker)?;
         ctx.link_bindings(spin_world::v2::llm::add_to_linker)?;
+        streaming::add_to_linker(&mut ctx)?;
         Ok(())
     }
 
@@ -49,27 +51,30 @@ impl Factor for LlmFactor {
         &self,
         mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
+        let engines = ctx
+            .take_runtime_config()
+            .map(|c| c.engines)
+            .unwrap_or_else(|| LlmEngines::single(self.default_engine_creator.create()));
         let component_allowed_models = ctx
             .app()
             .components()
             .map(|component| {
-                Ok((
-                    component.id().to_string(),
-                    component
-                        .get_metadata(ALLOWED_MODELS_KEY)?
-                        .unwrap_or_default()
-                        .into_iter()
-                        .collect::<HashSet<_>>()
-                        .into(),
-                ))
+                let allowed_models = component
+                    .get_metadata(ALLOWED_MODELS_KEY)?
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect::<HashSet<_>>();
+                if let Some(model) = allowed_models.iter().find(|m| !engines.serves(m)) {
+                    anyhow::bail!(
+                        "Component '{}' has '{model}' in 'ai_models', but no engine in the runtime config is configured for it",
+                        component.id()
+                    );
+                }
+                Ok((component.id().to_string(), allowed_models.into()))
             })
             .collect::<anyhow::Result<_>>()?;
-        let engine = ctx
-            .take_runtime_config()
-            .map(|c| c.engine)
-            .unwrap_or_else(|| self.default_engine_creator.create());
         Ok(AppState {
-            engine,
+            engines: Arc::new(engines),
             component_allowed_models,
         })
     }
@@ -84,30 +89,91 @@ impl Factor for LlmFactor {
             .get(ctx.app_component().id())
             .cloned()
             .unwrap_or_default();
-        let engine = ctx.app_state().engine.clone();
+        let engines = ctx.app_state().engines.clone();
 
         Ok(InstanceState {
-            engine,
+            engines,
             allowed_models,
+            streams: spin_resource_table::Table::new(1024),
         })
     }
 }
 
 /// The application state for the LLM factor.
 pub struct AppState {
-    engine: Arc<Mutex<dyn LlmEngine>>,
+    engines: Arc<LlmEngines>,
     component_allowed_models: HashMap<String, Arc<HashSet<String>>>,
 }
 
 /// The instance state for the LLM factor.
 pub struct InstanceState {
-    engine: Arc<Mutex<dyn LlmEngine>>,
+    engines: Arc<LlmEngines>,
     pub allowed_models: Arc<HashSet<String>>,
+    streams: spin_resource_table::Table<streaming::InferencingStream>,
 }
 
 /// The runtime configuration for the LLM factor.
 pub struct RuntimeConfig {
-    engine: Arc<Mutex<dyn LlmEngine>>,
+    engines: LlmEngines,
+}
+
+impl RuntimeConfig {
+    /// Creates a runtime configuration that serves models with the given engines.
+    pub fn new(engines: LlmEngines) -> Self {
+        Self { engines }
+    }
+}
+
+/// The engines that serve models.
+///
+/// A model with its own engine is only served by that engine. Any other model is served by the
+/// fallback engines, tried in order: an engine is skipped only if it reports
+/// `ModelNotSupported` for the model. Any other error, including a failure to reach a remote
+/// server, is returned without trying the remaining engines.
+#[derive(Clone)]
+pub struct LlmEngines {
+    models: HashMap<String, Arc<Mutex<dyn LlmEngine>>>,
+    fallback: Vec<Arc<Mutex<dyn LlmEngine>>>,
+}
+
+impl LlmEngines {
+    /// Creates engines that serve the models in `models` with their engines and any other model
+    /// with the `fallback` engines, tried in order.
+    pub fn new(
+        models: HashMap<String, Arc<Mutex<dyn LlmEngine>>>,
+        fallback: Vec<Arc<Mutex<dyn LlmEngine>>>,
+    ) -> Self {
+        Self { models, fallback }
+    }
+
+    /// Creates engines that serve every model with one engine.
+    pub fn single(engine: Arc<Mutex<dyn LlmEngine>>) -> Self {
+        Self {
+            models: Default::default(),
+            fallback: vec![engine],
+        }
+    }
+
+    /// Returns whether any engine may serve the given model.
+    pub fn serves(&self, model: &str) -> bool {
+        self.models.contains_key(model)
+            || self.fallback.iter().any(|engine| {
+                // Engines are only locked while serving requests, so an engine that is busy
+                // is assumed to serve the model.
+                engine
+                    .try_lock()
+                    .map_or(true, |engine| engine.may_serve(model))
+            })
+    }
+
+    /// The engines to try for the given model, in order. The next engine should only be tried
+    /// if the previous one reports that it does not support the model.
+    fn for_model(&self, model: &str) -> Vec<Arc<Mutex<dyn LlmEngine>>> {
+        match self.models.get(model) {
+            Some(engine) => vec![engine.clone()],
+            None => self.fallback.clone(),
+        }
+    }
 }
 
 impl SelfInstanceBuilder for InstanceState {}
@@ -122,12 +188,36 @@ pub trait LlmEngine: Send + Sync {
         params: v2::InferencingParams,
     ) -> Result<v2::InferencingResult, v2::Error>;
 
+    /// Performs inferencing, sending the generated text to `tokens` as it is generated.
+    ///
+    /// Engines that cannot stream send the whole text once inferencing is complete.
+    async fn infer_stream(
+        &mut self,
+        model: v1::InferencingModel,
+        prompt: String,
+        params: v2::InferencingParams,
+        tokens: TokenSender,
+    ) -> Result<v2::InferencingUsage, v2::Error> {
+        let result = self.infer(model, prompt, params).await?;
+        // The reader may already have gone away, which is not an inferencing error.
+        let _ = tokens.send(result.text);
+        Ok(result.usage)
+    }
+
     async fn generate_embeddings(
         &mut self,
         model: v2::EmbeddingModel,
         data: Vec<String>,
     ) -> Result<v2::EmbeddingsResult, v2::Error>;
 
+    /// Returns whether the engine may serve the given model.
+    ///
+    /// Engines that can only find out by trying, such as those for remote servers, return true.
+    fn may_serve(&self, model: &str) -> bool {
+        let _ = model;
+        true
+    }
+
     /// A human-readable summary of the given engine's configuration
     ///
     /// Example: "local model"
@@ -136,6 +226,9 @@ pub trait LlmEngine: Send + Sync {
     }
 }
 
+/// Sends text generated by a streaming inference as it is generated.
+pub type TokenSender = tokio::sync::mpsc::UnboundedSender<String>;
+
 /// A creator for an LLM engine.
 pub trait LlmEngineCreator: Send + Sync {
     fn create(&self) -> Arc<Mutex<dyn LlmEngine>>;
@@ -1,15 +1,16 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use spin_factors::runtime_config::toml::GetTomlValue;
-use spin_llm_remote_http::RemoteHttpLlmEngine;
+use spin_llm_remote_http::{OpenAiApi, OpenAiEndpoint, OpenAiLlmEngine, RemoteHttpLlmEngine};
 use spin_world::async_trait;
 use spin_world::v1::llm::{self as v1};
 use spin_world::v2::llm::{self as v2};
 use tokio::sync::Mutex;
 use url::Url;
 
-use crate::{LlmEngine, LlmEngineCreator, RuntimeConfig};
+use crate::{LlmEngine, LlmEngineCreator, LlmEngines, RuntimeConfig, TokenSender};
 
 #[cfg(feature = "llm")]
 mod local {
@@ -27,6 +28,17 @@ mod local {
             self.infer(model, prompt, params).await
         }
 
+        async fn infer_stream(
+            &mut self,
+            model: v2::InferencingModel,
+            prompt: String,
+            params: v2::InferencingParams,
+            tokens: TokenSender,
+        ) -> Result<v2::InferencingUsage, v2::Error> {
+            self.infer_stream(model, prompt, params, move |text| tokens.send(text).is_ok())
+                .await
+        }
+
         async fn generate_embeddings(
             &mut self,
             model: v2::EmbeddingModel,
@@ -75,6 +87,18 @@ impl LlmEngine for RemoteHttpLlmEngine {
         self.infer(model, prompt, params).await
     }
 
+    async fn infer_stream(
+        &mut self,
+        model: v1::InferencingModel,
+        prompt: String,
+        params: v2::InferencingParams,
+        tokens: TokenSender,
+    ) -> Result<v2::InferencingUsage, v2::Error> {
+        spin_telemetry::monotonic_counter!(spin.llm_infer = 1, model_name = model);
+        self.infer_stream(model, prompt, params, move |text| tokens.send(text).is_ok())
+            .await
+    }
+
     async fn generate_embeddings(
         &mut self,
         model: v2::EmbeddingModel,
@@ -88,6 +112,47 @@ impl LlmEngine for RemoteHttpLlmEngine {
     }
 }
 
+#[async_trait]
+impl LlmEngine for OpenAiLlmEngine {
+    async fn infer(
+        &mut self,
+        model: v1::InferencingModel,
+        prompt: String,
+        params: v2::InferencingParams,
+    ) -> Result<v2::InferencingResult, v2::Error> {
+        spin_telemetry::monotonic_counter!(spin.llm_infer = 1, model_name = model);
+        self.infer(model, prompt, params).await
+    }
+
+    async fn infer_stream(
+        &mut self,
+        model: v1::InferencingModel,
+        prompt: String,
+        params: v2::InferencingParams,
+        tokens: TokenSender,
+    ) -> Result<v2::InferencingUsage, v2::Error> {
+        spin_telemetry::monotonic_counter!(spin.llm_infer = 1, model_name = model);
+        self.infer_stream(model, prompt, params, move |text| tokens.send(text).is_ok())
+            .await
+    }
+
+    async fn generate_embeddings(
+        &mut self,
+        model: v2::EmbeddingModel,
+        data: Vec<String>,
+    ) -> Result<v2::EmbeddingsResult, v2::Error> {
+        self.generate_embeddings(model, data).await
+    }
+
+    fn may_serve(&self, model: &str) -> bool {
+        self.serves(model)
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(self.summary())
+    }
+}
+
 pub fn runtime_config_from_toml(
     table: &impl GetTomlValue,
     state_dir: Option<PathBuf>,
@@ -95,11 +160,61 @@ pub fn runtime_config_from_toml(
     let Some(value) = table.get("llm_compute") else {
         return Ok(None);
     };
-    let config: LlmCompute = value.clone().try_into()?;
+    // A `type` configures a single engine for every model.
+    let engines = if value.get("type").is_some() {
+        let config: LlmCompute = value.clone().try_into()?;
+        LlmEngines::single(config.into_engine(state_dir)?)
+    } else {
+        let config: RoutedLlmCompute = value.clone().try_into()?;
+        config.into_engines(state_dir)?
+    };
 
-    Ok(Some(RuntimeConfig {
-        engine: config.into_engine(state_dir)?,
-    }))
+    Ok(Some(RuntimeConfig { engines }))
+}
+
+/// Configuration of an engine per model, with fallback engines for other models:
+///
+/// ```toml
+/// [llm_compute.routes.all-minilm-l6-v2]
+/// type = "spin"
+///
+/// [llm_compute.routes.llama2-chat]
+/// type = "remote_http"
+/// url = "http://llm.example.com"
+/// auth_token = "..."
+///
+/// [[llm_compute.fallback]]
+/// type = "open_ai"
+/// url = "http://vllm:8000"
+/// ```
+///
+/// A model that is not listed in `routes` is served by the first `fallback` engine that
+/// supports it. A fallback engine is skipped if it reports that it does not support the model,
+/// which remote servers do by responding with 404 Not Found. Any other error is returned to the
+/// guest.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutedLlmCompute {
+    #[serde(default)]
+    routes: HashMap<String, LlmCompute>,
+    #[serde(default)]
+    fallback: Vec<LlmCompute>,
+}
+
+impl RoutedLlmCompute {
+    fn into_engines(self, state_dir: Option<PathBuf>) -> anyhow::Result<LlmEngines> {
+        let models = self
+            .routes
+            .into_iter()
+            .map(|(model, config)| Ok((model, config.into_engine(state_dir.clone())?)))
+            .collect::<anyhow::Result<_>>()?;
+        let fallback = self
+            .fallback
+            .into_iter()
+            .map(|config| config.into_engine(state_dir.clone()))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(LlmEngines::new(models, fallback))
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -107,6 +222,7 @@ pub fn runtime_config_from_toml(
 pub enum LlmCompute {
     Spin,
     RemoteHttp(RemoteHttpCompute),
+    OpenAi(OpenAiCompute),
 }
 
 impl LlmCompute {
@@ -123,6 +239,7 @@ impl LlmCompute {
                 config.url,
                 config.auth_token,
             ))),
+            LlmCompute::OpenAi(config) => Arc::new(Mutex::new(config.into_engine()?)),
         };
         Ok(engine)
     }
@@ -134,6 +251,118 @@ pub struct RemoteHttpCompute {
     auth_token: String,
 }
 
+/// Configuration for OpenAI-compatible servers such as vLLM and Ollama:
+///
+/// ```toml
+/// [llm_compute]
+/// type = "open_ai"
+/// url = "http://vllm:8000"
+/// auth_token = "..."
+/// api = "chat"
+///
+/// [llm_compute.models."all-minilm-l6-v2"]
+/// url = "http://ollama:11434"
+/// model = "all-minilm"
+/// headers = { "x-api-key" = "..." }
+/// ```
+///
+/// Models without their own entry are sent to the top-level `url`. Settings of a model's entry
+/// that are omitted are taken from the top level, except that a model with its own `url` does not
+/// inherit the top-level `auth_token` and `headers`.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenAiCompute {
+    url: Option<Url>,
+    auth_token: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    api: Option<OpenAiApi>,
+    #[serde(default)]
+    models: HashMap<String, OpenAiModelCompute>,
+}
+
+/// The configuration of a model served by an OpenAI-compatible server.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenAiModelCompute {
+    url: Option<Url>,
+    auth_token: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    api: Option<OpenAiApi>,
+    /// The name of the model on the server, if different from the name used by components.
+    model: Option<String>,
+}
+
+impl OpenAiCompute {
+    fn into_engine(self) -> anyhow::Result<OpenAiLlmEngine> {
+        anyhow::ensure!(
+            self.url.is_some() || !self.models.is_empty(),
+            "llm_compute of type 'open_ai' must have a 'url' or at least one entry in 'models'"
+        );
+        let model_endpoints = self
+            .models
+            .iter()
+            .map(|(name, model)| Ok((name.clone(), self.model_endpoint(name, model)?)))
+            .collect::<anyhow::Result<_>>()?;
+        let default_endpoint = self.url.map(|url| OpenAiEndpoint {
+            url,
+            headers: endpoint_headers(self.auth_token.as_deref(), [&self.headers]),
+            api: self.api.unwrap_or_default(),
+            model: None,
+        });
+        Ok(OpenAiLlmEngine::new(default_endpoint, model_endpoints))
+    }
+
+    /// The endpoint of a model with its own entry.
+    ///
+    /// The top-level `auth_token` and `headers` are credentials for the top-level `url`, so a
+    /// model only inherits them along with the `url`.
+    fn model_endpoint(
+        &self,
+        name: &str,
+        model: &OpenAiModelCompute,
+    ) -> anyhow::Result<OpenAiEndpoint> {
+        let (url, auth_token, headers) = match &model.url {
+            Some(url) => (url, model.auth_token.as_deref(), vec![&model.headers]),
+            None => {
+                let url = self.url.as_ref().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "llm_compute model '{name}' has no 'url' and there is no default 'url'"
+                    )
+                })?;
+                let auth_token = model.auth_token.as_deref().or(self.auth_token.as_deref());
+                (url, auth_token, vec![&self.headers, &model.headers])
+            }
+        };
+        Ok(OpenAiEndpoint {
+            url: url.clone(),
+            headers: endpoint_headers(auth_token, headers),
+            api: model.api.or(self.api).unwrap_or_default(),
+            model: model.model.clone(),
+        })
+    }
+}
+
+/// The headers of an endpoint, merged in order, with `authorization` set from the auth token if
+/// there is one.
+///
+/// Header names are case-insensitive, so they are lowercased to let later headers override
+/// earlier ones however they are spelled.
+fn endpoint_headers<'a>(
+    auth_token: Option<&str>,
+    headers: impl IntoIterator<Item = &'a HashMap<String, String>>,
+) -> Vec<(String, String)> {
+    let mut merged = HashMap::new();
+    for (name, value) in headers.into_iter().flatten() {
+        merged.insert(name.to_ascii_lowercase(), value.clone());
+    }
+    if let Some(token) = auth_token {
+        merged.insert("authorization".into(), format!("Bearer {token}"));
+    }
+    merged.into_iter().collect()
+}
+
 /// A noop engine used when the local engine feature is disabled.
 #[cfg(not(feature = "llm"))]
 mod noop {
@@ -170,3 +399,116 @@ mod noop {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_ai_compute(table: toml::Table) -> anyhow::Result<OpenAiLlmEngine> {
+        let LlmCompute::OpenAi(config) = table.try_into()? else {
+            panic!("expected open_ai compute");
+        };
+        config.into_engine()
+    }
+
+    #[test]
+    fn open_ai_compute_from_toml() {
+        let engine = open_ai_compute(toml::toml! {
+            type = "open_ai"
+            url = "http://vllm:8000"
+            auth_token = "secret"
+
+            [models.embedder]
+            url = "http://ollama:11434"
+            model = "all-minilm"
+        })
+        .unwrap();
+        assert_eq!(
+            engine.summary(),
+            "OpenAI-compatible models at http://vllm:8000/"
+        );
+
+        assert!(open_ai_compute(toml::toml! {
+            type = "open_ai"
+        })
+        .is_err());
+        assert!(open_ai_compute(toml::toml! {
+            type = "open_ai"
+            [models.embedder]
+            model = "all-minilm"
+        })
+        .is_err());
+        assert!(open_ai_compute(toml::toml! {
+            type = "open_ai"
+            url = "http://vllm:8000"
+            unknown = true
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn routed_llm_compute_from_toml() {
+        let routed = |table: toml::Table| -> anyhow::Result<LlmEngines> {
+            let config: RoutedLlmCompute = table.try_into()?;
+            config.into_engines(None)
+        };
+        let engines = routed(toml::toml! {
+            [routes.chat]
+            type = "open_ai"
+            [routes.chat.models.chat]
+            url = "http://vllm:8000"
+
+            [[fallback]]
+            type = "open_ai"
+            [fallback.models.embed]
+            url = "http://ollama:11434"
+        })
+        .unwrap();
+        assert!(engines.serves("chat"));
+        assert!(engines.serves("embed"));
+        assert!(!engines.serves("other"));
+
+        // `models` configures the models of an OpenAI-compatible engine, not routes.
+        assert!(routed(toml::toml! {
+            [models.chat]
+            type = "spin"
+        })
+        .is_err());
+    }
+
+    fn model_headers(table: toml::Table, model: &str) -> Vec<(String, String)> {
+        let LlmCompute::OpenAi(config) = table.try_into().unwrap() else {
+            panic!("expected open_ai compute");
+        };
+        let mut headers = config
+            .model_endpoint(model, &config.models[model])
+            .unwrap()
+            .headers;
+        headers.sort();
+        headers
+    }
+
+    #[test]
+    fn open_ai_credentials_are_inherited_with_url() {
+        let table = toml::toml! {
+            type = "open_ai"
+            url = "http://vllm:8000"
+            auth_token = "secret"
+            headers = { "X-Api-Key" = "top" }
+
+            [models.inherits]
+            headers = { "x-api-key" = "model" }
+
+            [models.elsewhere]
+            url = "http://ollama:11434"
+        };
+        assert_eq!(
+            model_headers(table.clone(), "inherits"),
+            [
+                ("authorization".to_string(), "Bearer secret".to_string()),
+                ("x-api-key".to_string(), "model".to_string()),
+            ]
+        );
+        assert!(model_headers(table, "elsewhere").is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+//! Streaming inferencing, which yields generated text to the guest as a WASI input stream.
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use spin_factors::wasmtime::component::{Resource, ResourceTable};
+use spin_world::spin::llm::streaming;
+use spin_world::v2::llm::{self as v2};
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver};
+use tracing::field::Empty;
+use tracing::{instrument, Instrument, Level};
+use wasmtime_wasi::{HostInputStream, InputStream, StreamError, StreamResult, Subscribe};
+
+use crate::host::{access_denied_error, default_params};
+use crate::{InstanceState, LlmFactor};
+
+pub(crate) fn add_to_linker<T: Send + 'static>(
+    ctx: &mut spin_factors::InitContext<T, LlmFactor>,
+) -> anyhow::Result<()> {
+    fn type_annotate<T, F>(f: F) -> F
+    where
+        F: Fn(&mut T) -> StreamingImpl<'_>,
+    {
+        f
+    }
+    let get_data_with_table = ctx.get_data_with_table_fn();
+    let closure = type_annotate(move |data| {
+        let (state, table) = get_data_with_table(data);
+        StreamingImpl { state, table }
+    });
+    streaming::add_to_linker_get_host(ctx.linker(), closure)
+}
+
+/// The outcome of a streaming inference, set once the engine has finished generating.
+type Outcome = Arc<Mutex<Option<Result<v2::InferencingUsage, v2::Error>>>>;
+
+/// The host state of an `inferencing-stream` resource.
+pub(crate) struct InferencingStream {
+    /// The generated text, until the guest takes it.
+    text: Option<TokenStream>,
+    outcome: Outcome,
+}
+
+pub(crate) struct StreamingImpl<'a> {
+    state: &'a mut InstanceState,
+    table: &'a mut ResourceTable,
+}
+
+impl StreamingImpl<'_> {
+    fn stream(&mut self, rep: u32) -> Result<&mut InferencingStream, v2::Error> {
+        self.state
+            .streams
+            .get_mut(rep)
+            .ok_or_else(|| v2::Error::RuntimeError("unknown inferencing stream".into()))
+    }
+}
+
+#[async_trait]
+impl streaming::Host for StreamingImpl<'_> {
+    #[instrument(name = "spin_llm.infer_stream", skip(self, prompt), err(level = Level::INFO), fields(otel.kind = "client", llm.backend = Empty))]
+    async fn infer_stream(
+        &mut self,
+        model: v2::InferencingModel,
+        prompt: String,
+        params: Option<v2::InferencingParams>,
+    ) -> Result<Resource<streaming::InferencingStream>, v2::Error> {
+        if !self.state.allowed_models.contains(&model) {
+            return Err(access_denied_error(&model));
+        }
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let outcome = Outcome::default();
+
+        let engines = self.state.engines.for_model(&model);
+        let params = params.unwrap_or_else(default_params);
+        let task_outcome = outcome.clone();
+        tokio::spawn(
+            async move {
+                let mut result = Err(v2::Error::ModelNotSupported);
+                for engine in engines {
+                    let mut engine = engine.lock().await;
+                    tracing::Span::current().record("llm.backend", engine.summary());
+                    result = engine
+                        .infer_stream(model.clone(), prompt.clone(), params, sender.clone())
+                        .await;
+                    if !matches!(result, Err(v2::Error::ModelNotSupported)) {
+                        break;
+                    }
+                }
+                *task_outcome.lock().unwrap() = Some(result);
+                // The text stream only ends once the outcome is known, so that a failure can be
+                // reported to the reader.
+                drop(sender);
+            }
+            .in_current_span(),
+        );
+
+        let text = TokenStream {
+            receiver,
+            buffer: Bytes::new(),
+            outcome: outcome.clone(),
+        };
+        let rep = self
+            .state
+            .streams
+            .push(InferencingStream {
+                text: Some(text),
+                outcome,
+            })
+            .map_err(|()| v2::Error::RuntimeError("too many inferencing streams".into()))?;
+        Ok(Resource::new_own(rep))
+    }
+}
+
+#[async_trait]
+impl streaming::HostInferencingStream for StreamingImpl<'_> {
+    async fn text(
+        &mut self,
+        stream: Resource<streaming::InferencingStream>,
+    ) -> Result<Resource<InputStream>, v2::Error> {
+        let text = self.stream(stream.rep())?.text.take().ok_or_else(|| {
+            v2::Error::InvalidInput("the text of this inferencing stream was already taken".into())
+        })?;
+        let text: InputStream = Box::new(text);
+        self.table
+            .push(text)
+            .map_err(|e| v2::Error::RuntimeError(e.to_string()))
+    }
+
+    async fn usage(
+        &mut self,
+        stream: Resource<streaming::InferencingStream>,
+    ) -> Result<Option<v2::InferencingUsage>, v2::Error> {
+        match &*self.stream(stream.rep())?.outcome.lock().unwrap() {
+            None => Ok(None),
+            Some(Ok(usage)) => Ok(Some(*usage)),
+            Some(Err(e)) => Err(e.clone()),
+        }
+    }
+
+    async fn drop(&mut self, stream: Resource<streaming::InferencingStream>) -> anyhow::Result<()> {
+        self.state.streams.remove(stream.rep());
+        Ok(())
+    }
+}
+
+/// The text generated by a streaming inference, read as it arrives from the engine.
+struct TokenStream {
+    receiver: UnboundedReceiver<String>,
+    /// Text received from the engine but not yet read.
+    buffer: Bytes,
+    outcome: Outcome,
+}
+
+impl TokenStream {
+    /// The error to report once the engine has finished sending text.
+    fn end_error(&self) -> StreamError {
+        match &*self.outcome.lock().unwrap() {
+            Some(Err(e)) => StreamError::LastOperationFailed(anyhow::anyhow!("{e:?}")),
+            _ => StreamError::Closed,
+        }
+    }
+}
+
+impl HostInputStream for TokenStream {
+    fn read(&mut self, size: usize) -> StreamResult<Bytes> {
+        if self.buffer.is_empty() {
+            match self.receiver.try_recv() {
+                Ok(text) => self.buffer = text.into(),
+                Err(TryRecvError::Empty) => return Ok(Bytes::new()),
+                Err(TryRecvError::Disconnected) => return Err(self.end_error()),
+            }
+        }
+        let len = size.min(self.buffer.len());
+        Ok(self.buffer.split_to(len))
+    }
+}
+
+#[async_trait]
+impl Subscribe for TokenStream {
+    async fn ready(&mut self) {
+        if self.buffer.is_empty() {
+            // If the channel is closed, the next read reports the end of the stream.
+            if let Some(text) = self.receiver.recv().await {
+                self.buffer = text.into();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_stream() -> (mpsc::UnboundedSender<String>, Outcome, TokenStream) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let outcome = Outcome::default();
+        let stream = TokenStream {
+            receiver,
+            buffer: Bytes::new(),
+            outcome: outcome.clone(),
+        };
+        (sender, outcome, stream)
+    }
+
+    #[tokio::test]
+    async fn token_stream_yields_text_as_it_arrives() {
+        let (sender, _outcome, mut stream) = token_stream();
+        assert!(stream.read(16).unwrap().is_empty());
+
+        sender.send("Hello".into()).unwrap();
+        sender.send(", world".into()).unwrap();
+        stream.ready().await;
+        assert_eq!(stream.read(3).unwrap(), "Hel");
+        assert_eq!(stream.read(16).unwrap(), "lo");
+        assert_eq!(stream.read(16).unwrap(), ", world");
+
+        drop(sender);
+        stream.ready().await;
+        assert!(matches!(stream.read(16), Err(StreamError::Closed)));
+    }
+
+    #[tokio::test]
+    async fn token_stream_reports_engine_failure() {
+        let (sender, outcome, mut stream) = token_stream();
+        *outcome.lock().unwrap() = Some(Err(v2::Error::RuntimeError("boom".into())));
+        drop(sender);
+        assert!(matches!(
+            stream.read(16),
+            Err(StreamError::LastOperationFailed(e)) if e.to_string().contains("boom")
+        ));
+    }
+}
@@ -1,7 +1,7 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use spin_factor_llm::{LlmEngine, LlmFactor};
+use spin_factor_llm::{LlmEngine, LlmEngines, LlmFactor, RuntimeConfig};
 use spin_factors::{anyhow, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::v1::llm::{self as v1};
@@ -73,6 +73,192 @@ async fn llm_works() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn models_are_routed_to_their_engines() -> anyhow::Result<()> {
+    let engines = LlmEngines::new(
+        HashMap::from([(
+            "chat".to_owned(),
+            NamedEngine::engine("chat-engine", &["chat"]),
+        )]),
+        vec![
+            NamedEngine::engine("first-fallback", &["embed"]),
+            NamedEngine::engine("second-fallback", &["chat", "other"]),
+        ],
+    );
+    let env = TestEnvironment::new(TestFactors {
+        llm: LlmFactor::new(|| -> Arc<Mutex<dyn LlmEngine>> { unreachable!() }),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        ai_models = ["chat", "embed", "other", "unsupported"]
+    })
+    .runtime_config(TestFactorsRuntimeConfig {
+        llm: Some(RuntimeConfig::new(engines)),
+    })?;
+    let mut state = env.build_instance_state().await?;
+
+    let served_by = |result: Result<v2::InferencingResult, v2::Error>| result.map(|r| r.text);
+    assert_eq!(
+        served_by(state.llm.infer("chat".into(), "".into(), None).await)?,
+        "chat-engine"
+    );
+    assert_eq!(
+        served_by(state.llm.infer("embed".into(), "".into(), None).await)?,
+        "first-fallback"
+    );
+    assert_eq!(
+        served_by(state.llm.infer("other".into(), "".into(), None).await)?,
+        "second-fallback"
+    );
+    assert!(matches!(
+        state.llm.infer("unsupported".into(), "".into(), None).await,
+        Err(v2::Error::ModelNotSupported)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn fallback_stops_at_other_errors() -> anyhow::Result<()> {
+    let engines = LlmEngines::new(
+        HashMap::new(),
+        vec![
+            NamedEngine::engine("first-fallback", &["chat"]),
+            Arc::new(Mutex::new(FailingEngine)),
+            NamedEngine::engine("second-fallback", &["chat", "other"]),
+        ],
+    );
+    let env = TestEnvironment::new(TestFactors {
+        llm: LlmFactor::new(|| -> Arc<Mutex<dyn LlmEngine>> { unreachable!() }),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        ai_models = ["chat", "other"]
+    })
+    .runtime_config(TestFactorsRuntimeConfig {
+        llm: Some(RuntimeConfig::new(engines)),
+    })?;
+    let mut state = env.build_instance_state().await?;
+
+    assert_eq!(
+        state.llm.infer("chat".into(), "".into(), None).await?.text,
+        "first-fallback"
+    );
+    assert!(matches!(
+        state.llm.infer("other".into(), "".into(), None).await,
+        Err(v2::Error::RuntimeError(msg)) if msg == "server unavailable"
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unserved_allowed_models_are_rejected() -> anyhow::Result<()> {
+    let engines = LlmEngines::new(
+        HashMap::from([(
+            "chat".to_owned(),
+            NamedEngine::engine("chat-engine", &["chat"]),
+        )]),
+        vec![NamedEngine::listing("embed-engine", &["embed"])],
+    );
+    let env = TestEnvironment::new(TestFactors {
+        llm: LlmFactor::new(|| -> Arc<Mutex<dyn LlmEngine>> { unreachable!() }),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        ai_models = ["chat", "embed", "other"]
+    })
+    .runtime_config(TestFactorsRuntimeConfig {
+        llm: Some(RuntimeConfig::new(engines)),
+    })?;
+    let err = env.build_instance_state().await.err().unwrap();
+    assert!(format!("{err:?}").contains("'other'"), "{err:?}");
+    Ok(())
+}
+
+/// An engine that supports some models and answers with its name.
+struct NamedEngine {
+    name: &'static str,
+    models: &'static [&'static str],
+    /// Whether the engine knows which models it supports before it is asked to serve them.
+    lists_models: bool,
+}
+
+impl NamedEngine {
+    fn engine(name: &'static str, models: &'static [&'static str]) -> Arc<Mutex<dyn LlmEngine>> {
+        Arc::new(Mutex::new(NamedEngine {
+            name,
+            models,
+            lists_models: false,
+        }))
+    }
+
+    fn listing(name: &'static str, models: &'static [&'static str]) -> Arc<Mutex<dyn LlmEngine>> {
+        Arc::new(Mutex::new(NamedEngine {
+            name,
+            models,
+            lists_models: true,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmEngine for NamedEngine {
+    async fn infer(
+        &mut self,
+        model: v1::InferencingModel,
+        _prompt: String,
+        _params: v2::InferencingParams,
+    ) -> Result<v2::InferencingResult, v2::Error> {
+        if !self.models.contains(&model.as_str()) {
+            return Err(v2::Error::ModelNotSupported);
+        }
+        Ok(v2::InferencingResult {
+            text: self.name.to_owned(),
+            usage: v2::InferencingUsage {
+                prompt_token_count: 0,
+                generated_token_count: 0,
+            },
+        })
+    }
+
+    async fn generate_embeddings(
+        &mut self,
+        _model: v2::EmbeddingModel,
+        _data: Vec<String>,
+    ) -> Result<v2::EmbeddingsResult, v2::Error> {
+        Err(v2::Error::ModelNotSupported)
+    }
+
+    fn may_serve(&self, model: &str) -> bool {
+        !self.lists_models || self.models.contains(&model)
+    }
+}
+
+/// An engine whose server cannot be reached.
+struct FailingEngine;
+
+#[async_trait::async_trait]
+impl LlmEngine for FailingEngine {
+    async fn infer(
+        &mut self,
+        _model: v1::InferencingModel,
+        _prompt: String,
+        _params: v2::InferencingParams,
+    ) -> Result<v2::InferencingResult, v2::Error> {
+        Err(v2::Error::RuntimeError("server unavailable".to_owned()))
+    }
+
+    async fn generate_embeddings(
+        &mut self,
+        _model: v2::EmbeddingModel,
+        _data: Vec<String>,
+    ) -> Result<v2::EmbeddingsResult, v2::Error> {
+        Err(v2::Error::RuntimeError("server unavailable".to_owned()))
+    }
+}
+
 struct FakeLLm {
     handle: Box<dyn Fn(Operation) -> Result<OperationResult, v2::Error> + Sync + Send>,
 }
@@ -28,6 +28,28 @@ pub trait Client: Send + Sync + 'static {
         statement: String,
         params: Vec<ParameterValue>,
     ) -> Result<RowSet, v2::Error>;
+
+    async fn begin(&mut self) -> Result<(), v2::Error> {
+        self.execute("START TRANSACTION".to_owned(), vec![]).await
+    }
+
+    async fn commit(&mut self) -> Result<(), v2::Error> {
+        self.execute("COMMIT".to_owned(), vec![]).await
+    }
+
+    async fn rollback(&mut self) -> Result<(), v2::Error> {
+        self.execute("ROLLBACK".to_owned(), vec![]).await
+    }
+
+    /// Checks that a pooled connection is still usable before it is checked out again.
+    async fn is_healthy(&mut self) -> bool {
+        true
+    }
+
+    /// Discards any session state left by a guest before the connection is returned to its pool.
+    async fn reset(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -40,9 +62,7 @@ impl Client for MysqlClient {
 
         let opts = build_opts(address)?;
 
-        let connection_pool = mysql_async::Pool::new(opts);
-
-        connection_pool.get_conn().await.map_err(|e| anyhow!(e))
+        MysqlClient::new(opts).await.map_err(|e| anyhow!(e))
     }
 
     async fn execute(
@@ -86,6 +106,37 @@ impl Client for MysqlClient {
             }
         }
     }
+
+    // Transaction statements can't all be prepared, so they use the text protocol.
+    async fn begin(&mut self) -> Result<(), v2::Error> {
+        self.query_drop("START TRANSACTION")
+            .await
+            .map_err(|e| v2::Error::QueryFailed(format!("{:?}", e)))
+    }
+
+    async fn commit(&mut self) -> Result<(), v2::Error> {
+        self.query_drop("COMMIT")
+            .await
+            .map_err(|e| v2::Error::QueryFailed(format!("{:?}", e)))
+    }
+
+    async fn rollback(&mut self) -> Result<(), v2::Error> {
+        self.query_drop("ROLLBACK")
+            .await
+            .map_err(|e| v2::Error::QueryFailed(format!("{:?}", e)))
+    }
+
+    async fn is_healthy(&mut self) -> bool {
+        self.ping().await.is_ok()
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        // Servers without COM_RESET_CONNECTION can at least end an open transaction.
+        if !MysqlClient::reset(self).await? {
+            self.query_drop("ROLLBACK").await?;
+        }
+        Ok(())
+    }
 }
 
 fn to_sql_parameter(value: ParameterValue) -> mysql_async::Value {
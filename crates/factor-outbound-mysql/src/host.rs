@@ -1,6 +1,7 @@
 use anyhow::Result;
 use spin_core::async_trait;
 use spin_core::wasmtime::component::Resource;
+use spin_world::spin::mysql::mysql as v3;
 use spin_world::v1::mysql as v1;
 use spin_world::v2::mysql::{self as v2, Connection};
 use spin_world::v2::rdbms_types as v2_types;
@@ -15,7 +16,8 @@ impl<C: Client> InstanceState<C> {
     async fn open_connection(&mut self, address: &str) -> Result<Resource<Connection>, v2::Error> {
         self.connections
             .push(
-                C::build_client(address)
+                self.pools
+                    .get(address)
                     .await
                     .map_err(|e| v2::Error::ConnectionFailed(format!("{e:?}")))?,
             )
@@ -24,11 +26,34 @@ impl<C: Client> InstanceState<C> {
     }
 
     async fn get_client(&mut self, connection: Resource<Connection>) -> Result<&mut C, v2::Error> {
+        self.client(connection.rep())
+    }
+
+    fn client(&mut self, connection: u32) -> Result<&mut C, v2::Error> {
         self.connections
-            .get_mut(connection.rep())
+            .get_mut(connection)
+            .map(|client| &mut **client)
             .ok_or_else(|| v2::Error::ConnectionFailed("no connection found".into()))
     }
 
+    /// Returns the connection of a transaction in progress.
+    fn transaction_connection(&self, transaction: u32) -> Result<u32, v2::Error> {
+        self.transactions
+            .get(transaction)
+            .copied()
+            .ok_or_else(|| v2::Error::Other("no transaction found".into()))
+    }
+
+    /// Removes a transaction, returning its connection.
+    fn end_transaction(&mut self, transaction: u32) -> Result<u32, v2::Error> {
+        let connection = self
+            .transactions
+            .remove(transaction)
+            .ok_or_else(|| v2::Error::Other("no transaction found".into()))?;
+        self.open_transactions.remove(&connection);
+        Ok(connection)
+    }
+
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
         self.allowed_hosts.check_url(address, "mysql").await
     }
@@ -84,12 +109,134 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
     }
 
     async fn drop(&mut self, connection: Resource<Connection>) -> Result<()> {
+        // Returning the connection to its pool rolls back any transaction in progress.
         self.connections.remove(connection.rep());
+        self.open_transactions.remove(&connection.rep());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: Client> v3::Host for InstanceState<C> {}
+
+/// Apart from `begin`, the v3 connection is the v2 one, so it delegates to the v2 implementation.
+#[async_trait]
+impl<C: Client> v3::HostConnection for InstanceState<C> {
+    async fn open(&mut self, address: String) -> Result<Resource<v3::Connection>, v2::Error> {
+        let connection = <Self as v2::HostConnection>::open(self, address).await?;
+        Ok(Resource::new_own(connection.rep()))
+    }
+
+    async fn execute(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<(), v2::Error> {
+        <Self as v2::HostConnection>::execute(
+            self,
+            Resource::new_borrow(connection.rep()),
+            statement,
+            params,
+        )
+        .await
+    }
+
+    async fn query(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<v2_types::RowSet, v2::Error> {
+        <Self as v2::HostConnection>::query(
+            self,
+            Resource::new_borrow(connection.rep()),
+            statement,
+            params,
+        )
+        .await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.begin", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql"))]
+    async fn begin(
+        &mut self,
+        connection: Resource<v3::Connection>,
+    ) -> Result<Resource<v3::Transaction>, v2::Error> {
+        let connection = connection.rep();
+        self.client(connection)?;
+        if self.open_transactions.contains(&connection) {
+            return Err(v2::Error::Other(
+                "a transaction is already in progress on this connection".into(),
+            ));
+        }
+        let transaction = self
+            .transactions
+            .push(connection)
+            .map_err(|_| v2::Error::Other("too many transactions".into()))?;
+        if let Err(e) = self.client(connection)?.begin().await {
+            self.transactions.remove(transaction);
+            return Err(e);
+        }
+        self.open_transactions.insert(connection);
+        Ok(Resource::new_own(transaction))
+    }
+
+    async fn drop(&mut self, connection: Resource<v3::Connection>) -> Result<()> {
+        <Self as v2::HostConnection>::drop(self, Resource::new_own(connection.rep())).await
+    }
+}
+
+#[async_trait]
+impl<C: Client> v3::HostTransaction for InstanceState<C> {
+    #[instrument(name = "spin_outbound_mysql.execute", skip(self, transaction, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
+    async fn execute(
+        &mut self,
+        transaction: Resource<v3::Transaction>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<(), v2::Error> {
+        let connection = self.transaction_connection(transaction.rep())?;
+        self.client(connection)?.execute(statement, params).await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.query", skip(self, transaction, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
+    async fn query(
+        &mut self,
+        transaction: Resource<v3::Transaction>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<v2_types::RowSet, v2::Error> {
+        let connection = self.transaction_connection(transaction.rep())?;
+        self.client(connection)?.query(statement, params).await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.commit", skip(self, this), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql"))]
+    async fn commit(&mut self, this: Resource<v3::Transaction>) -> Result<(), v2::Error> {
+        let connection = self.end_transaction(this.rep())?;
+        self.client(connection)?.commit().await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.rollback", skip(self, this), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql"))]
+    async fn rollback(&mut self, this: Resource<v3::Transaction>) -> Result<(), v2::Error> {
+        let connection = self.end_transaction(this.rep())?;
+        self.client(connection)?.rollback().await
+    }
+
+    async fn drop(&mut self, transaction: Resource<v3::Transaction>) -> Result<()> {
+        // Committed and rolled back transactions have already been removed.
+        let Ok(connection) = self.end_transaction(transaction.rep()) else {
+            return Ok(());
+        };
+        if let Ok(client) = self.client(connection) {
+            if let Err(e) = client.rollback().await {
+                tracing::warn!("Failed to roll back dropped transaction: {e:?}");
+            }
+        }
         Ok(())
     }
 }
 
-impl<C: Send> v2_types::Host for InstanceState<C> {
+impl<C: Client> v2_types::Host for InstanceState<C> {
     fn convert_error(&mut self, error: v2::Error) -> Result<v2::Error> {
         Ok(error)
     }
@@ -1,8 +1,15 @@
 pub mod client;
 mod host;
+pub mod pool;
+pub mod runtime_config;
+
+use std::collections::HashSet;
+use std::sync::Arc;
 
 use client::Client;
 use mysql_async::Conn as MysqlClient;
+use pool::{PooledClient, Pools};
+use runtime_config::RuntimeConfig;
 use spin_factor_outbound_networking::{OutboundAllowedHosts, OutboundNetworkingFactor};
 use spin_factors::{Factor, InitContext, RuntimeFactors, SelfInstanceBuilder};
 use spin_world::v1::mysql as v1;
@@ -13,21 +20,25 @@ pub struct OutboundMysqlFactor<C = MysqlClient> {
 }
 
 impl<C: Send + Sync + Client + 'static> Factor for OutboundMysqlFactor<C> {
-    type RuntimeConfig = ();
-    type AppState = ();
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState<C>;
     type InstanceBuilder = InstanceState<C>;
 
     fn init<T: Send + 'static>(&mut self, mut ctx: InitContext<T, Self>) -> anyhow::Result<()> {
         ctx.link_bindings(v1::add_to_linker)?;
         ctx.link_bindings(v2::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::mysql::mysql::add_to_linker)?;
         Ok(())
     }
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: spin_factors::ConfigureAppContext<T, Self>,
+        mut ctx: spin_factors::ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let config = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState {
+            pools: Arc::new(Pools::new(config.pool)),
+        })
     }
 
     fn prepare<T: spin_factors::RuntimeFactors>(
@@ -39,7 +50,10 @@ impl<C: Send + Sync + Client + 'static> Factor for OutboundMysqlFactor<C> {
             .allowed_hosts();
         Ok(InstanceState {
             allowed_hosts,
+            pools: ctx.app_state().pools.clone(),
             connections: Default::default(),
+            transactions: Default::default(),
+            open_transactions: Default::default(),
         })
     }
 }
@@ -58,9 +72,19 @@ impl<C> OutboundMysqlFactor<C> {
     }
 }
 
-pub struct InstanceState<C> {
+pub struct AppState<C: Client> {
+    /// Connection pools shared by all instances of the app.
+    pools: Arc<Pools<C>>,
+}
+
+pub struct InstanceState<C: Client> {
     allowed_hosts: OutboundAllowedHosts,
-    connections: spin_resource_table::Table<C>,
+    pools: Arc<Pools<C>>,
+    connections: spin_resource_table::Table<PooledClient<C>>,
+    /// The connection of each transaction in progress.
+    transactions: spin_resource_table::Table<u32>,
+    /// The connections with a transaction in progress.
+    open_transactions: HashSet<u32>,
 }
 
-impl<C: Send + 'static> SelfInstanceBuilder for InstanceState<C> {}
+impl<C: Client> SelfInstanceBuilder for InstanceState<C> {}
@@ -1,16 +1,16 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
 use spin_world::async_trait;
-use spin_world::spin::postgres::postgres::{
-    self as v3, Column, DbDataType, DbValue, ParameterValue, RowSet,
+use spin_world::spin::postgres4_0_0::postgres::{
+    self as v4, Column, DbDataType, DbValue, Interval, ParameterValue, RowSet,
 };
-use tokio_postgres::types::Type;
+use tokio_postgres::types::{FromSql, Type};
 use tokio_postgres::{config::SslMode, types::ToSql, Row};
 use tokio_postgres::{Client as TokioClient, NoTls, Socket};
 
 #[async_trait]
-pub trait Client {
+pub trait Client: Send + Sync + 'static {
     async fn build_client(address: &str) -> Result<Self>
     where
         Self: Sized;
@@ -19,13 +19,37 @@ pub trait Client {
         &self,
         statement: String,
         params: Vec<ParameterValue>,
-    ) -> Result<u64, v3::Error>;
+    ) -> Result<u64, v4::Error>;
 
     async fn query(
         &self,
         statement: String,
         params: Vec<ParameterValue>,
-    ) -> Result<RowSet, v3::Error>;
+    ) -> Result<RowSet, v4::Error>;
+
+    async fn begin(&self) -> Result<(), v4::Error> {
+        self.execute("BEGIN".to_owned(), vec![]).await.map(|_| ())
+    }
+
+    async fn commit(&self) -> Result<(), v4::Error> {
+        self.execute("COMMIT".to_owned(), vec![]).await.map(|_| ())
+    }
+
+    async fn rollback(&self) -> Result<(), v4::Error> {
+        self.execute("ROLLBACK".to_owned(), vec![])
+            .await
+            .map(|_| ())
+    }
+
+    /// Checks that a pooled connection is still usable before it is checked out again.
+    async fn is_healthy(&self) -> bool {
+        true
+    }
+
+    /// Discards any session state left by a guest before the connection is returned to its pool.
+    async fn reset(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -55,12 +79,12 @@ impl Client for TokioClient {
         &self,
         statement: String,
         params: Vec<ParameterValue>,
-    ) -> Result<u64, v3::Error> {
+    ) -> Result<u64, v4::Error> {
         let params = params
             .iter()
             .map(to_sql_parameter)
             .collect::<Result<Vec<_>>>()
-            .map_err(|e| v3::Error::ValueConversionFailed(format!("{:?}", e)))?;
+            .map_err(|e| v4::Error::ValueConversionFailed(format!("{:?}", e)))?;
 
         let params_refs: Vec<&(dyn ToSql + Sync)> = params
             .iter()
@@ -69,19 +93,19 @@ impl Client for TokioClient {
 
         self.execute(&statement, params_refs.as_slice())
             .await
-            .map_err(|e| v3::Error::QueryFailed(format!("{:?}", e)))
+            .map_err(|e| v4::Error::QueryFailed(format!("{:?}", e)))
     }
 
     async fn query(
         &self,
         statement: String,
         params: Vec<ParameterValue>,
-    ) -> Result<RowSet, v3::Error> {
+    ) -> Result<RowSet, v4::Error> {
         let params = params
             .iter()
             .map(to_sql_parameter)
             .collect::<Result<Vec<_>>>()
-            .map_err(|e| v3::Error::BadParameter(format!("{:?}", e)))?;
+            .map_err(|e| v4::Error::BadParameter(format!("{:?}", e)))?;
 
         let params_refs: Vec<&(dyn ToSql + Sync)> = params
             .iter()
@@ -91,7 +115,7 @@ impl Client for TokioClient {
         let results = self
             .query(&statement, params_refs.as_slice())
             .await
-            .map_err(|e| v3::Error::QueryFailed(format!("{:?}", e)))?;
+            .map_err(|e| v4::Error::QueryFailed(format!("{:?}", e)))?;
 
         if results.is_empty() {
             return Ok(RowSet {
@@ -105,10 +129,21 @@ impl Client for TokioClient {
             .iter()
             .map(convert_row)
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| v3::Error::QueryFailed(format!("{:?}", e)))?;
+            .map_err(|e| v4::Error::QueryFailed(format!("{:?}", e)))?;
 
         Ok(RowSet { columns, rows })
     }
+
+    async fn is_healthy(&self) -> bool {
+        !self.is_closed() && self.simple_query("").await.is_ok()
+    }
+
+    async fn reset(&self) -> Result<()> {
+        // `DISCARD ALL` cannot run inside a transaction, so end any the guest left open first.
+        self.batch_execute("ROLLBACK").await?;
+        self.batch_execute("DISCARD ALL").await?;
+        Ok(())
+    }
 }
 
 fn spawn_connection<T>(connection: tokio_postgres::Connection<Socket, T>)
@@ -158,6 +193,26 @@ fn to_sql_parameter(value: &ParameterValue) -> Result<Box<dyn ToSql + Send + Syn
                 .ok_or_else(|| anyhow!("invalid epoch timestamp {v}"))?;
             Ok(Box::new(ts))
         }
+        ParameterValue::Uuid(v) => {
+            let uuid = uuid::Uuid::parse_str(v).with_context(|| format!("invalid UUID {v}"))?;
+            Ok(Box::new(uuid))
+        }
+        ParameterValue::Jsonb(v) => {
+            let json: serde_json::Value = serde_json::from_slice(v).context("invalid JSON")?;
+            Ok(Box::new(json))
+        }
+        ParameterValue::Decimal(v) => {
+            let numeric = v
+                .parse::<PgNumeric>()
+                .map_err(|e| anyhow!("invalid decimal {v}: {e}"))?;
+            Ok(Box::new(numeric))
+        }
+        ParameterValue::ArrayStr(v) => Ok(Box::new(v.clone())),
+        ParameterValue::Interval(v) => Ok(Box::new(PgInterval {
+            micros: v.micros,
+            days: v.days,
+            months: v.months,
+        })),
         ParameterValue::DbNull => Ok(Box::new(PgNull)),
     }
 }
@@ -190,6 +245,11 @@ fn convert_data_type(pg_type: &Type) -> DbDataType {
         Type::TIMESTAMP | Type::TIMESTAMPTZ => DbDataType::Timestamp,
         Type::DATE => DbDataType::Date,
         Type::TIME => DbDataType::Time,
+        Type::UUID => DbDataType::Uuid,
+        Type::JSON | Type::JSONB => DbDataType::Jsonb,
+        Type::NUMERIC => DbDataType::Decimal,
+        Type::TEXT_ARRAY | Type::VARCHAR_ARRAY | Type::BPCHAR_ARRAY => DbDataType::ArrayStr,
+        Type::INTERVAL => DbDataType::Interval,
         _ => {
             tracing::debug!("Couldn't convert Postgres type {} to WIT", pg_type.name(),);
             DbDataType::Other
@@ -285,6 +345,45 @@ fn convert_entry(row: &Row, index: usize) -> anyhow::Result<DbValue> {
                 None => DbValue::DbNull,
             }
         }
+        &Type::UUID => {
+            let value: Option<uuid::Uuid> = row.try_get(index)?;
+            match value {
+                Some(v) => DbValue::Uuid(v.to_string()),
+                None => DbValue::DbNull,
+            }
+        }
+        &Type::JSON | &Type::JSONB => {
+            let value: Option<serde_json::Value> = row.try_get(index)?;
+            match value {
+                Some(v) => DbValue::Jsonb(serde_json::to_vec(&v)?),
+                None => DbValue::DbNull,
+            }
+        }
+        &Type::NUMERIC => {
+            let value: Option<PgNumeric> = row.try_get(index)?;
+            match value {
+                Some(v) => DbValue::Decimal(v.to_string()),
+                None => DbValue::DbNull,
+            }
+        }
+        &Type::TEXT_ARRAY | &Type::VARCHAR_ARRAY | &Type::BPCHAR_ARRAY => {
+            let value: Option<Vec<Option<String>>> = row.try_get(index)?;
+            match value {
+                Some(v) => DbValue::ArrayStr(v),
+                None => DbValue::DbNull,
+            }
+        }
+        &Type::INTERVAL => {
+            let value: Option<PgInterval> = row.try_get(index)?;
+            match value {
+                Some(v) => DbValue::Interval(Interval {
+                    micros: v.micros,
+                    days: v.days,
+                    months: v.months,
+                }),
+                None => DbValue::DbNull,
+            }
+        }
         t => {
             tracing::debug!(
                 "Couldn't convert Postgres type {} in column {}",
@@ -332,6 +431,254 @@ fn tuplify_time(value: chrono::NaiveTime) -> anyhow::Result<(u8, u8, u8, u32)> {
     ))
 }
 
+/// A Postgres INTERVAL, which the Postgres crate has no type for. Its binary
+/// format is the microseconds, days and months of the interval, in that order.
+#[derive(Debug, PartialEq)]
+struct PgInterval {
+    micros: i64,
+    days: i32,
+    months: i32,
+}
+
+impl ToSql for PgInterval {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut tokio_postgres::types::private::BytesMut,
+    ) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend_from_slice(&self.micros.to_be_bytes());
+        out.extend_from_slice(&self.days.to_be_bytes());
+        out.extend_from_slice(&self.months.to_be_bytes());
+        Ok(tokio_postgres::types::IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+
+    tokio_postgres::types::to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for PgInterval {
+    fn from_sql(
+        _ty: &Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let raw: &[u8; 16] = raw
+            .try_into()
+            .map_err(|_| format!("invalid interval of {} bytes", raw.len()))?;
+        let (micros, rest) = raw.split_at(8);
+        let (days, months) = rest.split_at(4);
+        Ok(Self {
+            micros: i64::from_be_bytes(micros.try_into()?),
+            days: i32::from_be_bytes(days.try_into()?),
+            months: i32::from_be_bytes(months.try_into()?),
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+}
+
+/// A Postgres NUMERIC, which may have up to 131072 digits before the decimal point and 16383
+/// after it, or be NaN or infinite. That is beyond any Rust decimal type, so NUMERIC values are
+/// converted to and from their text representation without going through one.
+///
+/// The binary format is a header of the number of base-10000 digits, the weight of the first
+/// digit, the sign, and the display scale, followed by the digits, all as 16-bit integers.
+#[derive(Debug, PartialEq)]
+enum PgNumeric {
+    NaN,
+    Infinity {
+        negative: bool,
+    },
+    Finite {
+        negative: bool,
+        /// The decimal digits before the point, without leading zeros (or `0`).
+        int: String,
+        /// The decimal digits after the point.
+        frac: String,
+    },
+}
+
+const NUMERIC_POS: u16 = 0x0000;
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+const NUMERIC_PINF: u16 = 0xD000;
+const NUMERIC_NINF: u16 = 0xF000;
+
+impl PgNumeric {
+    fn finite(negative: bool, int: &str, frac: &str) -> Self {
+        let int = match int.trim_start_matches('0') {
+            "" => "0",
+            int => int,
+        };
+        // Postgres has no negative zero.
+        let zero = int == "0" && frac.bytes().all(|b| b == b'0');
+        Self::Finite {
+            negative: negative && !zero,
+            int: int.to_owned(),
+            frac: frac.to_owned(),
+        }
+    }
+}
+
+impl std::str::FromStr for PgNumeric {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("nan") {
+            return Ok(Self::NaN);
+        }
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if unsigned.eq_ignore_ascii_case("infinity") {
+            return Ok(Self::Infinity { negative });
+        }
+        let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let is_digits = |digits: &str| digits.bytes().all(|b| b.is_ascii_digit());
+        if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+            return Err("expected decimal digits with an optional sign and decimal point");
+        }
+        Ok(Self::finite(negative, int, frac))
+    }
+}
+
+impl std::fmt::Display for PgNumeric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NaN => f.write_str("NaN"),
+            Self::Infinity { negative } => {
+                f.write_str(if *negative { "-Infinity" } else { "Infinity" })
+            }
+            Self::Finite {
+                negative,
+                int,
+                frac,
+            } => {
+                if *negative {
+                    f.write_str("-")?;
+                }
+                f.write_str(int)?;
+                if !frac.is_empty() {
+                    write!(f, ".{frac}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ToSql for PgNumeric {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut tokio_postgres::types::private::BytesMut,
+    ) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        let (sign, digits, weight, scale) = match self {
+            Self::NaN => (NUMERIC_NAN, vec![], 0, 0),
+            Self::Infinity { negative: false } => (NUMERIC_PINF, vec![], 0, 0),
+            Self::Infinity { negative: true } => (NUMERIC_NINF, vec![], 0, 0),
+            Self::Finite {
+                negative,
+                int,
+                frac,
+            } => {
+                // Align the decimal digits to base-10000 digits on either side of the point.
+                let int_pad = (4 - int.len() % 4) % 4;
+                let frac_pad = (4 - frac.len() % 4) % 4;
+                let decimal = format!("{}{int}{frac}{}", "0".repeat(int_pad), "0".repeat(frac_pad));
+                let mut digits = decimal
+                    .as_bytes()
+                    .chunks(4)
+                    .map(|chunk| chunk.iter().fold(0, |n, d| n * 10 + i16::from(d - b'0')))
+                    .collect::<Vec<_>>();
+                let mut weight = i16::try_from((int.len() + int_pad) / 4)? - 1;
+                // Leading and trailing zero digits are implied by the weight and scale.
+                let leading = digits.iter().take_while(|d| **d == 0).count();
+                digits.drain(..leading);
+                weight -= i16::try_from(leading)?;
+                while digits.last() == Some(&0) {
+                    digits.pop();
+                }
+                if digits.is_empty() {
+                    weight = 0;
+                }
+                let sign = if *negative { NUMERIC_NEG } else { NUMERIC_POS };
+                (sign, digits, weight, u16::try_from(frac.len())?)
+            }
+        };
+        out.extend_from_slice(&i16::try_from(digits.len())?.to_be_bytes());
+        out.extend_from_slice(&weight.to_be_bytes());
+        out.extend_from_slice(&sign.to_be_bytes());
+        out.extend_from_slice(&scale.to_be_bytes());
+        for digit in digits {
+            out.extend_from_slice(&digit.to_be_bytes());
+        }
+        Ok(tokio_postgres::types::IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+
+    tokio_postgres::types::to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for PgNumeric {
+    fn from_sql(
+        _ty: &Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let read = |i: usize| -> Result<[u8; 2], String> {
+            raw.get(i * 2..i * 2 + 2)
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| format!("invalid numeric of {} bytes", raw.len()))
+        };
+        let ndigits = usize::from(u16::from_be_bytes(read(0)?));
+        let weight = i32::from(i16::from_be_bytes(read(1)?));
+        let sign = u16::from_be_bytes(read(2)?);
+        let scale = usize::from(u16::from_be_bytes(read(3)?));
+        let negative = match sign {
+            NUMERIC_NAN => return Ok(Self::NaN),
+            NUMERIC_PINF => return Ok(Self::Infinity { negative: false }),
+            NUMERIC_NINF => return Ok(Self::Infinity { negative: true }),
+            NUMERIC_POS => false,
+            NUMERIC_NEG => true,
+            _ => return Err(format!("invalid numeric sign {sign:#x}").into()),
+        };
+        if raw.len() != 8 + ndigits * 2 {
+            return Err(format!("invalid numeric of {} bytes", raw.len()).into());
+        }
+        // The digit at index `i` is multiplied by 10000^(weight - i).
+        let digit = |i: i32| -> Result<i16, String> {
+            match usize::try_from(i) {
+                Ok(i) if i < ndigits => Ok(i16::from_be_bytes(read(4 + i)?)),
+                _ => Ok(0),
+            }
+        };
+        let mut int = String::new();
+        for i in 0..=weight {
+            int.push_str(&format!("{:04}", digit(i)?));
+        }
+        let mut frac = String::new();
+        let mut i = weight + 1;
+        while frac.len() < scale {
+            frac.push_str(&format!("{:04}", digit(i)?));
+            i += 1;
+        }
+        frac.truncate(scale);
+        Ok(Self::finite(negative, &int, &frac))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+}
+
 /// Although the Postgres crate converts Rust Option::None to Postgres NULL,
 /// it enforces the type of the Option as it does so. (For example, trying to
 /// pass an Option::<i32>::None to a VARCHAR column fails conversion.) As we
@@ -373,3 +720,77 @@ impl std::fmt::Debug for PgNull {
         f.debug_struct("NULL").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_round_trips() {
+        let interval = PgInterval {
+            micros: -1_500_000,
+            days: 3,
+            months: 14,
+        };
+        let mut buf = tokio_postgres::types::private::BytesMut::new();
+        interval.to_sql(&Type::INTERVAL, &mut buf).unwrap();
+        assert_eq!(buf.len(), 16);
+        assert_eq!(
+            PgInterval::from_sql(&Type::INTERVAL, &buf).unwrap(),
+            interval
+        );
+        assert!(PgInterval::from_sql(&Type::INTERVAL, &buf[..8]).is_err());
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert!(to_sql_parameter(&ParameterValue::Uuid("not-a-uuid".into())).is_err());
+        assert!(to_sql_parameter(&ParameterValue::Jsonb(b"{".to_vec())).is_err());
+        assert!(to_sql_parameter(&ParameterValue::Decimal("1.2.3".into())).is_err());
+        assert!(to_sql_parameter(&ParameterValue::Decimal("-1234.5678".into())).is_ok());
+    }
+
+    fn numeric_round_trip(value: &str) -> String {
+        let numeric = value.parse::<PgNumeric>().unwrap();
+        let mut buf = tokio_postgres::types::private::BytesMut::new();
+        numeric.to_sql(&Type::NUMERIC, &mut buf).unwrap();
+        PgNumeric::from_sql(&Type::NUMERIC, &buf)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn numeric_round_trips() {
+        for value in [
+            "0",
+            "0.00",
+            "1",
+            "10000",
+            "-1234.5678",
+            "0.00001",
+            "123456789012345678901234567890.123456789012345678901234567890",
+            "NaN",
+            "Infinity",
+            "-Infinity",
+        ] {
+            assert_eq!(numeric_round_trip(value), value);
+        }
+        assert_eq!(numeric_round_trip("-000.00"), "0.00");
+        assert_eq!(numeric_round_trip(".5"), "0.5");
+    }
+
+    #[test]
+    fn numeric_is_decoded_from_postgres_format() {
+        // 12345.678: digits 1, 2345, 6780 with weight 1 and scale 3
+        let raw = [0, 3, 0, 1, 0, 0, 0, 3, 0, 1, 0x09, 0x29, 0x1a, 0x7c];
+        let numeric = PgNumeric::from_sql(&Type::NUMERIC, &raw).unwrap();
+        assert_eq!(numeric.to_string(), "12345.678");
+
+        // NaN, which no Rust decimal type can represent
+        let raw = [0, 0, 0, 0, 0xc0, 0, 0, 0];
+        let numeric = PgNumeric::from_sql(&Type::NUMERIC, &raw).unwrap();
+        assert_eq!(numeric, PgNumeric::NaN);
+
+        assert!(PgNumeric::from_sql(&Type::NUMERIC, &raw[..6]).is_err());
+    }
+}
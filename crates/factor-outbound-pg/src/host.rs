@@ -1,6 +1,7 @@
 use anyhow::Result;
 use spin_core::{async_trait, wasmtime::component::Resource};
-use spin_world::spin::postgres::postgres::{self as v3};
+use spin_world::spin::postgres3_0_0::postgres::{self as v3};
+use spin_world::spin::postgres4_0_0::postgres::{self as v4};
 use spin_world::v1::postgres as v1;
 use spin_world::v1::rdbms_types as v1_types;
 use spin_world::v2::postgres::{self as v2};
@@ -16,24 +17,48 @@ impl<C: Client> InstanceState<C> {
     async fn open_connection<Conn: 'static>(
         &mut self,
         address: &str,
-    ) -> Result<Resource<Conn>, v3::Error> {
+    ) -> Result<Resource<Conn>, v4::Error> {
         self.connections
             .push(
-                C::build_client(address)
+                self.pools
+                    .get(address)
                     .await
-                    .map_err(|e| v3::Error::ConnectionFailed(format!("{e:?}")))?,
+                    .map_err(|e| v4::Error::ConnectionFailed(format!("{e:?}")))?,
             )
-            .map_err(|_| v3::Error::ConnectionFailed("too many connections".into()))
+            .map_err(|_| v4::Error::ConnectionFailed("too many connections".into()))
             .map(Resource::new_own)
     }
 
     async fn get_client<Conn: 'static>(
         &mut self,
         connection: Resource<Conn>,
-    ) -> Result<&C, v3::Error> {
+    ) -> Result<&C, v4::Error> {
+        self.client(connection.rep())
+    }
+
+    fn client(&self, connection: u32) -> Result<&C, v4::Error> {
         self.connections
-            .get(connection.rep())
-            .ok_or_else(|| v3::Error::ConnectionFailed("no connection found".into()))
+            .get(connection)
+            .map(|client| &**client)
+            .ok_or_else(|| v4::Error::ConnectionFailed("no connection found".into()))
+    }
+
+    /// Returns the connection of a transaction in progress.
+    fn transaction_connection(&self, transaction: u32) -> Result<u32, v4::Error> {
+        self.transactions
+            .get(transaction)
+            .copied()
+            .ok_or_else(|| v4::Error::Other("no transaction found".into()))
+    }
+
+    /// Removes a transaction, returning its connection.
+    fn end_transaction(&mut self, transaction: u32) -> Result<u32, v4::Error> {
+        let connection = self
+            .transactions
+            .remove(transaction)
+            .ok_or_else(|| v4::Error::Other("no transaction found".into()))?;
+        self.open_transactions.remove(&connection);
+        Ok(connection)
     }
 
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
@@ -65,26 +90,24 @@ impl<C: Client> InstanceState<C> {
     }
 }
 
-fn v2_params_to_v3(
+fn v2_params_to_v4(
     params: Vec<v2_types::ParameterValue>,
-) -> Result<Vec<v3::ParameterValue>, v2::Error> {
+) -> Result<Vec<v4::ParameterValue>, v2::Error> {
     params.into_iter().map(|p| p.try_into()).collect()
 }
 
 #[async_trait]
-impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnection
-    for InstanceState<C>
-{
+impl<C: Send + Sync + Client> v4::HostConnection for InstanceState<C> {
     #[instrument(name = "spin_outbound_pg.open", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
-    async fn open(&mut self, address: String) -> Result<Resource<v3::Connection>, v3::Error> {
+    async fn open(&mut self, address: String) -> Result<Resource<v4::Connection>, v4::Error> {
         spin_factor_outbound_networking::record_address_fields(&address);
 
         if !self
             .is_address_allowed(&address)
             .await
-            .map_err(|e| v3::Error::Other(e.to_string()))?
+            .map_err(|e| v4::Error::Other(e.to_string()))?
         {
-            return Err(v3::Error::ConnectionFailed(format!(
+            return Err(v4::Error::ConnectionFailed(format!(
                 "address {address} is not permitted"
             )));
         }
@@ -94,10 +117,10 @@ impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnecti
     #[instrument(name = "spin_outbound_pg.execute", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
     async fn execute(
         &mut self,
-        connection: Resource<v3::Connection>,
+        connection: Resource<v4::Connection>,
         statement: String,
-        params: Vec<v3::ParameterValue>,
-    ) -> Result<u64, v3::Error> {
+        params: Vec<v4::ParameterValue>,
+    ) -> Result<u64, v4::Error> {
         Ok(self
             .get_client(connection)
             .await?
@@ -108,10 +131,10 @@ impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnecti
     #[instrument(name = "spin_outbound_pg.query", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
     async fn query(
         &mut self,
-        connection: Resource<v3::Connection>,
+        connection: Resource<v4::Connection>,
         statement: String,
-        params: Vec<v3::ParameterValue>,
-    ) -> Result<v3::RowSet, v3::Error> {
+        params: Vec<v4::ParameterValue>,
+    ) -> Result<v4::RowSet, v4::Error> {
         Ok(self
             .get_client(connection)
             .await?
@@ -119,28 +142,154 @@ impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnecti
             .await?)
     }
 
-    async fn drop(&mut self, connection: Resource<v3::Connection>) -> anyhow::Result<()> {
+    #[instrument(name = "spin_outbound_pg.begin", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn begin(
+        &mut self,
+        connection: Resource<v4::Connection>,
+    ) -> Result<Resource<v4::Transaction>, v4::Error> {
+        let connection = connection.rep();
+        self.client(connection)?;
+        if self.open_transactions.contains(&connection) {
+            return Err(v4::Error::Other(
+                "a transaction is already in progress on this connection".into(),
+            ));
+        }
+        let transaction = self
+            .transactions
+            .push(connection)
+            .map_err(|_| v4::Error::Other("too many transactions".into()))?;
+        if let Err(e) = self.client(connection)?.begin().await {
+            self.transactions.remove(transaction);
+            return Err(e);
+        }
+        self.open_transactions.insert(connection);
+        Ok(Resource::new_own(transaction))
+    }
+
+    async fn drop(&mut self, connection: Resource<v4::Connection>) -> anyhow::Result<()> {
+        // Returning the connection to its pool rolls back any transaction in progress.
         self.connections.remove(connection.rep());
+        self.open_transactions.remove(&connection.rep());
         Ok(())
     }
 }
 
-impl<C: Send> v2_types::Host for InstanceState<C> {
+#[async_trait]
+impl<C: Send + Sync + Client> v4::HostTransaction for InstanceState<C> {
+    #[instrument(name = "spin_outbound_pg.execute", skip(self, transaction, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
+    async fn execute(
+        &mut self,
+        transaction: Resource<v4::Transaction>,
+        statement: String,
+        params: Vec<v4::ParameterValue>,
+    ) -> Result<u64, v4::Error> {
+        let connection = self.transaction_connection(transaction.rep())?;
+        Ok(self.client(connection)?.execute(statement, params).await?)
+    }
+
+    #[instrument(name = "spin_outbound_pg.query", skip(self, transaction, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
+    async fn query(
+        &mut self,
+        transaction: Resource<v4::Transaction>,
+        statement: String,
+        params: Vec<v4::ParameterValue>,
+    ) -> Result<v4::RowSet, v4::Error> {
+        let connection = self.transaction_connection(transaction.rep())?;
+        Ok(self.client(connection)?.query(statement, params).await?)
+    }
+
+    #[instrument(name = "spin_outbound_pg.commit", skip(self, this), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn commit(&mut self, this: Resource<v4::Transaction>) -> Result<(), v4::Error> {
+        let connection = self.end_transaction(this.rep())?;
+        self.client(connection)?.commit().await
+    }
+
+    #[instrument(name = "spin_outbound_pg.rollback", skip(self, this), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn rollback(&mut self, this: Resource<v4::Transaction>) -> Result<(), v4::Error> {
+        let connection = self.end_transaction(this.rep())?;
+        self.client(connection)?.rollback().await
+    }
+
+    async fn drop(&mut self, transaction: Resource<v4::Transaction>) -> anyhow::Result<()> {
+        // Committed and rolled back transactions have already been removed.
+        let Ok(connection) = self.end_transaction(transaction.rep()) else {
+            return Ok(());
+        };
+        if let Ok(client) = self.client(connection) {
+            if let Err(e) = client.rollback().await {
+                tracing::warn!("Failed to roll back dropped transaction: {e:?}");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<C: Client> v2_types::Host for InstanceState<C> {
     fn convert_error(&mut self, error: v2::Error) -> Result<v2::Error> {
         Ok(error)
     }
 }
 
+impl<C: Send + Sync + Client> v4::Host for InstanceState<C> {
+    fn convert_error(&mut self, error: v4::Error) -> Result<v4::Error> {
+        Ok(error)
+    }
+}
+
 impl<C: Send + Sync + Client> v3::Host for InstanceState<C> {
     fn convert_error(&mut self, error: v3::Error) -> Result<v3::Error> {
         Ok(error)
     }
 }
 
-/// Delegate a function call to the v3::HostConnection implementation
+/// The v3 interface differs from v4 only in its types, so it delegates to the v4 implementation.
+#[async_trait]
+impl<C: Send + Sync + Client> v3::HostConnection for InstanceState<C> {
+    async fn open(&mut self, address: String) -> Result<Resource<v3::Connection>, v3::Error> {
+        let connection = <Self as v4::HostConnection>::open(self, address).await?;
+        Ok(Resource::new_own(connection.rep()))
+    }
+
+    async fn execute(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<v3::ParameterValue>,
+    ) -> Result<u64, v3::Error> {
+        Ok(<Self as v4::HostConnection>::execute(
+            self,
+            Resource::new_borrow(connection.rep()),
+            statement,
+            params.into_iter().map(Into::into).collect(),
+        )
+        .await?)
+    }
+
+    async fn query(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<v3::ParameterValue>,
+    ) -> Result<v3::RowSet, v3::Error> {
+        Ok(<Self as v4::HostConnection>::query(
+            self,
+            Resource::new_borrow(connection.rep()),
+            statement,
+            params.into_iter().map(Into::into).collect(),
+        )
+        .await?
+        .into())
+    }
+
+    async fn drop(&mut self, connection: Resource<v3::Connection>) -> anyhow::Result<()> {
+        <Self as v4::HostConnection>::drop(self, Resource::new_own(connection.rep())).await
+    }
+}
+
+/// Delegate a function call to the v4::HostConnection implementation
 macro_rules! delegate {
     ($self:ident.$name:ident($address:expr, $($arg:expr),*)) => {{
-        if !$self.is_address_allowed(&$address).await.map_err(|e| v3::Error::Other(e.to_string()))? {
+        if !$self.is_address_allowed(&$address).await.map_err(|e| v4::Error::Other(e.to_string()))? {
             return Err(v1::PgError::ConnectionFailed(format!(
                 "address {} is not permitted", $address
             )));
@@ -149,7 +298,7 @@ macro_rules! delegate {
             Ok(c) => c,
             Err(e) => return Err(e.into()),
         };
-        <Self as v3::HostConnection>::$name($self, connection, $($arg),*)
+        <Self as v4::HostConnection>::$name($self, connection, $($arg),*)
             .await
             .map_err(|e| e.into())
     }};
@@ -186,7 +335,7 @@ impl<C: Send + Sync + Client> v2::HostConnection for InstanceState<C> {
         Ok(self
             .get_client(connection)
             .await?
-            .execute(statement, v2_params_to_v3(params)?)
+            .execute(statement, v2_params_to_v4(params)?)
             .await?)
     }
 
@@ -200,7 +349,7 @@ impl<C: Send + Sync + Client> v2::HostConnection for InstanceState<C> {
         Ok(self
             .get_client(connection)
             .await?
-            .query(statement, v2_params_to_v3(params)?)
+            .query(statement, v2_params_to_v4(params)?)
             .await?
             .into())
     }
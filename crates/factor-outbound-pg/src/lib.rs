@@ -1,7 +1,14 @@
 pub mod client;
 mod host;
+pub mod pool;
+pub mod runtime_config;
+
+use std::collections::HashSet;
+use std::sync::Arc;
 
 use client::Client;
+use pool::{PooledClient, Pools};
+use runtime_config::RuntimeConfig;
 use spin_factor_outbound_networking::{OutboundAllowedHosts, OutboundNetworkingFactor};
 use spin_factors::{
     anyhow, ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
@@ -13,8 +20,8 @@ pub struct OutboundPgFactor<C = PgClient> {
 }
 
 impl<C: Send + Sync + Client + 'static> Factor for OutboundPgFactor<C> {
-    type RuntimeConfig = ();
-    type AppState = ();
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState<C>;
     type InstanceBuilder = InstanceState<C>;
 
     fn init<T: Send + 'static>(
@@ -23,15 +30,19 @@ impl<C: Send + Sync + Client + 'static> Factor for OutboundPgFactor<C> {
     ) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v1::postgres::add_to_linker)?;
         ctx.link_bindings(spin_world::v2::postgres::add_to_linker)?;
-        ctx.link_bindings(spin_world::spin::postgres::postgres::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::postgres3_0_0::postgres::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::postgres4_0_0::postgres::add_to_linker)?;
         Ok(())
     }
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: ConfigureAppContext<T, Self>,
+        mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let config = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState {
+            pools: Arc::new(Pools::new(config.pool)),
+        })
     }
 
     fn prepare<T: RuntimeFactors>(
@@ -43,7 +54,10 @@ impl<C: Send + Sync + Client + 'static> Factor for OutboundPgFactor<C> {
             .allowed_hosts();
         Ok(InstanceState {
             allowed_hosts,
+            pools: ctx.app_state().pools.clone(),
             connections: Default::default(),
+            transactions: Default::default(),
+            open_transactions: Default::default(),
         })
     }
 }
@@ -62,9 +76,19 @@ impl<C> OutboundPgFactor<C> {
     }
 }
 
-pub struct InstanceState<C> {
+pub struct AppState<C: Client> {
+    /// Connection pools shared by all instances of the app.
+    pools: Arc<Pools<C>>,
+}
+
+pub struct InstanceState<C: Client> {
     allowed_hosts: OutboundAllowedHosts,
-    connections: spin_resource_table::Table<C>,
+    pools: Arc<Pools<C>>,
+    connections: spin_resource_table::Table<PooledClient<C>>,
+    /// The connection of each transaction in progress.
+    transactions: spin_resource_table::Table<u32>,
+    /// The connections with a transaction in progress.
+    open_transactions: HashSet<u32>,
 }
 
-impl<C: Send + 'static> SelfInstanceBuilder for InstanceState<C> {}
+impl<C: Client> SelfInstanceBuilder for InstanceState<C> {}
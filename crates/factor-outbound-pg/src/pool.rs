@@ -0,0 +1,34 @@
+//! App-level pools of PostgreSQL connections.
+
+use std::marker::PhantomData;
+
+use spin_connection_pool::ManageConnection;
+use spin_world::async_trait;
+
+use crate::client::Client;
+
+/// Pools of PostgreSQL connections, by address.
+pub type Pools<C> = spin_connection_pool::Pools<ClientManager<C>>;
+
+/// A PostgreSQL connection checked out from a pool.
+pub type PooledClient<C> = spin_connection_pool::PooledConnection<ClientManager<C>>;
+
+/// Manages the pooled connections of a [`Client`] type.
+pub struct ClientManager<C>(PhantomData<C>);
+
+#[async_trait]
+impl<C: Client> ManageConnection for ClientManager<C> {
+    type Connection = C;
+
+    async fn connect(address: &str) -> anyhow::Result<C> {
+        C::build_client(address).await
+    }
+
+    async fn is_healthy(client: &mut C) -> bool {
+        client.is_healthy().await
+    }
+
+    async fn reset(client: &mut C) -> anyhow::Result<()> {
+        client.reset().await
+    }
+}
@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use spin_connection_pool::PoolConfig;
+use spin_factors::{
+    anyhow::{self, Context as _},
+    runtime_config::toml::GetTomlValue,
+};
+
+/// Runtime configuration for outbound PostgreSQL.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// The configuration of the per-address connection pools.
+    #[serde(default)]
+    pub pool: PoolConfig,
+}
+
+/// Resolves the runtime configuration from the `[outbound_pg]` table of a runtime config file.
+///
+/// See [`PoolConfig`] for the `[outbound_pg.pool]` table.
+pub fn runtime_config_from_toml(
+    table: &impl GetTomlValue,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("outbound_pg") else {
+        return Ok(None);
+    };
+    let config = value
+        .clone()
+        .try_into()
+        .context("invalid `[outbound_pg]` runtime config")?;
+    Ok(Some(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn pool_config_from_toml() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_pg.pool]
+            max_size = 4
+            idle_timeout_secs = 10
+        };
+        let config = runtime_config_from_toml(&table)?.unwrap();
+        assert_eq!(config.pool.max_size, 4);
+        assert_eq!(config.pool.idle_timeout, Duration::from_secs(10));
+        assert!(config.pool.health_check);
+
+        let table: toml::Table = toml::toml! {
+            [outbound_pg.pool]
+            max_size = 0
+        };
+        assert!(runtime_config_from_toml(&table).is_err());
+        Ok(())
+    }
+}
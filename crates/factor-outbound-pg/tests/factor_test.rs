@@ -1,4 +1,7 @@
+use std::sync::Mutex;
+
 use anyhow::{bail, Result};
+use spin_core::wasmtime::component::Resource;
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factor_outbound_pg::client::Client;
 use spin_factor_outbound_pg::OutboundPgFactor;
@@ -6,10 +9,10 @@ use spin_factor_variables::VariablesFactor;
 use spin_factors::{anyhow, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::async_trait;
-use spin_world::spin::postgres::postgres::Error as PgError;
-use spin_world::spin::postgres::postgres::HostConnection;
-use spin_world::spin::postgres::postgres::{self as v2};
-use spin_world::spin::postgres::postgres::{ParameterValue, RowSet};
+use spin_world::spin::postgres4_0_0::postgres::Error as PgError;
+use spin_world::spin::postgres4_0_0::postgres::HostConnection;
+use spin_world::spin::postgres4_0_0::postgres::{self as v2};
+use spin_world::spin::postgres4_0_0::postgres::{ParameterValue, RowSet};
 
 #[derive(RuntimeFactors)]
 struct TestFactors {
@@ -103,23 +106,103 @@ async fn exercise_query() -> anyhow::Result<()> {
     Ok(())
 }
 
-// TODO: We can expand this mock to track calls and simulate return values
-pub struct MockClient {}
+#[tokio::test]
+async fn exercise_transaction() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let connection = state
+        .pg
+        .open("postgres://localhost:5432/test".to_string())
+        .await?;
+    let rep = connection.rep();
+
+    let transaction = state.pg.begin(Resource::new_borrow(rep)).await?;
+    let res = state.pg.begin(Resource::new_borrow(rep)).await;
+    assert!(res.is_err(), "only one transaction may be in progress");
+
+    v2::HostTransaction::execute(
+        &mut state.pg,
+        Resource::new_borrow(transaction.rep()),
+        "INSERT INTO test VALUES (1)".to_string(),
+        vec![],
+    )
+    .await?;
+    v2::HostTransaction::commit(&mut state.pg, transaction).await?;
+
+    // A new transaction can begin once the previous one is committed.
+    let transaction = state.pg.begin(Resource::new_borrow(rep)).await?;
+    v2::HostTransaction::rollback(&mut state.pg, transaction).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn dropped_transaction_is_rolled_back() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let address = "postgres://localhost:5432/dropped";
+    let connection = state.pg.open(address.to_string()).await?;
+    let rep = connection.rep();
+    let transaction = state.pg.begin(Resource::new_borrow(rep)).await?;
+    v2::HostTransaction::execute(
+        &mut state.pg,
+        Resource::new_borrow(transaction.rep()),
+        "INSERT INTO test VALUES (1)".to_string(),
+        vec![],
+    )
+    .await?;
+    v2::HostTransaction::drop(&mut state.pg, transaction).await?;
+
+    assert_eq!(
+        MockClient::statements(address),
+        ["BEGIN", "INSERT INTO test VALUES (1)", "ROLLBACK"]
+    );
+    // The connection is free for a new transaction.
+    state.pg.begin(Resource::new_borrow(rep)).await?;
+
+    Ok(())
+}
+
+/// The statements executed by mock clients, with the address of the client.
+static STATEMENTS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+pub struct MockClient {
+    address: String,
+}
+
+impl MockClient {
+    /// The statements executed by mock clients connected to `address`.
+    fn statements(address: &str) -> Vec<String> {
+        STATEMENTS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(a, _)| a == address)
+            .map(|(_, statement)| statement.clone())
+            .collect()
+    }
+}
 
 #[async_trait]
 impl Client for MockClient {
-    async fn build_client(_address: &str) -> anyhow::Result<Self>
+    async fn build_client(address: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        Ok(MockClient {})
+        Ok(MockClient {
+            address: address.to_owned(),
+        })
     }
 
     async fn execute(
         &self,
-        _statement: String,
+        statement: String,
         _params: Vec<ParameterValue>,
     ) -> Result<u64, v2::Error> {
+        STATEMENTS
+            .lock()
+            .unwrap()
+            .push((self.address.clone(), statement));
         Ok(0)
     }
 
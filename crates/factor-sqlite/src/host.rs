@@ -107,6 +107,39 @@ impl v2::HostConnection for InstanceState {
     }
 }
 
+#[async_trait]
+impl spin_world::spin::sqlite::transactions::Host for InstanceState {
+    #[instrument(name = "spin_sqlite.begin", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", sqlite.backend = Empty))]
+    async fn begin(&mut self, connection: Resource<v2::Connection>) -> Result<(), v2::Error> {
+        let conn = self.get_connection(connection)?;
+        tracing::Span::current().record(
+            "sqlite.backend",
+            conn.summary().as_deref().unwrap_or("unknown"),
+        );
+        conn.begin_transaction().await
+    }
+
+    #[instrument(name = "spin_sqlite.commit", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", sqlite.backend = Empty))]
+    async fn commit(&mut self, connection: Resource<v2::Connection>) -> Result<(), v2::Error> {
+        let conn = self.get_connection(connection)?;
+        tracing::Span::current().record(
+            "sqlite.backend",
+            conn.summary().as_deref().unwrap_or("unknown"),
+        );
+        conn.commit().await
+    }
+
+    #[instrument(name = "spin_sqlite.rollback", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", sqlite.backend = Empty))]
+    async fn rollback(&mut self, connection: Resource<v2::Connection>) -> Result<(), v2::Error> {
+        let conn = self.get_connection(connection)?;
+        tracing::Span::current().record(
+            "sqlite.backend",
+            conn.summary().as_deref().unwrap_or("unknown"),
+        );
+        conn.rollback().await
+    }
+}
+
 #[async_trait]
 impl v1::Host for InstanceState {
     async fn open(&mut self, database: String) -> Result<u32, v1::Error> {
@@ -1,4 +1,5 @@
 mod host;
+pub mod migrations;
 pub mod runtime_config;
 
 use std::collections::{HashMap, HashSet};
@@ -37,6 +38,7 @@ impl Factor for SqliteFactor {
     ) -> anyhow::Result<()> {
         ctx.link_bindings(v1::add_to_linker)?;
         ctx.link_bindings(v2::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::sqlite::transactions::add_to_linker)?;
         Ok(())
     }
 
@@ -206,6 +208,31 @@ pub trait Connection: Send + Sync {
 
     async fn execute_batch(&self, statements: &str) -> anyhow::Result<()>;
 
+    /// Begin a transaction. Subsequent queries on this connection run inside the transaction until
+    /// [`Connection::commit`] or [`Connection::rollback`] is called.
+    ///
+    /// Implementations must roll back a transaction which is still in progress when the connection
+    /// is dropped.
+    async fn begin_transaction(&self) -> Result<(), v2::Error> {
+        Err(v2::Error::Io(
+            "transactions are not supported by this database".into(),
+        ))
+    }
+
+    /// Commit the transaction in progress.
+    async fn commit(&self) -> Result<(), v2::Error> {
+        Err(v2::Error::Io(
+            "transactions are not supported by this database".into(),
+        ))
+    }
+
+    /// Roll back the transaction in progress.
+    async fn rollback(&self) -> Result<(), v2::Error> {
+        Err(v2::Error::Io(
+            "transactions are not supported by this database".into(),
+        ))
+    }
+
     /// A human-readable summary of the connection's configuration
     ///
     /// Example: "libSQL at libsql://example.com"
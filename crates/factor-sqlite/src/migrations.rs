@@ -0,0 +1,342 @@
+//! Migrations of the SQLite databases used by an app.
+//!
+//! Components declare a directory of migration scripts per database label in the manifest. The
+//! loader embeds the scripts in the locked app, and they are applied in version order before the
+//! trigger starts. Applied migrations are recorded in a bookkeeping table in each database so that
+//! each is only applied once.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use spin_factors::{
+    anyhow::{self, bail, Context as _},
+    App,
+};
+use spin_locked_app::MetadataKey;
+use spin_world::v2::sqlite as v2;
+
+use crate::{AppState, Connection};
+
+/// Metadata key for the migrations of a component, by database label.
+pub const MIGRATIONS_KEY: MetadataKey<HashMap<String, Vec<Migration>>> =
+    MetadataKey::new("sqlite_migrations");
+
+/// The table in which applied migrations are recorded.
+pub const MIGRATIONS_TABLE: &str = "_spin_migrations";
+
+/// A migration script.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Migration {
+    /// The version of the migration. Migrations are applied in version order.
+    pub version: i64,
+    /// The name of the migration, e.g. `create_users`.
+    pub name: String,
+    /// The SQL statements of the migration.
+    pub sql: String,
+}
+
+/// The migrations which were (or, in a dry run, would be) applied to a database.
+#[derive(Debug)]
+pub struct MigrationReport {
+    /// The label of the database.
+    pub label: String,
+    /// The migrations, in the order they were applied.
+    pub applied: Vec<Migration>,
+}
+
+/// Applies the pending migrations declared by the app's components to each database.
+///
+/// If `dry_run` is true, the databases are not modified, and the report lists the migrations
+/// which would be applied.
+pub async fn migrate(
+    sqlite: &AppState,
+    app: &App,
+    dry_run: bool,
+) -> anyhow::Result<Vec<MigrationReport>> {
+    let mut reports = vec![];
+    for (label, migrations) in app_migrations(app)? {
+        let connection = sqlite
+            .get_connection(&label)
+            .await
+            .with_context(|| format!("SQLite database '{label}' is not defined"))?
+            .with_context(|| format!("failed to connect to SQLite database '{label}'"))?;
+        let pending = pending_migrations(connection.as_ref(), &migrations)
+            .await
+            .with_context(|| format!("failed to check migrations of SQLite database '{label}'"))?;
+        let applied = if dry_run {
+            pending
+        } else {
+            apply_migrations(connection.as_ref(), &pending)
+                .await
+                .with_context(|| format!("failed to migrate SQLite database '{label}'"))?
+        };
+        reports.push(MigrationReport { label, applied });
+    }
+    Ok(reports)
+}
+
+/// Collects the migrations declared by all components of the app, by database label.
+///
+/// Components sharing a database must declare the same migrations for it.
+fn app_migrations(app: &App) -> anyhow::Result<BTreeMap<String, Vec<Migration>>> {
+    let mut declared: BTreeMap<String, (String, Vec<Migration>)> = BTreeMap::new();
+    for component in app.components() {
+        let Some(migrations) = component.get_metadata(MIGRATIONS_KEY)? else {
+            continue;
+        };
+        for (label, mut migrations) in migrations {
+            migrations.sort_by_key(|m| m.version);
+            match declared.get(&label) {
+                Some((other, existing)) if *existing != migrations => bail!(
+                    "components '{other}' and '{}' declare different migrations for SQLite database '{label}'",
+                    component.id()
+                ),
+                Some(_) => {}
+                None => {
+                    declared.insert(label, (component.id().to_owned(), migrations));
+                }
+            }
+        }
+    }
+    Ok(declared
+        .into_iter()
+        .map(|(label, (_, migrations))| (label, migrations))
+        .collect())
+}
+
+/// Returns the migrations which have not yet been applied to the database, in version order.
+async fn pending_migrations(
+    connection: &dyn Connection,
+    migrations: &[Migration],
+) -> anyhow::Result<Vec<Migration>> {
+    let table = connection
+        .query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?",
+            vec![v2::Value::Text(MIGRATIONS_TABLE.to_owned())],
+        )
+        .await?;
+    let mut applied = HashMap::new();
+    if !table.rows.is_empty() {
+        let result = connection
+            .query(
+                &format!("SELECT version, name FROM {MIGRATIONS_TABLE}"),
+                vec![],
+            )
+            .await?;
+        for row in result.rows {
+            match row.values.as_slice() {
+                [v2::Value::Integer(version), v2::Value::Text(name)] => {
+                    applied.insert(*version, name.clone());
+                }
+                other => bail!("unexpected row in {MIGRATIONS_TABLE}: {other:?}"),
+            }
+        }
+    }
+
+    let mut pending = vec![];
+    for migration in migrations {
+        match applied.get(&migration.version) {
+            Some(name) if *name == migration.name => {}
+            Some(name) => bail!(
+                "migration {} was applied as '{name}' but is now named '{}'",
+                migration.version,
+                migration.name
+            ),
+            None => pending.push(migration.clone()),
+        }
+    }
+    pending.sort_by_key(|m| m.version);
+    Ok(pending)
+}
+
+/// Applies the migrations in order, each in its own transaction, and returns those applied.
+///
+/// Another instance of the app may be migrating the same database concurrently, so each
+/// transaction takes the write lock up front and checks again whether its migration has been
+/// applied in the meantime.
+async fn apply_migrations(
+    connection: &dyn Connection,
+    migrations: &[Migration],
+) -> anyhow::Result<Vec<Migration>> {
+    if migrations.is_empty() {
+        return Ok(vec![]);
+    }
+    connection
+        .execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE} (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )"
+        ))
+        .await?;
+    let mut applied = vec![];
+    for migration in migrations {
+        let Migration { version, name, .. } = migration;
+        connection.execute_batch("BEGIN IMMEDIATE").await?;
+        match apply_migration(connection, migration).await {
+            Ok(true) => applied.push(migration.clone()),
+            Ok(false) => {}
+            Err(e) => {
+                // Make sure a failed migration leaves no transaction behind.
+                let _ = connection.execute_batch("ROLLBACK").await;
+                return Err(e.context(format!("migration {version} '{name}' failed")));
+            }
+        }
+    }
+    Ok(applied)
+}
+
+/// Applies a migration inside the transaction in progress, and commits it.
+///
+/// Returns false if the migration turned out to be applied already.
+async fn apply_migration(
+    connection: &dyn Connection,
+    migration: &Migration,
+) -> anyhow::Result<bool> {
+    let Migration { version, name, sql } = migration;
+    let result = connection
+        .query(
+            &format!("SELECT name FROM {MIGRATIONS_TABLE} WHERE version = ?"),
+            vec![v2::Value::Integer(*version)],
+        )
+        .await?;
+    let apply = match result.rows.first().map(|row| row.values.as_slice()) {
+        None => true,
+        Some([v2::Value::Text(applied)]) if applied == name => false,
+        Some([v2::Value::Text(applied)]) => {
+            bail!("migration {version} was applied as '{applied}' but is now named '{name}'")
+        }
+        Some(other) => bail!("unexpected row in {MIGRATIONS_TABLE}: {other:?}"),
+    };
+    if apply {
+        // The script may not end in a semicolon (or may end in a comment), so terminate it.
+        let batch = format!(
+            "{sql}\n;\nINSERT INTO {MIGRATIONS_TABLE} (version, name) VALUES ({version}, '{}');",
+            name.replace('\'', "''")
+        );
+        connection.execute_batch(&batch).await?;
+    }
+    connection.execute_batch("COMMIT").await?;
+    Ok(apply)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// A connection which records batches and reports the given applied migrations.
+    #[derive(Default)]
+    struct MockConnection {
+        applied: Vec<(i64, &'static str)>,
+        batches: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Connection for MockConnection {
+        async fn query(
+            &self,
+            query: &str,
+            parameters: Vec<v2::Value>,
+        ) -> Result<v2::QueryResult, v2::Error> {
+            let rows = if query.contains("sqlite_master") {
+                if self.applied.is_empty() {
+                    vec![]
+                } else {
+                    vec![v2::RowResult {
+                        values: vec![v2::Value::Text(MIGRATIONS_TABLE.into())],
+                    }]
+                }
+            } else if query.contains("WHERE version = ?") {
+                self.applied
+                    .iter()
+                    .filter(|(version, _)| {
+                        matches!(parameters.as_slice(), [v2::Value::Integer(v)] if v == version)
+                    })
+                    .map(|(_, name)| v2::RowResult {
+                        values: vec![v2::Value::Text(name.to_string())],
+                    })
+                    .collect()
+            } else {
+                self.applied
+                    .iter()
+                    .map(|(version, name)| v2::RowResult {
+                        values: vec![
+                            v2::Value::Integer(*version),
+                            v2::Value::Text(name.to_string()),
+                        ],
+                    })
+                    .collect()
+            };
+            Ok(v2::QueryResult {
+                columns: vec![],
+                rows,
+            })
+        }
+
+        async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+            self.batches.lock().unwrap().push(statements.to_owned());
+            Ok(())
+        }
+    }
+
+    fn migration(version: i64, name: &str) -> Migration {
+        Migration {
+            version,
+            name: name.into(),
+            sql: format!("CREATE TABLE {name} (id INTEGER)"),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_unapplied_migrations_are_pending() -> anyhow::Result<()> {
+        let connection = MockConnection {
+            applied: vec![(1, "one")],
+            ..Default::default()
+        };
+        let migrations = [migration(2, "two"), migration(1, "one")];
+        let pending = pending_migrations(&connection, &migrations).await?;
+        assert_eq!(pending, vec![migration(2, "two")]);
+
+        let applied = apply_migrations(&connection, &pending).await?;
+        assert_eq!(applied, pending);
+        let batches = connection.batches.lock().unwrap();
+        assert_eq!(batches.len(), 4);
+        assert_eq!(batches[1], "BEGIN IMMEDIATE");
+        assert!(batches[2].contains("CREATE TABLE two"));
+        assert!(batches[2].contains("VALUES (2, 'two')"));
+        assert_eq!(batches[3], "COMMIT");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrently_applied_migrations_are_skipped() -> anyhow::Result<()> {
+        // Another instance applied migration 2 after the pending migrations were checked.
+        let connection = MockConnection {
+            applied: vec![(1, "one"), (2, "two")],
+            ..Default::default()
+        };
+        let applied = apply_migrations(&connection, &[migration(2, "two")]).await?;
+        assert!(applied.is_empty());
+        let batches = connection.batches.lock().unwrap();
+        assert!(!batches.iter().any(|b| b.contains("CREATE TABLE two")));
+        assert_eq!(batches.last().map(String::as_str), Some("COMMIT"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renamed_migrations_are_rejected() {
+        let connection = MockConnection {
+            applied: vec![(1, "one")],
+            ..Default::default()
+        };
+        let err = pending_migrations(&connection, &[migration(1, "uno")])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("applied as 'one'"), "{err}");
+    }
+}
@@ -1,13 +1,49 @@
 use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Context;
-use spin_app::{App, AppComponent};
+use serde::Deserialize;
+use spin_app::{App, AppComponent, MetadataKey};
 use spin_core::{async_trait, Component};
 use spin_factors::{
     AsInstanceState, ConfiguredApp, Factor, HasInstanceBuilder, RuntimeFactors,
     RuntimeFactorsInstanceState,
 };
 
+/// The component metadata key for [`ComponentLimits`].
+pub const COMPONENT_LIMITS_KEY: MetadataKey<ComponentLimits> = MetadataKey::new("limits");
+
+/// Resource limits applied to each instance of a component.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct ComponentLimits {
+    /// The maximum size of each linear memory, in bytes.
+    pub memory: Option<u64>,
+    /// The maximum number of elements in each table.
+    pub table_elements: Option<u32>,
+    /// The maximum number of (core) instances.
+    pub instances: Option<usize>,
+}
+
+impl ComponentLimits {
+    /// Applies these limits to the given [`spin_core::StoreBuilder`].
+    ///
+    /// Exceeding an explicitly-configured limit traps with an error
+    /// describing the limit.
+    pub fn apply(&self, store_builder: &mut spin_core::StoreBuilder) {
+        if let Some(memory) = self.memory {
+            store_builder.max_memory_size(memory.try_into().unwrap_or(usize::MAX));
+        }
+        if let Some(table_elements) = self.table_elements {
+            store_builder.max_table_elements(table_elements);
+        }
+        if let Some(instances) = self.instances {
+            store_builder.max_instances(instances);
+        }
+        if self.memory.is_some() || self.table_elements.is_some() {
+            store_builder.trap_on_grow_failure(true);
+        }
+    }
+}
+
 /// A FactorsExecutor manages execution of a Spin app.
 ///
 /// It is generic over the executor's [`RuntimeFactors`]. Additionally, it
@@ -64,8 +100,18 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutor<T, U> {
         }
 
         let mut component_instance_pres = HashMap::new();
+        let mut component_limits = HashMap::new();
 
         for app_component in configured_app.app().components() {
+            let limits = app_component
+                .get_metadata(COMPONENT_LIMITS_KEY)
+                .with_context(|| {
+                    format!("invalid limits for component {:?}", app_component.id())
+                })?;
+            if let Some(limits) = limits {
+                component_limits.insert(app_component.id().to_string(), limits);
+            }
+
             let component = component_loader
                 .load_component(self.core_engine.as_ref(), &app_component)
                 .await?;
@@ -78,6 +124,7 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutor<T, U> {
             executor: self.clone(),
             configured_app,
             component_instance_pres,
+            component_limits,
         })
     }
 }
@@ -123,6 +170,8 @@ pub struct FactorsExecutorApp<T: RuntimeFactors, U> {
     configured_app: ConfiguredApp<T>,
     // Maps component IDs -> InstancePres
     component_instance_pres: HashMap<String, InstancePre<T, U>>,
+    // Maps component IDs -> configured resource limits
+    component_limits: HashMap<String, ComponentLimits>,
 }
 
 impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
@@ -161,7 +210,18 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
             .factors
             .prepare(&self.configured_app, component_id)?;
 
-        let store_builder = self.executor.core_engine.store_builder();
+        let mut store_builder = self.executor.core_engine.store_builder();
+        if let Some(limits) = self.component_limits.get(component_id) {
+            limits.apply(&mut store_builder);
+        }
+        let limited_component_id = component_id.to_string();
+        store_builder.on_limit_exceeded(move |resource| {
+            spin_telemetry::metrics::monotonic_counter!(
+                spin.resource_limit_exceeded = 1,
+                component_id = limited_component_id.as_str(),
+                resource = resource
+            );
+        });
 
         let mut builder = FactorsInstanceBuilder {
             store_builder,
@@ -301,7 +361,7 @@ impl<T: RuntimeFactorsInstanceState, U> AsInstanceState<T> for InstanceState<T,
 mod tests {
     use spin_factor_wasi::{DummyFilesMounter, WasiFactor};
     use spin_factors::RuntimeFactors;
-    use spin_factors_test::TestEnvironment;
+    use spin_factors_test::{toml, TestEnvironment};
 
     use super::*;
 
@@ -341,6 +401,44 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn component_limits_are_applied() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors).extend_manifest(toml! {
+            [component.limited]
+            source = "does-not-exist.wasm"
+            limits = { memory = "1 MiB", instances = 0 }
+        });
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+
+        let factors_app = executor
+            .load_app(app, Default::default(), &CoreInstanceComponentLoader)
+            .await?;
+
+        let limits = factors_app.component_limits["limited"];
+        assert_eq!(limits.memory, Some(1 << 20));
+        assert_eq!(limits.instances, Some(0));
+        assert_eq!(limits.table_elements, None);
+
+        let err = factors_app
+            .prepare("limited")?
+            .instantiate(())
+            .await
+            .err()
+            .expect("instantiation should exceed the instance limit");
+        assert!(
+            format!("{err:?}").contains("instance count"),
+            "unexpected error: {err:?}"
+        );
+        Ok(())
+    }
+
     struct DummyComponentLoader;
 
     #[async_trait]
@@ -353,4 +451,20 @@ mod tests {
             Component::new(engine, "(component)")
         }
     }
+
+    struct CoreInstanceComponentLoader;
+
+    #[async_trait]
+    impl ComponentLoader for CoreInstanceComponentLoader {
+        async fn load_component(
+            &self,
+            engine: &spin_core::wasmtime::Engine,
+            _component: &AppComponent,
+        ) -> anyhow::Result<Component> {
+            Component::new(
+                engine,
+                "(component (core module $m) (core instance (instantiate $m)))",
+            )
+        }
+    }
 }
@@ -8,9 +8,106 @@ pub struct HttpTriggerConfig {
     pub component: String,
     /// HTTP route the component will be invoked for
     pub route: HttpTriggerRouteConfig,
+    /// HTTP methods the route is restricted to. If empty, the route matches any method.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<String>,
+    /// Host patterns the route is restricted to, such as `example.com` or `*.example.com`.
+    /// If empty, the route matches any host.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
     /// The HTTP executor the component requires
     #[serde(default)]
     pub executor: Option<HttpExecutorType>,
+    /// Maximum time the component may take to handle a request, such as `"30s"`
+    /// or `"500ms"`. Overrides any server-wide default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Compression of the route's responses. Responses are not compressed if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<HttpCompressionConfig>,
+}
+
+/// Compression of the responses of an HTTP route.
+///
+/// The encoding is negotiated with the client through `Accept-Encoding`. Responses
+/// which already have a `Content-Encoding` are never compressed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpCompressionConfig {
+    /// The encodings which may be used, in order of preference.
+    pub encodings: Vec<ContentEncoding>,
+    /// The content types which may be compressed, such as `application/json`.
+    /// A pattern such as `text/*` matches all subtypes.
+    pub content_types: Vec<String>,
+    /// Responses with a `Content-Length` smaller than this many bytes are not compressed.
+    pub min_size: u64,
+}
+
+impl Default for HttpCompressionConfig {
+    fn default() -> Self {
+        Self {
+            encodings: vec![
+                ContentEncoding::Zstd,
+                ContentEncoding::Br,
+                ContentEncoding::Gzip,
+            ],
+            content_types: [
+                "text/*",
+                "application/javascript",
+                "application/json",
+                "application/wasm",
+                "application/xml",
+                "image/svg+xml",
+            ]
+            .map(String::from)
+            .to_vec(),
+            min_size: 1024,
+        }
+    }
+}
+
+impl HttpCompressionConfig {
+    /// Returns whether responses of the given content type may be compressed.
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        self.content_types.iter().any(|pattern| {
+            let pattern = pattern.trim().to_ascii_lowercase();
+            match pattern.strip_suffix("/*") {
+                Some(ty) => essence
+                    .split_once('/')
+                    .is_some_and(|(essence_ty, _)| essence_ty == ty),
+                None => essence == pattern,
+            }
+        })
+    }
+}
+
+/// A content coding used to compress HTTP responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    /// Gzip compression.
+    Gzip,
+    /// Brotli compression.
+    Br,
+    /// Zstandard compression.
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// The name of the encoding in `Accept-Encoding` and `Content-Encoding` headers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Br => "br",
+            Self::Zstd => "zstd",
+        }
+    }
 }
 
 /// An HTTP trigger route
@@ -105,4 +202,48 @@ mod tests {
         assert_eq!(config.entrypoint, "_start");
         assert_eq!(config.argv, "${SCRIPT_NAME} ${ARGS}");
     }
+
+    #[test]
+    fn route_conditions_default_to_empty() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "test"
+            route = "/items"
+        }
+        .try_into()
+        .unwrap();
+        assert!(config.methods.is_empty());
+        assert!(config.hosts.is_empty());
+
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "test"
+            route = "/items"
+            methods = ["GET", "HEAD"]
+            hosts = ["*.example.com"]
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.methods, ["GET", "HEAD"]);
+        assert_eq!(config.hosts, ["*.example.com"]);
+    }
+
+    #[test]
+    fn compression_config() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "test"
+            route = "/items"
+            [compression]
+            encodings = ["gzip"]
+            content_types = ["text/*", "application/json"]
+        }
+        .try_into()
+        .unwrap();
+        let compression = config.compression.unwrap();
+        assert_eq!(compression.encodings, [ContentEncoding::Gzip]);
+        assert_eq!(compression.min_size, 1024);
+
+        assert!(compression.allows_content_type("text/html; charset=utf-8"));
+        assert!(compression.allows_content_type("Application/JSON"));
+        assert!(!compression.allows_content_type("image/png"));
+        assert!(!compression.allows_content_type("textual/plain"));
+    }
 }
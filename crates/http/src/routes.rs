@@ -2,9 +2,10 @@
 
 #![deny(missing_docs)]
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use http::Method;
 use indexmap::IndexMap;
-use std::{collections::HashMap, fmt};
+use std::{collections::HashMap, fmt, sync::Arc};
 
 use crate::config::HttpTriggerRouteConfig;
 
@@ -12,8 +13,14 @@ use crate::config::HttpTriggerRouteConfig;
 #[derive(Clone, Debug)]
 pub struct Router {
     /// Resolves paths to routing information - specifically component IDs
-    /// but also recording about the original route.
-    router: std::sync::Arc<routefinder::Router<RouteHandler>>,
+    /// but also recording about the original route - for routes which apply
+    /// to any host.
+    router: Arc<routefinder::Router<RouteHandlers>>,
+    /// As `router`, but for routes restricted to particular hosts. These are
+    /// ordered from the most to the least specific host pattern.
+    host_routers: Arc<Vec<(HostPattern, routefinder::Router<RouteHandlers>)>>,
+    /// The reachable routes, in the order they were declared.
+    routes: Arc<Vec<RouteHandler>>,
 }
 
 /// What a route maps to
@@ -28,6 +35,31 @@ struct RouteHandler {
     /// The route, including any application base and capturing information about whether it has a trailing wildcard.
     /// (This avoids re-parsing the route string.)
     parsed_based_route: ParsedRoute,
+    /// The methods and hosts the route is restricted to.
+    conditions: RouteConditions,
+}
+
+/// The handlers for a single route pattern.
+#[derive(Clone, Debug, Default)]
+struct RouteHandlers {
+    /// Handlers which are restricted to particular methods.
+    by_method: IndexMap<Method, RouteHandler>,
+    /// The handler for any method without a more specific handler.
+    any_method: Option<RouteHandler>,
+}
+
+impl RouteHandlers {
+    /// Returns the handler for the given method. If no method is given, method
+    /// restrictions are ignored.
+    fn get(&self, method: Option<&Method>) -> Option<&RouteHandler> {
+        match method {
+            Some(method) => self.by_method.get(method).or(self.any_method.as_ref()),
+            None => self
+                .any_method
+                .as_ref()
+                .or_else(|| self.by_method.values().next()),
+        }
+    }
 }
 
 /// A detected duplicate route.
@@ -41,30 +73,171 @@ pub struct DuplicateRoute {
     pub effective_id: String,
 }
 
+/// Conditions, in addition to the path, which a request must satisfy to match a route.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RouteConditions {
+    /// The methods the route is restricted to. If empty, any method matches.
+    methods: Vec<Method>,
+    /// The hosts the route is restricted to. If empty, any host matches.
+    hosts: Vec<HostPattern>,
+}
+
+impl RouteConditions {
+    /// Parses route conditions from the method names and host patterns given in
+    /// the trigger configuration. An empty list places no restriction.
+    pub fn parse(methods: &[impl AsRef<str>], hosts: &[impl AsRef<str>]) -> Result<Self> {
+        let mut conditions = Self::default();
+        for method in methods {
+            let method = method.as_ref();
+            let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .with_context(|| format!("invalid HTTP method '{method}'"))?;
+            if !conditions.methods.contains(&method) {
+                conditions.methods.push(method);
+            }
+        }
+        for host in hosts {
+            let host = HostPattern::parse(host.as_ref())?;
+            if !conditions.hosts.contains(&host) {
+                conditions.hosts.push(host);
+            }
+        }
+        Ok(conditions)
+    }
+
+    /// The combinations of host and method covered by these conditions, where
+    /// `None` stands for "any".
+    fn combinations(&self) -> Vec<(Option<&HostPattern>, Option<&Method>)> {
+        let hosts = if self.hosts.is_empty() {
+            vec![None]
+        } else {
+            self.hosts.iter().map(Some).collect()
+        };
+        let methods = if self.methods.is_empty() {
+            vec![None]
+        } else {
+            self.methods.iter().map(Some).collect()
+        };
+        hosts
+            .into_iter()
+            .flat_map(|host| methods.iter().map(move |method| (host, *method)))
+            .collect()
+    }
+}
+
+/// A pattern matching the host a request was made to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum HostPattern {
+    /// Matches exactly the given host.
+    Exact(String),
+    /// Matches any subdomain of the given domain. The suffix includes the leading `.`.
+    Subdomain(String),
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        let pattern = pattern.to_ascii_lowercase();
+        let (is_wildcard, host) = match pattern.strip_prefix("*.") {
+            Some(domain) => (true, domain),
+            None => (false, pattern.as_str()),
+        };
+        if host.is_empty() || host.contains(|c| matches!(c, '*' | ':' | '/')) {
+            anyhow::bail!("invalid host pattern '{pattern}': expected a host name such as 'example.com' or '*.example.com'");
+        }
+        Ok(if is_wildcard {
+            Self::Subdomain(format!(".{host}"))
+        } else {
+            Self::Exact(host.to_owned())
+        })
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::Exact(exact) => host == exact,
+            Self::Subdomain(suffix) => host.len() > suffix.len() && host.ends_with(suffix.as_str()),
+        }
+    }
+
+    /// Sort key which orders patterns from most to least specific.
+    fn precedence(&self) -> (bool, std::cmp::Reverse<usize>) {
+        match self {
+            Self::Exact(exact) => (false, std::cmp::Reverse(exact.len())),
+            Self::Subdomain(suffix) => (true, std::cmp::Reverse(suffix.len())),
+        }
+    }
+}
+
+impl fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(exact) => f.write_str(exact),
+            Self::Subdomain(suffix) => write!(f, "*{suffix}"),
+        }
+    }
+}
+
+/// The reason a request could not be routed.
+#[derive(Debug)]
+pub enum RoutingError {
+    /// No route matched the request.
+    NotFound,
+    /// A route matched the request path, but not the request method.
+    MethodNotAllowed {
+        /// The methods accepted by the routes that matched the path.
+        allowed: Vec<Method>,
+    },
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("no route matched the request"),
+            Self::MethodNotAllowed { .. } => {
+                f.write_str("a route matched the request path but not the request method")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
 impl Router {
     /// Builds a router based on application configuration.
     pub fn build<'a>(
         base: &str,
         component_routes: impl IntoIterator<Item = (&'a str, &'a HttpTriggerRouteConfig)>,
+    ) -> Result<(Self, Vec<DuplicateRoute>)> {
+        Self::build_with_conditions(
+            base,
+            component_routes
+                .into_iter()
+                .map(|(component_id, route)| (component_id, route, RouteConditions::default())),
+        )
+    }
+
+    /// Builds a router based on application configuration, where routes may
+    /// additionally be restricted to particular methods and hosts.
+    pub fn build_with_conditions<'a>(
+        base: &str,
+        component_routes: impl IntoIterator<
+            Item = (&'a str, &'a HttpTriggerRouteConfig, RouteConditions),
+        >,
     ) -> Result<(Self, Vec<DuplicateRoute>)> {
         // Some information we need to carry between stages of the builder.
         struct RoutingEntry<'a> {
             based_route: String,
             raw_route: &'a str,
             component_id: &'a str,
+            conditions: RouteConditions,
         }
 
-        let mut routes = IndexMap::new();
-        let mut duplicates = vec![];
-
         // Filter out private endpoints and capture the routes.
-        let routes_iter = component_routes
+        let entries = component_routes
             .into_iter()
-            .filter_map(|(component_id, route)| {
+            .filter_map(|(component_id, route, conditions)| {
                 match route {
                     HttpTriggerRouteConfig::Route(raw_route) => {
                         let based_route = sanitize_with_base(base, raw_route);
-                        Some(Ok(RoutingEntry { based_route, raw_route, component_id }))
+                        Some(Ok(RoutingEntry { based_route, raw_route, component_id, conditions }))
                     }
                     HttpTriggerRouteConfig::Private(endpoint) => if endpoint.private {
                         None
@@ -75,25 +248,38 @@ impl Router {
             })
             .collect::<Result<Vec<_>>>()?;
 
-        // Remove duplicates.
-        for re in routes_iter {
-            let effective_id = re.component_id.to_string();
-            let replaced = routes.insert(re.raw_route, re);
-            if let Some(replaced) = replaced {
-                duplicates.push(DuplicateRoute {
-                    route: replaced.based_route,
-                    replaced_id: replaced.component_id.to_string(),
-                    effective_id,
-                });
+        // Remove duplicates. Each (host, route, method) combination an entry covers is a
+        // slot; where entries claim the same slot the last one wins, and entries left
+        // without any slots are unreachable.
+        let mut slots = IndexMap::new();
+        let mut remaining_slots = vec![0usize; entries.len()];
+        let mut duplicates = vec![];
+        for (index, re) in entries.iter().enumerate() {
+            for (host, method) in re.conditions.combinations() {
+                remaining_slots[index] += 1;
+                let Some(replaced) = slots.insert((host, re.raw_route, method), index) else {
+                    continue;
+                };
+                remaining_slots[replaced] -= 1;
+                if remaining_slots[replaced] == 0 {
+                    let replaced = &entries[replaced];
+                    duplicates.push(DuplicateRoute {
+                        route: replaced.based_route.clone(),
+                        replaced_id: replaced.component_id.to_string(),
+                        effective_id: re.component_id.to_string(),
+                    });
+                }
             }
         }
 
-        // Build a `routefinder` from the remaining routes.
-
-        let mut rf = routefinder::Router::new();
-
-        for re in routes.into_values() {
-            let (rfroute, parsed) = Self::parse_route(&re.based_route).map_err(|e| {
+        // Build handlers for the remaining routes.
+        let mut handlers = HashMap::new();
+        let mut routes = vec![];
+        for (index, re) in entries.iter().enumerate() {
+            if remaining_slots[index] == 0 {
+                continue;
+            }
+            let (_, parsed) = Self::parse_route(&re.based_route).map_err(|e| {
                 anyhow!(
                     "Error parsing route {} associated with component {}: {e}",
                     re.based_route,
@@ -103,16 +289,59 @@ impl Router {
 
             let handler = RouteHandler {
                 component_id: re.component_id.to_string(),
-                based_route: re.based_route,
+                based_route: re.based_route.clone(),
                 raw_route: re.raw_route.to_string(),
                 parsed_based_route: parsed,
+                conditions: re.conditions.clone(),
             };
+            handlers.insert(index, handler.clone());
+            routes.push(handler);
+        }
 
-            rf.add(rfroute, handler).map_err(|e| anyhow!("{e}"))?;
+        // Group the handlers by host and route pattern.
+        let mut groups: IndexMap<Option<&HostPattern>, IndexMap<&str, RouteHandlers>> =
+            IndexMap::new();
+        for ((host, _, method), index) in slots {
+            let handler = handlers[&index].clone();
+            let route_handlers = groups
+                .entry(host)
+                .or_default()
+                .entry(entries[index].based_route.as_str())
+                .or_default();
+            match method {
+                Some(method) => {
+                    route_handlers.by_method.insert(method.clone(), handler);
+                }
+                None => route_handlers.any_method = Some(handler),
+            }
         }
 
+        // Build a `routefinder` for each host from the remaining routes.
+        let mut router = routefinder::Router::new();
+        let mut host_routers = vec![];
+
+        for (host, host_routes) in groups {
+            let mut rf = routefinder::Router::new();
+
+            for (based_route, route_handlers) in host_routes {
+                let (rfroute, _) = Self::parse_route(based_route)
+                    .map_err(|e| anyhow!("Error parsing route {based_route}: {e}"))?;
+                rf.add(rfroute, route_handlers)
+                    .map_err(|e| anyhow!("{e}"))?;
+            }
+
+            match host {
+                Some(host) => host_routers.push((host.clone(), rf)),
+                None => router = rf,
+            }
+        }
+
+        host_routers.sort_by_key(|(host, _)| host.precedence());
+
         let router = Self {
-            router: std::sync::Arc::new(rf),
+            router: Arc::new(router),
+            host_routers: Arc::new(host_routers),
+            routes: Arc::new(routes),
         };
 
         Ok((router, duplicates))
@@ -136,52 +365,116 @@ impl Router {
 
     /// Returns the constructed routes.
     pub fn routes(&self) -> impl Iterator<Item = (&(impl fmt::Display + fmt::Debug), &String)> {
-        self.router
+        self.routes
             .iter()
-            .map(|(_spec, handler)| (&handler.parsed_based_route, &handler.component_id))
+            .map(|handler| (handler, &handler.component_id))
     }
 
     /// This returns the component ID that should handle the given path, or an error
     /// if no component matches.
     ///
+    /// Method restrictions are ignored, and routes restricted to particular hosts are
+    /// not considered. Use [`Router::route_request`] to route a full request.
+    ///
     /// If multiple components could potentially handle the same request based on their
     /// defined routes, components with matching exact routes take precedence followed
     /// by matching wildcard patterns with the longest matching prefix.
     pub fn route(&self, p: &str) -> Result<RouteMatch> {
-        let best_match = self
-            .router
-            .best_match(p)
-            .ok_or_else(|| anyhow!("Cannot match route for path {p}"))?;
-
-        let route_handler = best_match.handler().clone();
-        let named_wildcards = best_match
-            .captures()
+        self.route_impl(None, None, p)
+            .map_err(|_| anyhow!("Cannot match route for path {p}"))
+    }
+
+    /// This returns the component ID that should handle a request with the given
+    /// method, host and path.
+    ///
+    /// Routes restricted to the request's host take precedence over unrestricted
+    /// routes, with exact host patterns preferred over wildcard patterns. Within
+    /// those, paths are matched as for [`Router::route`], and a route restricted to
+    /// the request's method is preferred over an unrestricted route for the same
+    /// path. If a path matches but no route accepts the method, this returns
+    /// [`RoutingError::MethodNotAllowed`].
+    pub fn route_request(
+        &self,
+        method: &Method,
+        host: Option<&str>,
+        path: &str,
+    ) -> Result<RouteMatch, RoutingError> {
+        self.route_impl(Some(method), host, path)
+    }
+
+    fn route_impl(
+        &self,
+        method: Option<&Method>,
+        host: Option<&str>,
+        p: &str,
+    ) -> Result<RouteMatch, RoutingError> {
+        let host = host.and_then(normalize_host);
+        let host_routers = self
+            .host_routers
             .iter()
-            .map(|(k, v)| (k.to_owned(), v.to_owned()))
-            .collect();
-        let trailing_wildcard = best_match.captures().wildcard().map(|s|
-            // Backward compatibility considerations - Spin has traditionally
-            // captured trailing slashes, but routefinder does not.
-            match (s.is_empty(), p.ends_with('/')) {
-                // route: /foo/..., path: /foo
-                (true, false) => s.to_owned(),
-                // route: /foo/..., path: /foo/
-                (true, true) => "/".to_owned(),
-                // route: /foo/..., path: /foo/bar
-                (false, false) => format!("/{s}"),
-                // route: /foo/..., path: /foo/bar/
-                (false, true) => format!("/{s}/"),
+            .filter(|(pattern, _)| host.as_deref().is_some_and(|host| pattern.matches(host)))
+            .map(|(_, rf)| rf);
+
+        // A more specific route which doesn't accept the method doesn't hide a less
+        // specific one which does, so every matching route is considered, from the
+        // most to the least specific.
+        let mut allowed = vec![];
+        for rf in host_routers.chain([self.router.as_ref()]) {
+            for route_match in rf.matches(p).into_iter().rev() {
+                let route_handlers = route_match.handler();
+                let Some(route_handler) = route_handlers.get(method) else {
+                    for method in route_handlers.by_method.keys() {
+                        if !allowed.contains(method) {
+                            allowed.push(method.clone());
+                        }
+                    }
+                    continue;
+                };
+
+                let route_handler = route_handler.clone();
+                let named_wildcards = route_match
+                    .captures()
+                    .iter()
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                    .collect();
+                let trailing_wildcard = route_match.captures().wildcard().map(|s|
+                    // Backward compatibility considerations - Spin has traditionally
+                    // captured trailing slashes, but routefinder does not.
+                    match (s.is_empty(), p.ends_with('/')) {
+                        // route: /foo/..., path: /foo
+                        (true, false) => s.to_owned(),
+                        // route: /foo/..., path: /foo/
+                        (true, true) => "/".to_owned(),
+                        // route: /foo/..., path: /foo/bar
+                        (false, false) => format!("/{s}"),
+                        // route: /foo/..., path: /foo/bar/
+                        (false, true) => format!("/{s}/"),
+                    }
+                );
+
+                return Ok(RouteMatch {
+                    route_handler,
+                    named_wildcards,
+                    trailing_wildcard,
+                });
             }
-        );
+        }
 
-        Ok(RouteMatch {
-            route_handler,
-            named_wildcards,
-            trailing_wildcard,
-        })
+        if allowed.is_empty() {
+            Err(RoutingError::NotFound)
+        } else {
+            Err(RoutingError::MethodNotAllowed { allowed })
+        }
     }
 }
 
+/// Extracts the lowercased host name, without any port, from a `Host` header
+/// value or URI authority.
+fn normalize_host(host: &str) -> Option<String> {
+    let authority: http::uri::Authority = host.parse().ok()?;
+    Some(authority.host().trim_end_matches('.').to_ascii_lowercase())
+}
+
 impl DuplicateRoute {
     /// The duplicated route pattern.
     pub fn route(&self) -> &str {
@@ -218,6 +511,22 @@ impl fmt::Display for ParsedRoute {
     }
 }
 
+impl fmt::Display for RouteHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.parsed_based_route)?;
+        let RouteConditions { methods, hosts } = &self.conditions;
+        if !methods.is_empty() {
+            let methods = methods.iter().map(Method::as_str).collect::<Vec<_>>();
+            write!(f, " (methods: {})", methods.join(", "))?;
+        }
+        if !hosts.is_empty() {
+            let hosts = hosts.iter().map(ToString::to_string).collect::<Vec<_>>();
+            write!(f, " (hosts: {})", hosts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
 /// A routing match for a URL.
 pub struct RouteMatch {
     route_handler: RouteHandler,
@@ -235,6 +544,7 @@ impl RouteMatch {
                 based_route: "/...".to_string(),
                 raw_route: "/...".to_string(),
                 parsed_based_route: ParsedRoute::TrailingWildcard(String::new()),
+                conditions: RouteConditions::default(),
             },
             named_wildcards: Default::default(),
             trailing_wildcard: Some(path.to_string()),
@@ -593,4 +903,185 @@ mod route_tests {
         let m = routes.route("/1/2/3").expect("/1/2/3 should have matched");
         assert_eq!("2", m.named_wildcards()["two"]);
     }
+
+    fn conditions(methods: &[&str], hosts: &[&str]) -> RouteConditions {
+        RouteConditions::parse(methods, hosts).unwrap()
+    }
+
+    #[test]
+    fn methods_route_to_different_components() -> Result<()> {
+        let items: HttpTriggerRouteConfig = "/items".into();
+        let (r, dups) = Router::build_with_conditions(
+            "/",
+            [
+                ("get-items", &items, conditions(&["GET"], &[])),
+                ("post-items", &items, conditions(&["post"], &[])),
+            ],
+        )?;
+        assert!(dups.is_empty());
+
+        let m = r.route_request(&Method::GET, None, "/items")?;
+        assert_eq!(m.component_id(), "get-items");
+        let m = r.route_request(&Method::POST, None, "/items")?;
+        assert_eq!(m.component_id(), "post-items");
+        Ok(())
+    }
+
+    #[test]
+    fn unmatched_method_is_not_allowed() -> Result<()> {
+        let items: HttpTriggerRouteConfig = "/items".into();
+        let (r, _dups) = Router::build_with_conditions(
+            "/",
+            [
+                ("get-items", &items, conditions(&["GET", "HEAD"], &[])),
+                ("post-items", &items, conditions(&["POST"], &[])),
+            ],
+        )?;
+
+        let Err(RoutingError::MethodNotAllowed { allowed }) =
+            r.route_request(&Method::DELETE, None, "/items")
+        else {
+            panic!("DELETE /items should not have been allowed");
+        };
+        assert_eq!(allowed, [Method::GET, Method::HEAD, Method::POST]);
+
+        assert!(matches!(
+            r.route_request(&Method::GET, None, "/other"),
+            Err(RoutingError::NotFound)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn method_specific_route_beats_any_method() -> Result<()> {
+        let items: HttpTriggerRouteConfig = "/items".into();
+        let (r, dups) = Router::build_with_conditions(
+            "/",
+            [
+                ("any", &items, RouteConditions::default()),
+                ("delete", &items, conditions(&["DELETE"], &[])),
+            ],
+        )?;
+        assert!(dups.is_empty());
+
+        assert_eq!(
+            r.route_request(&Method::DELETE, None, "/items")?
+                .component_id(),
+            "delete"
+        );
+        assert_eq!(
+            r.route_request(&Method::PUT, None, "/items")?
+                .component_id(),
+            "any"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn method_restricted_route_does_not_hide_less_specific_route() -> Result<()> {
+        let all: HttpTriggerRouteConfig = "/...".into();
+        let api: HttpTriggerRouteConfig = "/api/...".into();
+        let (r, dups) = Router::build_with_conditions(
+            "/",
+            [
+                ("catch-all", &all, RouteConditions::default()),
+                ("api-get", &api, conditions(&["GET"], &[])),
+            ],
+        )?;
+        assert!(dups.is_empty());
+
+        let route = |method| r.route_request(method, None, "/api/items").unwrap();
+        assert_eq!(route(&Method::GET).component_id(), "api-get");
+        assert_eq!(route(&Method::POST).component_id(), "catch-all");
+        assert_eq!(
+            route(&Method::POST).trailing_wildcard(),
+            "/api/items",
+            "wildcard captures should come from the route which matched"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hosts_route_to_different_components() -> Result<()> {
+        let all: HttpTriggerRouteConfig = "/...".into();
+        let api: HttpTriggerRouteConfig = "/api".into();
+        let (r, dups) = Router::build_with_conditions(
+            "/",
+            [
+                ("fallback", &all, RouteConditions::default()),
+                ("wildcard", &all, conditions(&[], &["*.example.com"])),
+                ("exact", &all, conditions(&[], &["www.example.com"])),
+                ("api", &api, conditions(&[], &["api.example.com"])),
+            ],
+        )?;
+        assert!(dups.is_empty());
+
+        let route = |host| r.route_request(&Method::GET, host, "/api").unwrap();
+        assert_eq!(route(Some("www.example.com")).component_id(), "exact");
+        assert_eq!(route(Some("WWW.Example.com:3000")).component_id(), "exact");
+        assert_eq!(route(Some("foo.example.com")).component_id(), "wildcard");
+        assert_eq!(route(Some("api.example.com")).component_id(), "api");
+        assert_eq!(route(Some("example.com")).component_id(), "fallback");
+        assert_eq!(route(None).component_id(), "fallback");
+
+        // Host-specific routes fall back to unrestricted routes for unmatched paths
+        let m = r.route_request(&Method::GET, Some("api.example.com"), "/other")?;
+        assert_eq!(m.component_id(), "wildcard");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_conditions_are_rejected() {
+        RouteConditions::parse(&["GET POST"], &[] as &[&str])
+            .expect_err("should not accept a method containing a space");
+        RouteConditions::parse(&[] as &[&str], &["foo.*.com"])
+            .expect_err("should not accept an inner wildcard");
+        RouteConditions::parse(&[] as &[&str], &["example.com:3000"])
+            .expect_err("should not accept a port");
+    }
+
+    #[test]
+    fn duplicates_take_conditions_into_account() {
+        let items: HttpTriggerRouteConfig = "/items".into();
+        let (routes, duplicates) = Router::build_with_conditions(
+            "/",
+            [
+                ("get-and-post", &items, conditions(&["GET", "POST"], &[])),
+                ("get", &items, conditions(&["GET"], &[])),
+                (
+                    "post-on-host",
+                    &items,
+                    conditions(&["POST"], &["example.com"]),
+                ),
+                ("post", &items, conditions(&["POST"], &[])),
+            ],
+        )
+        .unwrap();
+
+        // "get-and-post" has had both of its methods taken over
+        assert_eq!(1, duplicates.len());
+        assert_eq!("get-and-post", duplicates[0].replaced_id);
+        assert_eq!("post", duplicates[0].effective_id);
+        assert_eq!(3, routes.routes().count());
+    }
+
+    #[test]
+    fn conditional_routes_use_custom_display() {
+        let route: HttpTriggerRouteConfig = "/whee/...".into();
+        let (routes, _dups) = Router::build_with_conditions(
+            "/",
+            [(
+                "comp",
+                &route,
+                conditions(&["GET", "PUT"], &["*.example.com"]),
+            )],
+        )
+        .unwrap();
+
+        let (route, _) = routes.routes().next().unwrap();
+        assert_eq!(
+            "/whee (wildcard) (methods: GET, PUT) (hosts: *.example.com)",
+            format!("{route}")
+        );
+    }
 }
@@ -2,6 +2,7 @@ use core::str;
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
@@ -11,7 +12,7 @@ use aws_sdk_dynamodb::{
     config::{ProvideCredentials, SharedCredentialsProvider},
     operation::{
         batch_get_item::BatchGetItemOutput, batch_write_item::BatchWriteItemOutput,
-        get_item::GetItemOutput,
+        get_item::GetItemOutput, scan::ScanOutput,
     },
     primitives::Blob,
     types::{
@@ -21,7 +22,9 @@ use aws_sdk_dynamodb::{
     Client,
 };
 use spin_core::async_trait;
-use spin_factor_key_value::{log_error, Cas, Error, Store, StoreManager, SwapError};
+use spin_factor_key_value::{
+    log_error, Cas, Error, KeyPage, Store, StoreManager, SwapError, LIST_KEYS_PAGE_SIZE,
+};
 
 pub struct KeyValueAwsDynamo {
     /// AWS region
@@ -154,6 +157,8 @@ enum CasState {
     Unversioned(Blob),
     // Item was missing when fetched during `current`, expected to be new
     Unset,
+    // Item had expired when fetched during `current`, expected to still be expired
+    Expired,
     // Potentially new item -- `current` was never called to fetch version
     Unknown,
 }
@@ -172,6 +177,29 @@ const PK: &str = "PK";
 const VAL: &str = "VAL";
 /// Version key in DynamoDB items used for atomic operations
 const VER: &str = "VER";
+/// Expiry key in DynamoDB items storing the expiry time in epoch seconds. Enable TTL on the table
+/// with this attribute to have DynamoDB delete expired items.
+const EXP: &str = "EXP";
+
+/// A filter expression matching only items which have not expired, with `:now` as the current time.
+const NOT_EXPIRED_FILTER: &str = "(attribute_not_exists(EXP) OR EXP > :now)";
+
+/// The current time in epoch seconds.
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Returns whether the item has expired. DynamoDB deletes expired items lazily (typically within a
+/// few days), so expired items must be filtered out when reading.
+fn is_expired(item: &HashMap<String, AttributeValue>) -> bool {
+    match item.get(EXP) {
+        Some(AttributeValue::N(exp)) => exp.parse::<u64>().is_ok_and(|exp| exp <= now_secs()),
+        _ => false,
+    }
+}
 
 #[async_trait]
 impl Store for AwsDynamoStore {
@@ -185,18 +213,21 @@ impl Store for AwsDynamoStore {
                 PK,
                 aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
             )
-            .projection_expression(VAL)
+            .projection_expression(format!("{VAL},{EXP}"))
             .send()
             .await
             .map_err(log_error)?;
 
-        let item = response.item.and_then(|mut item| {
-            if let Some(AttributeValue::B(val)) = item.remove(VAL) {
-                Some(val.into_inner())
-            } else {
-                None
-            }
-        });
+        let item = response
+            .item
+            .filter(|item| !is_expired(item))
+            .and_then(|mut item| {
+                if let Some(AttributeValue::B(val)) = item.remove(VAL) {
+                    Some(val.into_inner())
+                } else {
+                    None
+                }
+            });
 
         Ok(item)
     }
@@ -213,6 +244,22 @@ impl Store for AwsDynamoStore {
         Ok(())
     }
 
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // Round up so that the entry lives for at least `ttl`.
+        let ttl_secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+        let expires_at = now_secs().saturating_add(ttl_secs);
+        self.client
+            .put_item()
+            .table_name(self.table.as_str())
+            .item(PK, AttributeValue::S(key.to_string()))
+            .item(VAL, AttributeValue::B(Blob::new(value)))
+            .item(EXP, AttributeValue::N(expires_at.to_string()))
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
     async fn delete(&self, key: &str) -> Result<(), Error> {
         self.client
             .delete_item()
@@ -234,12 +281,15 @@ impl Store for AwsDynamoStore {
                 PK,
                 aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
             )
-            .projection_expression(PK)
+            .projection_expression(format!("{PK},{EXP}"))
             .send()
             .await
             .map_err(log_error)?;
 
-        Ok(item.map(|item| item.contains_key(PK)).unwrap_or(false))
+        Ok(item
+            .filter(|item| !is_expired(item))
+            .map(|item| item.contains_key(PK))
+            .unwrap_or(false))
     }
 
     async fn get_keys(&self) -> Result<Vec<String>, Error> {
@@ -250,6 +300,8 @@ impl Store for AwsDynamoStore {
             .scan()
             .table_name(self.table.as_str())
             .projection_expression(PK)
+            .filter_expression(NOT_EXPIRED_FILTER)
+            .expression_attribute_values(":now", AttributeValue::N(now_secs().to_string()))
             .into_paginator()
             .send();
 
@@ -267,10 +319,60 @@ impl Store for AwsDynamoStore {
         Ok(primary_keys)
     }
 
+    /// `list_keys` scans a single page of the table, using the primary key of the last evaluated
+    /// item as the cursor. Since DynamoDB applies the prefix filter after selecting a page, a page
+    /// may contain fewer keys than the page size (or none at all) even though more keys follow.
+    async fn list_keys(
+        &self,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<KeyPage, Error> {
+        let mut scan = self
+            .client
+            .scan()
+            .table_name(self.table.as_str())
+            .projection_expression(PK)
+            .limit(LIST_KEYS_PAGE_SIZE as i32)
+            .expression_attribute_values(":now", AttributeValue::N(now_secs().to_string()));
+        if let Some(prefix) = prefix.filter(|p| !p.is_empty()) {
+            scan = scan
+                .filter_expression(format!(
+                    "begins_with({PK}, :prefix) AND {NOT_EXPIRED_FILTER}"
+                ))
+                .expression_attribute_values(":prefix", AttributeValue::S(prefix.to_owned()));
+        } else {
+            scan = scan.filter_expression(NOT_EXPIRED_FILTER);
+        }
+        if let Some(cursor) = cursor {
+            scan = scan.exclusive_start_key(PK, AttributeValue::S(cursor));
+        }
+
+        let ScanOutput {
+            items,
+            last_evaluated_key,
+            ..
+        } = scan.send().await.map_err(log_error)?;
+
+        let keys = items
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|mut item| match item.remove(PK) {
+                Some(AttributeValue::S(pk)) => Some(pk),
+                _ => None,
+            })
+            .collect();
+        let cursor = last_evaluated_key.and_then(|mut key| match key.remove(PK) {
+            Some(AttributeValue::S(pk)) => Some(pk),
+            _ => None,
+        });
+
+        Ok(KeyPage { keys, cursor })
+    }
+
     async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
         let mut results = Vec::with_capacity(keys.len());
         let mut keys_and_attributes_builder = KeysAndAttributes::builder()
-            .projection_expression(format!("{PK},{VAL}"))
+            .projection_expression(format!("{PK},{VAL},{EXP}"))
             .consistent_read(self.consistent_read);
         for key in keys {
             keys_and_attributes_builder = keys_and_attributes_builder.keys(HashMap::from_iter([(
@@ -300,7 +402,11 @@ impl Store for AwsDynamoStore {
                 responses.and_then(|mut responses| responses.remove(self.table.as_str()))
             {
                 for mut item in items {
+                    let expired = is_expired(&item);
                     match (item.remove(PK), item.remove(VAL)) {
+                        (Some(AttributeValue::S(pk)), _) if expired => {
+                            results.push((pk, None));
+                        }
                         (Some(AttributeValue::S(pk)), Some(AttributeValue::B(val))) => {
                             results.push((pk, Some(val.into_inner())));
                         }
@@ -394,12 +500,14 @@ impl Store for AwsDynamoStore {
             .consistent_read(true)
             .table_name(self.table.as_str())
             .key(PK, AttributeValue::S(key.clone()))
-            .projection_expression(VAL)
+            .projection_expression(format!("{VAL},{EXP}"))
             .send()
             .await
             .map_err(log_error)?;
 
-        let old_val = match item {
+        // An expired item counts as missing, but still physically exists until DynamoDB deletes it.
+        let expired = item.as_ref().is_some_and(is_expired);
+        let old_val = match item.filter(|_| !expired) {
             Some(mut current_item) => match current_item.remove(VAL) {
                 // We're expecting i64, so technically we could transmute but seems risky...
                 Some(AttributeValue::B(val)) => Some(
@@ -418,22 +526,30 @@ impl Store for AwsDynamoStore {
         let mut update = Update::builder()
             .table_name(self.table.as_str())
             .key(PK, AttributeValue::S(key))
-            .update_expression("SET #VAL = :new_val")
             .expression_attribute_names("#VAL", VAL)
             .expression_attribute_values(
                 ":new_val",
                 AttributeValue::B(Blob::new(new_val.to_string().as_bytes())),
             );
 
-        if let Some(old_val) = old_val {
+        if expired {
+            update = update
+                .update_expression("SET #VAL = :new_val REMOVE #EXP")
+                .condition_expression("#EXP <= :now")
+                .expression_attribute_names("#EXP", EXP)
+                .expression_attribute_values(":now", AttributeValue::N(now_secs().to_string()))
+        } else if let Some(old_val) = old_val {
             update = update
+                .update_expression("SET #VAL = :new_val")
                 .condition_expression("#VAL = :old_val")
                 .expression_attribute_values(
                     ":old_val",
                     AttributeValue::B(Blob::new(old_val.to_string().as_bytes())),
                 )
         } else {
-            update = update.condition_expression("attribute_not_exists (#VAL)")
+            update = update
+                .update_expression("SET #VAL = :new_val")
+                .condition_expression("attribute_not_exists (#VAL)")
         }
 
         self.client
@@ -474,11 +590,16 @@ impl Cas for CompareAndSwap {
             .consistent_read(true)
             .table_name(self.table.as_str())
             .key(PK, AttributeValue::S(self.key.clone()))
-            .projection_expression(format!("{VAL},{VER}"))
+            .projection_expression(format!("{VAL},{VER},{EXP}"))
             .send()
             .await
             .map_err(log_error)?;
 
+        if item.as_ref().is_some_and(is_expired) {
+            self.state.lock().unwrap().clone_from(&CasState::Expired);
+            return Ok(None);
+        }
+
         match item {
             Some(mut current_item) => match (current_item.remove(VAL), current_item.remove(VER)) {
                 (Some(AttributeValue::B(val)), Some(AttributeValue::N(ver))) => {
@@ -515,9 +636,10 @@ impl Cas for CompareAndSwap {
         let mut update = Update::builder()
             .table_name(self.table.as_str())
             .key(PK, AttributeValue::S(self.key.clone()))
-            .update_expression("SET #VAL = :val ADD #VER :increment")
+            .update_expression("SET #VAL = :val ADD #VER :increment REMOVE #EXP")
             .expression_attribute_names("#VAL", VAL)
             .expression_attribute_names("#VER", VER)
+            .expression_attribute_names("#EXP", EXP)
             .expression_attribute_values(":val", AttributeValue::B(Blob::new(value)))
             .expression_attribute_values(":increment", AttributeValue::N("1".to_owned()));
 
@@ -536,6 +658,11 @@ impl Cas for CompareAndSwap {
             CasState::Unset => {
                 update = update.condition_expression("attribute_not_exists (#VAL)");
             }
+            CasState::Expired => {
+                update = update
+                    .condition_expression("#EXP <= :now")
+                    .expression_attribute_values(":now", AttributeValue::N(now_secs().to_string()));
+            }
             CasState::Unknown => (),
         };
 
@@ -2,14 +2,18 @@ use anyhow::Result;
 use azure_data_cosmos::prelude::Operation;
 use azure_data_cosmos::resources::collection::PartitionKey;
 use azure_data_cosmos::{
-    prelude::{AuthorizationToken, CollectionClient, CosmosClient, Query},
+    prelude::{AuthorizationToken, CollectionClient, CosmosClient, Param, Query},
     CosmosEntity,
 };
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use spin_core::async_trait;
-use spin_factor_key_value::{log_cas_error, log_error, Cas, Error, Store, StoreManager, SwapError};
+use spin_factor_key_value::{
+    log_cas_error, log_error, Cas, Error, KeyPage, Store, StoreManager, SwapError,
+    LIST_KEYS_PAGE_SIZE,
+};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 pub struct KeyValueAzureCosmos {
     client: CollectionClient,
@@ -134,6 +138,23 @@ impl Store for AzureCosmosStore {
         let pair = Pair {
             id: key.to_string(),
             value: value.to_vec(),
+            ttl: None,
+        };
+        self.client
+            .create_document(pair)
+            .is_upsert(true)
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // Round up so that the entry lives for at least `ttl`.
+        let ttl_secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+        let pair = Pair {
+            id: key.to_string(),
+            value: value.to_vec(),
+            ttl: Some(i32::try_from(ttl_secs).unwrap_or(i32::MAX)),
         };
         self.client
             .create_document(pair)
@@ -159,6 +180,42 @@ impl Store for AzureCosmosStore {
         self.get_keys().await
     }
 
+    /// `list_keys` fetches a single page of query results, using the Cosmos continuation token as
+    /// the cursor.
+    async fn list_keys(
+        &self,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<KeyPage, Error> {
+        let stmt = match prefix {
+            Some(prefix) => Query::with_params(
+                "SELECT c.id FROM c WHERE STARTSWITH(c.id, @prefix)".to_string(),
+                vec![Param::new("@prefix".to_string(), prefix)],
+            ),
+            None => Query::new("SELECT c.id FROM c".to_string()),
+        };
+        let mut query = self
+            .client
+            .query_documents(stmt)
+            .query_cross_partition(true)
+            .max_item_count(LIST_KEYS_PAGE_SIZE as i32);
+        if let Some(cursor) = cursor {
+            query = query.continuation(cursor);
+        }
+
+        let mut stream = query.into_stream::<Key>();
+        match stream.next().await {
+            Some(resp) => {
+                let resp = resp.map_err(log_error)?;
+                Ok(KeyPage {
+                    keys: resp.results.into_iter().map(|(key, _)| key.id).collect(),
+                    cursor: resp.continuation_token.map(|c| c.as_string()),
+                })
+            }
+            None => Ok(KeyPage::default()),
+        }
+    }
+
     async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
         let in_clause: String = keys
             .into_iter()
@@ -276,6 +333,7 @@ impl Cas for CompareAndSwap {
         let pair = Pair {
             id: self.key.clone(),
             value,
+            ttl: None,
         };
 
         let doc_client = self
@@ -361,6 +419,10 @@ pub struct Pair {
     // In Azure CosmosDB, the default partition key is "/id", and this implementation assumes that partition ID is not changed.
     pub id: String,
     pub value: Vec<u8>,
+    /// Time to live in seconds. Only honored if time to live is enabled on the container (for
+    /// example with a default time to live of -1); Cosmos DB then hides and removes expired items.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i32>,
 }
 
 impl CosmosEntity for Pair {
@@ -370,3 +432,9 @@ impl CosmosEntity for Pair {
         self.id.clone()
     }
 }
+
+/// The key of a [`Pair`], as returned by key listing queries.
+#[derive(Deserialize, Clone, Debug)]
+struct Key {
+    id: String,
+}
@@ -1,9 +1,12 @@
 use anyhow::{Context, Result};
 use redis::{aio::MultiplexedConnection, parse_redis_url, AsyncCommands, Client, RedisError};
 use spin_core::async_trait;
-use spin_factor_key_value::{log_error, Cas, Error, Store, StoreManager, SwapError};
+use spin_factor_key_value::{
+    log_error, Cas, Error, KeyPage, Store, StoreManager, SwapError, LIST_KEYS_PAGE_SIZE,
+};
 use std::ops::DerefMut;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{Mutex, OnceCell};
 use url::Url;
 
@@ -81,6 +84,19 @@ impl Store for RedisStore {
             .map_err(log_error)
     }
 
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // SET rejects a zero expiry, so round sub-millisecond TTLs up.
+        let millis = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1);
+        redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("PX")
+            .arg(millis)
+            .exec_async(self.connection.lock().await.deref_mut())
+            .await
+            .map_err(log_error)
+    }
+
     async fn delete(&self, key: &str) -> Result<(), Error> {
         self.connection
             .lock()
@@ -108,6 +124,38 @@ impl Store for RedisStore {
             .map_err(log_error)
     }
 
+    /// `list_keys` pages through the keyspace with SCAN, using the SCAN cursor as the page cursor.
+    /// As with SCAN itself, a page may be empty even though more keys follow, and a key may be
+    /// returned more than once.
+    async fn list_keys(
+        &self,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<KeyPage, Error> {
+        let cursor: u64 = match cursor {
+            Some(cursor) => cursor
+                .parse()
+                .map_err(|_| Error::Other(format!("invalid list_keys cursor {cursor:?}")))?,
+            None => 0,
+        };
+        let pattern = format!("{}*", escape_glob(prefix.unwrap_or_default()));
+
+        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(LIST_KEYS_PAGE_SIZE)
+            .query_async(self.connection.lock().await.deref_mut())
+            .await
+            .map_err(log_error)?;
+
+        Ok(KeyPage {
+            keys,
+            cursor: (next_cursor != 0).then(|| next_cursor.to_string()),
+        })
+    }
+
     async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
         self.connection
             .lock()
@@ -168,6 +216,18 @@ impl Store for RedisStore {
     }
 }
 
+/// Escapes Redis glob-style pattern metacharacters so `s` matches literally.
+fn escape_glob(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 #[async_trait]
 impl Cas for CompareAndSwap {
     /// current will initiate a transaction by WATCH'ing a key in Redis, and then returning the
@@ -217,3 +277,14 @@ impl Cas for CompareAndSwap {
         self.key.clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_glob_escapes_metacharacters() {
+        assert_eq!(escape_glob("plain/prefix"), "plain/prefix");
+        assert_eq!(escape_glob("a*b?c[d]e\\f"), "a\\*b\\?c\\[d\\]e\\\\f");
+    }
+}
@@ -1,12 +1,16 @@
 use anyhow::Result;
 use rusqlite::{named_params, Connection};
 use spin_core::async_trait;
-use spin_factor_key_value::{log_cas_error, log_error, Cas, Error, Store, StoreManager, SwapError};
+use spin_factor_key_value::{
+    log_cas_error, log_error, Cas, Error, KeyPage, Store, StoreManager, SwapError,
+    LIST_KEYS_PAGE_SIZE,
+};
 use std::rc::Rc;
 use std::{
     path::PathBuf,
     sync::OnceLock,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::task;
 
@@ -47,6 +51,7 @@ impl KeyValueSqlite {
                            store TEXT NOT NULL,
                            key   TEXT NOT NULL,
                            value BLOB NOT NULL,
+                           expires_at INTEGER,
 
                            PRIMARY KEY (store, key)
                         )",
@@ -54,6 +59,21 @@ impl KeyValueSqlite {
             )
             .map_err(log_error)?;
 
+        // Databases created before entries could expire lack the `expires_at` column.
+        let has_expires_at = connection
+            .prepare("SELECT 1 FROM pragma_table_info('spin_key_value') WHERE name='expires_at'")
+            .map_err(log_error)?
+            .exists([])
+            .map_err(log_error)?;
+        if !has_expires_at {
+            connection
+                .execute(
+                    "ALTER TABLE spin_key_value ADD COLUMN expires_at INTEGER",
+                    [],
+                )
+                .map_err(log_error)?;
+        }
+
         // the array module is needed for `rarray` usage in queries.
         rusqlite::vtab::array::load_module(&connection).map_err(log_error)?;
 
@@ -99,18 +119,34 @@ struct SqliteStore {
 
 #[async_trait]
 impl Store for SqliteStore {
+    // Expired entries are evicted lazily, when they are next read.
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
         task::block_in_place(|| {
-            self.connection
-                .lock()
-                .unwrap()
-                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+            let connection = self.connection.lock().unwrap();
+            let row: Option<(Vec<u8>, Option<i64>)> = connection
+                .prepare_cached(
+                    "SELECT value, expires_at FROM spin_key_value WHERE store=$1 AND key=$2",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name, key], |row| row.get(0))
+                .query_map([&self.name, key], |row| Ok((row.get(0)?, row.get(1)?)))
                 .map_err(log_error)?
                 .next()
                 .transpose()
-                .map_err(log_error)
+                .map_err(log_error)?;
+
+            match row {
+                Some((_, Some(expires_at))) if expires_at <= now_millis() => {
+                    connection
+                        .prepare_cached(
+                            "DELETE FROM spin_key_value WHERE store=$1 AND key=$2 AND expires_at=$3",
+                        )
+                        .map_err(log_error)?
+                        .execute(rusqlite::params![&self.name, key, expires_at])
+                        .map_err(log_error)?;
+                    Ok(None)
+                }
+                row => Ok(row.map(|(value, _)| value)),
+            }
         })
     }
 
@@ -121,7 +157,7 @@ impl Store for SqliteStore {
                 .unwrap()
                 .prepare_cached(
                     "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=NULL",
                 )
                 .map_err(log_error)?
                 .execute(rusqlite::params![&self.name, key, value])
@@ -130,6 +166,24 @@ impl Store for SqliteStore {
         })
     }
 
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let ttl_millis = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+        let expires_at = now_millis().saturating_add(ttl_millis);
+        task::block_in_place(|| {
+            self.connection
+                .lock()
+                .unwrap()
+                .prepare_cached(
+                    "INSERT INTO spin_key_value (store, key, value, expires_at) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=$4",
+                )
+                .map_err(log_error)?
+                .execute(rusqlite::params![&self.name, key, value, expires_at])
+                .map_err(log_error)
+                .map(drop)
+        })
+    }
+
     async fn delete(&self, key: &str) -> Result<(), Error> {
         task::block_in_place(|| {
             self.connection
@@ -152,15 +206,65 @@ impl Store for SqliteStore {
             self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT key FROM spin_key_value WHERE store=$1")
+                .prepare_cached(
+                    "SELECT key FROM spin_key_value
+                     WHERE store=$1 AND (expires_at IS NULL OR expires_at > $2)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name], |row| row.get(0))
+                .query_map(rusqlite::params![&self.name, now_millis()], |row| {
+                    row.get(0)
+                })
                 .map_err(log_error)?
                 .map(|r| r.map_err(log_error))
                 .collect()
         })
     }
 
+    // Pages are ordered by key, and the cursor is the last key of the previous page.
+    async fn list_keys(
+        &self,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<KeyPage, Error> {
+        task::block_in_place(|| {
+            let mut keys: Vec<String> = self
+                .connection
+                .lock()
+                .unwrap()
+                .prepare_cached(
+                    "SELECT key FROM spin_key_value
+                     WHERE store=:name
+                       AND (:cursor IS NULL OR key > :cursor)
+                       AND substr(key, 1, length(:prefix)) = :prefix
+                       AND (expires_at IS NULL OR expires_at > :now)
+                     ORDER BY key LIMIT :limit",
+                )
+                .map_err(log_error)?
+                .query_map(
+                    named_params! {
+                        ":name": &self.name,
+                        ":cursor": cursor,
+                        ":prefix": prefix.unwrap_or_default(),
+                        ":now": now_millis(),
+                        // Fetch one extra key to find out whether there is another page.
+                        ":limit": LIST_KEYS_PAGE_SIZE + 1,
+                    },
+                    |row| row.get(0),
+                )
+                .map_err(log_error)?
+                .map(|r| r.map_err(log_error))
+                .collect::<Result<_, _>>()?;
+
+            let cursor = if keys.len() > LIST_KEYS_PAGE_SIZE {
+                keys.truncate(LIST_KEYS_PAGE_SIZE);
+                keys.last().cloned()
+            } else {
+                None
+            };
+            Ok(KeyPage { keys, cursor })
+        })
+    }
+
     async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
         task::block_in_place(|| {
             let sql_value_keys: Vec<rusqlite::types::Value> =
@@ -169,9 +273,9 @@ impl Store for SqliteStore {
             let row_iter: Vec<Result<(String, Option<Vec<u8>>), Error>> = self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT key, value FROM spin_key_value WHERE store=:name AND key IN rarray(:keys)")
+                .prepare_cached("SELECT key, value FROM spin_key_value WHERE store=:name AND key IN rarray(:keys) AND (expires_at IS NULL OR expires_at > :now)")
                 .map_err(log_error)?
-                .query_map(named_params! {":name": &self.name, ":keys": ptr}, |row| {
+                .query_map(named_params! {":name": &self.name, ":keys": ptr, ":now": now_millis()}, |row| {
                     <(String, Option<Vec<u8>>)>::try_from(row)
                 })
                 .map_err(log_error)?
@@ -194,7 +298,7 @@ impl Store for SqliteStore {
             for kv in key_values {
                 tx.prepare_cached(
                     "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=NULL",
                 )
                 .map_err(log_error)?
                 .execute(rusqlite::params![&self.name, kv.0, kv.1])
@@ -231,10 +335,14 @@ impl Store for SqliteStore {
 
             let tx = binding.transaction().map_err(log_error)?;
 
+            let now = now_millis();
             let value: Option<Vec<u8>> = tx
-                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+                .prepare_cached(
+                    "SELECT value FROM spin_key_value
+                     WHERE store=$1 AND key=$2 AND (expires_at IS NULL OR expires_at > $3)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name, &key], |row| row.get(0))
+                .query_map(rusqlite::params![&self.name, &key, now], |row| row.get(0))
                 .map_err(log_error)?
                 .next()
                 .transpose()
@@ -246,12 +354,19 @@ impl Store for SqliteStore {
             };
 
             let new_value = numeric + delta;
+            // Incrementing an entry keeps its expiry, unless it had already expired.
             tx.prepare_cached(
                 "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3,
+                       expires_at=CASE WHEN expires_at <= $4 THEN NULL ELSE expires_at END",
             )
             .map_err(log_error)?
-            .execute(rusqlite::params![&self.name, key, new_value.to_le_bytes()])
+            .execute(rusqlite::params![
+                &self.name,
+                key,
+                new_value.to_le_bytes(),
+                now
+            ])
             .map_err(log_error)
             .map(drop)?;
 
@@ -291,9 +406,15 @@ impl Cas for CompareAndSwap {
                 .connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+                .prepare_cached(
+                    "SELECT value FROM spin_key_value
+                     WHERE store=$1 AND key=$2 AND (expires_at IS NULL OR expires_at > $3)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name, &self.key], |row| row.get(0))
+                .query_map(
+                    rusqlite::params![&self.name, &self.key, now_millis()],
+                    |row| row.get(0),
+                )
                 .map_err(log_error)?
                 .next()
                 .transpose()
@@ -312,13 +433,14 @@ impl Cas for CompareAndSwap {
                 Some(old_val) => {
                     conn
                         .prepare_cached(
-                             "UPDATE spin_key_value SET value=:new_value WHERE store=:name and key=:key and value=:old_value")
+                             "UPDATE spin_key_value SET value=:new_value, expires_at=NULL WHERE store=:name and key=:key and value=:old_value and (expires_at IS NULL OR expires_at > :now)")
                         .map_err(log_cas_error)?
                         .execute(named_params! {
                             ":name": &self.name,
                             ":key": self.key,
                             ":old_value": old_val,
                             ":new_value": value,
+                            ":now": now_millis(),
                         })
                         .map_err(log_cas_error)?
                 }
@@ -327,7 +449,7 @@ impl Cas for CompareAndSwap {
                     let rows = tx
                         .prepare_cached(
                             "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=NULL",
                         )
                         .map_err(log_cas_error)?
                         .execute(rusqlite::params![&self.name, self.key, value])
@@ -355,6 +477,14 @@ impl Cas for CompareAndSwap {
     }
 }
 
+/// The current time as milliseconds since the Unix epoch, as stored in `expires_at`.
+fn now_millis() -> i64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    i64::try_from(since_epoch.as_millis()).unwrap_or(i64::MAX)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -498,6 +628,88 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn list_keys_pages() -> Result<()> {
+        let manager = KeyValueSqlite::new(DatabaseLocation::InMemory);
+        let store = manager.get("default").await?;
+        let other_store = manager.get("other").await?;
+
+        let key_values = (0..LIST_KEYS_PAGE_SIZE + 5)
+            .map(|i| (format!("page/{i:05}"), b"value".to_vec()))
+            .chain([("pagination".to_owned(), b"value".to_vec())])
+            .chain([("".to_owned(), b"empty".to_vec())])
+            .collect::<Vec<_>>();
+        store.set_many(key_values).await?;
+        other_store.set("page/other", b"value").await?;
+
+        let first = store.list_keys(Some("page/"), None).await?;
+        assert_eq!(first.keys.len(), LIST_KEYS_PAGE_SIZE);
+        assert_eq!(first.keys[0], "page/00000");
+        let cursor = first.cursor.expect("expected a second page");
+
+        let second = store.list_keys(Some("page/"), Some(cursor)).await?;
+        assert_eq!(
+            second.keys,
+            (LIST_KEYS_PAGE_SIZE..LIST_KEYS_PAGE_SIZE + 5)
+                .map(|i| format!("page/{i:05}"))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(second.cursor, None);
+
+        let unfiltered = store.list_keys(None, None).await?;
+        assert_eq!(unfiltered.keys[0], "");
+        assert!(unfiltered.cursor.is_some());
+
+        let empty = store.list_keys(Some("nope"), None).await?;
+        assert_eq!(empty, KeyPage::default());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn expired_entries_are_hidden() -> Result<()> {
+        let manager = KeyValueSqlite::new(DatabaseLocation::InMemory);
+        let store = manager.get("default").await?;
+
+        store
+            .set_with_ttl("short", b"value", Duration::from_millis(1))
+            .await?;
+        store
+            .set_with_ttl("long", b"value", Duration::from_secs(3600))
+            .await?;
+        store
+            .set_with_ttl("cleared", b"value", Duration::from_millis(1))
+            .await?;
+        store.set("cleared", b"persistent").await?;
+        std::thread::sleep(Duration::from_millis(10));
+
+        assert_eq!(store.get("short").await?, None);
+        assert!(!store.exists("short").await?);
+        assert_eq!(store.get("long").await?.as_deref(), Some(b"value" as &[_]));
+        assert_eq!(
+            store.get("cleared").await?.as_deref(),
+            Some(b"persistent" as &[_])
+        );
+
+        let mut keys = store.get_keys().await?;
+        keys.sort();
+        assert_eq!(keys, ["cleared", "long"]);
+        assert_eq!(store.list_keys(None, None).await?.keys, ["cleared", "long"]);
+
+        // An expired counter starts again from zero.
+        store
+            .set_with_ttl("counter", &5i64.to_le_bytes(), Duration::from_millis(1))
+            .await?;
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(store.increment("counter".to_owned(), 1).await?, 1);
+        assert_eq!(
+            store.get("counter").await?.as_deref(),
+            Some(&1i64.to_le_bytes() as &[_])
+        );
+
+        Ok(())
+    }
+
     async fn cas_failed(kv: &mut KeyValueDispatch, rep: u32) -> Result<()> {
         let cas_key = "fail".to_owned();
         let cas_orig_value = b"baz".to_vec();
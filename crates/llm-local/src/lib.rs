@@ -47,10 +47,13 @@ impl FromStr for InferencingModelArch {
 /// This trait does not specify anything about if the results are cached.
 #[async_trait]
 trait InferencingModel: Send + Sync {
+    /// Performs inferencing, passing newly generated text to `on_text`, if given, as it is
+    /// generated. Generation stops early if `on_text` returns false.
     async fn infer(
         &self,
         prompt: String,
         params: wasi_llm::InferencingParams,
+        on_text: Option<&mut (dyn FnMut(String) -> bool + Send)>,
     ) -> anyhow::Result<wasi_llm::InferencingResult>;
 }
 
@@ -64,11 +67,30 @@ impl LocalLlmEngine {
         let model = self.inferencing_model(model).await?;
 
         model
-            .infer(prompt, params)
+            .infer(prompt, params, None)
             .await
             .map_err(|e| wasi_llm::Error::RuntimeError(e.to_string()))
     }
 
+    /// Performs inferencing, passing the generated text to `on_text` as it is generated.
+    ///
+    /// Generation stops early if `on_text` returns false.
+    pub async fn infer_stream(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+        mut on_text: impl FnMut(String) -> bool + Send,
+    ) -> Result<wasi_llm::InferencingUsage, wasi_llm::Error> {
+        let model = self.inferencing_model(model).await?;
+
+        model
+            .infer(prompt, params, Some(&mut on_text))
+            .await
+            .map(|result| result.usage)
+            .map_err(|e| wasi_llm::Error::RuntimeError(e.to_string()))
+    }
+
     pub async fn generate_embeddings(
         &mut self,
         model: wasi_llm::EmbeddingModel,
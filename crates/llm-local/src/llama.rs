@@ -81,6 +81,7 @@ impl InferencingModel for LlamaModels {
         &self,
         prompt: String,
         params: wasi_llm::InferencingParams,
+        mut on_text: Option<&mut (dyn FnMut(String) -> bool + Send)>,
     ) -> anyhow::Result<wasi_llm::InferencingResult> {
         let model = Arc::clone(&self.model);
         let config = &self.config;
@@ -117,6 +118,8 @@ impl InferencingModel for LlamaModels {
 
         let mut index_pos = 0;
         let mut tokens_generated = 0;
+        let prompt_token_count = tokens.len();
+        let mut text_stream = TextStream::new(&tokenizer, prompt_token_count);
 
         for index in 0..params.max_tokens {
             let (context_size, context_index) = if self.cache.use_kv_cache && index > 0 {
@@ -146,6 +149,14 @@ impl InferencingModel for LlamaModels {
             tokens_generated += 1;
             tokens.push(next_token);
 
+            if let Some(on_text) = on_text.as_mut() {
+                if let Some(text) = text_stream.next_text(&tokens)? {
+                    if !on_text(text) {
+                        break;
+                    }
+                }
+            }
+
             // Validate if we have reached the end of the token(s)
             match eos_token_id {
                 Some(llama::LlamaEosToks::Single(eos_tok_id)) if next_token == eos_tok_id => {
@@ -174,6 +185,53 @@ impl InferencingModel for LlamaModels {
     }
 }
 
+/// Decodes generated tokens into text as they are generated.
+///
+/// A token's text can depend on the tokens around it (e.g. the spaces between words), so each
+/// step decodes the tokens since the last text passed on, rather than only the newest token, but
+/// never all the generated tokens again.
+struct TextStream<'a> {
+    tokenizer: &'a Tokenizer,
+    /// The start of the tokens whose text was last passed on.
+    prev_index: usize,
+    /// The end of the tokens whose text has been passed on.
+    current_index: usize,
+}
+
+impl<'a> TextStream<'a> {
+    fn new(tokenizer: &'a Tokenizer, start: usize) -> Self {
+        Self {
+            tokenizer,
+            prev_index: start,
+            current_index: start,
+        }
+    }
+
+    /// Returns the text added by the newly generated tokens, if any.
+    fn next_text(&mut self, tokens: &[u32]) -> Result<Option<String>> {
+        let decode = |tokens: &[u32]| {
+            self.tokenizer
+                .decode(tokens, true)
+                .map_err(|e| anyhow!(e.to_string()))
+        };
+        let prev_text = decode(&tokens[self.prev_index..self.current_index])?;
+        let text = decode(&tokens[self.prev_index..])?;
+        // Hold back text ending in a partially generated character until it is complete.
+        if text.ends_with(char::REPLACEMENT_CHARACTER) {
+            return Ok(None);
+        }
+        match text.get(prev_text.len()..).filter(|t| !t.is_empty()) {
+            Some(new_text) => {
+                let new_text = new_text.to_owned();
+                self.prev_index = self.current_index;
+                self.current_index = tokens.len();
+                Ok(Some(new_text))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
 ///  Loads a list of SafeTensors file paths from a given model directory and
 ///  path to the model index JSON file relative to the model folder.
 fn load_safetensors(model_dir: &Path, json_file: &str) -> Result<Vec<std::path::PathBuf>> {
@@ -1,12 +1,16 @@
+mod open_ai;
+
 use anyhow::Result;
 use reqwest::{
     header::{HeaderMap, HeaderValue},
-    Client, Url,
+    Client, StatusCode, Url,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use spin_world::v2::llm::{self as wasi_llm};
 
+pub use open_ai::{OpenAiApi, OpenAiEndpoint, OpenAiLlmEngine};
+
 #[derive(Clone)]
 pub struct RemoteHttpLlmEngine {
     auth_token: String,
@@ -38,6 +42,49 @@ struct InferResponseBody {
     usage: InferUsage,
 }
 
+impl From<InferUsage> for wasi_llm::InferencingUsage {
+    fn from(usage: InferUsage) -> Self {
+        wasi_llm::InferencingUsage {
+            prompt_token_count: usage.prompt_token_count,
+            generated_token_count: usage.generated_token_count,
+        }
+    }
+}
+
+/// Returns the response if it succeeded, or the error the server responded with.
+async fn check_status(
+    path: &str,
+    resp: reqwest::Response,
+) -> Result<reqwest::Response, wasi_llm::Error> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    Err(status_error(path, status, body))
+}
+
+/// The error for a failed response.
+///
+/// Servers respond with 404 Not Found to requests for models they don't serve, which is reported
+/// as `ModelNotSupported` so that another engine can be tried for the model.
+fn status_error(path: &str, status: StatusCode, body: String) -> wasi_llm::Error {
+    if status == StatusCode::NOT_FOUND {
+        tracing::debug!("POST /{path} failed with status {status}: {body}");
+        return wasi_llm::Error::ModelNotSupported;
+    }
+    wasi_llm::Error::RuntimeError(format!("POST /{path} failed with status {status}: {body}"))
+}
+
+/// An event of a streamed inferencing response: either generated text or, finally, the usage.
+#[derive(Deserialize)]
+struct InferStreamEvent {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    usage: Option<InferUsage>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all(deserialize = "camelCase"))]
 struct EmbeddingUsage {
@@ -57,6 +104,81 @@ impl RemoteHttpLlmEngine {
         prompt: String,
         params: wasi_llm::InferencingParams,
     ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+        let resp = self
+            .infer_request(model, prompt, params, false)?
+            .send()
+            .await
+            .map_err(|err| {
+                wasi_llm::Error::RuntimeError(format!("POST /infer request error: {err}"))
+            })?;
+        let resp = check_status("infer", resp).await?;
+
+        match resp.json::<InferResponseBody>().await {
+            Ok(val) => Ok(wasi_llm::InferencingResult {
+                text: val.text,
+                usage: val.usage.into(),
+            }),
+            Err(err) => Err(wasi_llm::Error::RuntimeError(format!(
+                "Failed to deserialize response for \"POST  /index\": {err}"
+            ))),
+        }
+    }
+
+    /// Performs inferencing, passing the generated text to `on_text` as it arrives from the
+    /// server as server-sent events.
+    ///
+    /// Stops reading the response if `on_text` returns false.
+    pub async fn infer_stream(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+        mut on_text: impl FnMut(String) -> bool + Send,
+    ) -> Result<wasi_llm::InferencingUsage, wasi_llm::Error> {
+        let resp = self
+            .infer_request(model, prompt, params, true)?
+            .header("accept", "text/event-stream")
+            .send()
+            .await
+            .map_err(|err| {
+                wasi_llm::Error::RuntimeError(format!("POST /infer request error: {err}"))
+            })?;
+        let mut resp = check_status("infer", resp).await?;
+
+        let mut events = SseParser::default();
+        while let Some(chunk) = resp.chunk().await.map_err(|err| {
+            wasi_llm::Error::RuntimeError(format!("POST /infer response error: {err}"))
+        })? {
+            for data in events.push(&chunk) {
+                let event: InferStreamEvent = serde_json::from_str(&data).map_err(|err| {
+                    wasi_llm::Error::RuntimeError(format!(
+                        "Failed to deserialize event for \"POST  /infer\": {err}"
+                    ))
+                })?;
+                if let Some(usage) = event.usage {
+                    return Ok(usage.into());
+                }
+                if let Some(text) = event.text {
+                    if !on_text(text) {
+                        return Err(wasi_llm::Error::RuntimeError(
+                            "Inferencing stream was cancelled".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        Err(wasi_llm::Error::RuntimeError(
+            "Inferencing stream ended without usage information".to_string(),
+        ))
+    }
+
+    fn infer_request(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, wasi_llm::Error> {
         let client = self.client.get_or_insert_with(Default::default);
 
         let mut headers = HeaderMap::new();
@@ -76,12 +198,16 @@ impl RemoteHttpLlmEngine {
             top_k: params.top_k,
             top_p: params.top_p,
         };
-        let body = serde_json::to_string(&json!({
+        let mut body = json!({
             "model": model,
             "prompt": prompt,
             "options": inference_options
-        }))
-        .map_err(|_| wasi_llm::Error::RuntimeError("Failed to serialize JSON".to_string()))?;
+        });
+        if stream {
+            body["stream"] = json!(true);
+        }
+        let body = serde_json::to_string(&body)
+            .map_err(|_| wasi_llm::Error::RuntimeError("Failed to serialize JSON".to_string()))?;
 
         let infer_url = self
             .url
@@ -89,28 +215,10 @@ impl RemoteHttpLlmEngine {
             .map_err(|_| wasi_llm::Error::RuntimeError("Failed to create URL".to_string()))?;
         tracing::info!("Sending remote inference request to {infer_url}");
 
-        let resp = client
+        Ok(client
             .request(reqwest::Method::POST, infer_url)
             .headers(headers)
-            .body(body)
-            .send()
-            .await
-            .map_err(|err| {
-                wasi_llm::Error::RuntimeError(format!("POST /infer request error: {err}"))
-            })?;
-
-        match resp.json::<InferResponseBody>().await {
-            Ok(val) => Ok(wasi_llm::InferencingResult {
-                text: val.text,
-                usage: wasi_llm::InferencingUsage {
-                    prompt_token_count: val.usage.prompt_token_count,
-                    generated_token_count: val.usage.generated_token_count,
-                },
-            }),
-            Err(err) => Err(wasi_llm::Error::RuntimeError(format!(
-                "Failed to deserialize response for \"POST  /index\": {err}"
-            ))),
-        }
+            .body(body))
     }
 
     pub async fn generate_embeddings(
@@ -149,6 +257,7 @@ impl RemoteHttpLlmEngine {
             .map_err(|err| {
                 wasi_llm::Error::RuntimeError(format!("POST /embed request error: {err}"))
             })?;
+        let resp = check_status("embed", resp).await?;
 
         match resp.json::<EmbeddingResponseBody>().await {
             Ok(val) => Ok(wasi_llm::EmbeddingsResult {
@@ -177,3 +286,58 @@ impl RemoteHttpLlmEngine {
         }
     }
 }
+
+/// Splits the data of server-sent events out of a response body as it arrives.
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    /// Adds a chunk of the body, returning the data of each event it completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+        let mut events = vec![];
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let event = self.buffer.drain(..end + 2).collect::<Vec<_>>();
+            let event = String::from_utf8_lossy(&event);
+            let data = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect::<Vec<_>>();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sse_parser_splits_events_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: {\"text\":").is_empty());
+        assert_eq!(
+            parser.push(b" \"a\"}\r\n\r\n: comment\n\ndata: one\ndata: two\n\ndata: x"),
+            vec!["{\"text\": \"a\"}", "one\ntwo"]
+        );
+        assert_eq!(parser.push(b"\n\n"), vec!["x"]);
+    }
+
+    #[test]
+    fn unknown_models_are_not_supported() {
+        assert!(matches!(
+            status_error("infer", StatusCode::NOT_FOUND, "no such model".into()),
+            wasi_llm::Error::ModelNotSupported
+        ));
+        assert!(matches!(
+            status_error("infer", StatusCode::UNAUTHORIZED, "bad token".into()),
+            wasi_llm::Error::RuntimeError(msg) if msg.contains("401")
+        ));
+    }
+}
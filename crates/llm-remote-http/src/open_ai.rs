@@ -0,0 +1,470 @@
+//! An engine for servers that implement the OpenAI API, such as vLLM and Ollama.
+
+use std::collections::HashMap;
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, Url,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use spin_world::v2::llm::{self as wasi_llm};
+
+use crate::{check_status, SseParser};
+
+const COMPLETIONS_PATH: &str = "v1/completions";
+const CHAT_COMPLETIONS_PATH: &str = "v1/chat/completions";
+const EMBEDDINGS_PATH: &str = "v1/embeddings";
+
+/// An LLM engine that sends requests to OpenAI-compatible servers, routing each model to the
+/// server configured for it.
+#[derive(Clone)]
+pub struct OpenAiLlmEngine {
+    default_endpoint: Option<OpenAiEndpoint>,
+    model_endpoints: HashMap<String, OpenAiEndpoint>,
+    client: Option<Client>,
+}
+
+/// An OpenAI-compatible server to send requests for a model to.
+#[derive(Clone, Debug)]
+pub struct OpenAiEndpoint {
+    /// The base URL of the server. Requests are sent to `<url>/v1/...`.
+    pub url: Url,
+    /// Headers sent with every request, such as `authorization`.
+    pub headers: Vec<(String, String)>,
+    /// The API used for inferencing.
+    pub api: OpenAiApi,
+    /// The name of the model on the server, if different from the name used by components.
+    pub model: Option<String>,
+}
+
+/// The OpenAI API used for inferencing.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAiApi {
+    /// `/v1/chat/completions`, with the prompt sent as a single user message.
+    #[default]
+    Chat,
+    /// `/v1/completions`.
+    Completions,
+}
+
+impl OpenAiApi {
+    fn path(self) -> &'static str {
+        match self {
+            OpenAiApi::Chat => CHAT_COMPLETIONS_PATH,
+            OpenAiApi::Completions => COMPLETIONS_PATH,
+        }
+    }
+}
+
+impl OpenAiEndpoint {
+    fn model_name<'a>(&'a self, model: &'a str) -> &'a str {
+        self.model.as_deref().unwrap_or(model)
+    }
+
+    fn infer_body(
+        &self,
+        model: &str,
+        prompt: String,
+        params: &wasi_llm::InferencingParams,
+        stream: bool,
+    ) -> Value {
+        // `top_k` and `repetition_penalty` are not part of the OpenAI API but are honoured by
+        // vLLM and ignored by most other servers. There is no equivalent of
+        // `repeat_penalty_last_n_token_count`.
+        let mut body = json!({
+            "model": self.model_name(model),
+            "max_tokens": params.max_tokens,
+            "temperature": params.temperature,
+            "top_p": params.top_p,
+            "top_k": params.top_k,
+            "repetition_penalty": params.repeat_penalty,
+        });
+        match self.api {
+            OpenAiApi::Chat => body["messages"] = json!([{ "role": "user", "content": prompt }]),
+            OpenAiApi::Completions => body["prompt"] = json!(prompt),
+        }
+        if stream {
+            body["stream"] = json!(true);
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        body
+    }
+}
+
+impl OpenAiLlmEngine {
+    /// Creates an engine that sends requests for the models in `model_endpoints` to their
+    /// endpoints and requests for any other model to `default_endpoint`.
+    pub fn new(
+        default_endpoint: Option<OpenAiEndpoint>,
+        model_endpoints: HashMap<String, OpenAiEndpoint>,
+    ) -> Self {
+        Self {
+            default_endpoint,
+            model_endpoints,
+            client: None,
+        }
+    }
+
+    pub async fn infer(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+    ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+        let endpoint = self.endpoint(&model)?;
+        let path = endpoint.api.path();
+        let body = endpoint.infer_body(&model, prompt, &params, false);
+        let resp: CompletionResponse = self
+            .post(&endpoint, path, body)
+            .await?
+            .json()
+            .await
+            .map_err(|err| deserialize_error(path, err))?;
+        Ok(resp.into())
+    }
+
+    /// Performs inferencing, passing the generated text to `on_text` as it arrives from the
+    /// server.
+    ///
+    /// Stops reading the response if `on_text` returns false.
+    pub async fn infer_stream(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+        mut on_text: impl FnMut(String) -> bool + Send,
+    ) -> Result<wasi_llm::InferencingUsage, wasi_llm::Error> {
+        let endpoint = self.endpoint(&model)?;
+        let path = endpoint.api.path();
+        let body = endpoint.infer_body(&model, prompt, &params, true);
+        let mut resp = self.post(&endpoint, path, body).await?;
+
+        let mut usage = wasi_llm::InferencingUsage {
+            prompt_token_count: 0,
+            generated_token_count: 0,
+        };
+        let mut events = SseParser::default();
+        while let Some(chunk) = resp.chunk().await.map_err(|err| {
+            wasi_llm::Error::RuntimeError(format!("POST /{path} response error: {err}"))
+        })? {
+            for data in events.push(&chunk) {
+                if data == "[DONE]" {
+                    return Ok(usage);
+                }
+                let chunk: CompletionResponse =
+                    serde_json::from_str(&data).map_err(|err| deserialize_error(path, err))?;
+                if let Some(chunk_usage) = chunk.usage {
+                    usage = chunk_usage.into();
+                }
+                for text in chunk.choices.into_iter().filter_map(Choice::into_text) {
+                    if !text.is_empty() && !on_text(text) {
+                        return Err(wasi_llm::Error::RuntimeError(
+                            "Inferencing stream was cancelled".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(usage)
+    }
+
+    pub async fn generate_embeddings(
+        &mut self,
+        model: wasi_llm::EmbeddingModel,
+        data: Vec<String>,
+    ) -> Result<wasi_llm::EmbeddingsResult, wasi_llm::Error> {
+        let endpoint = self.endpoint(&model)?;
+        let body = json!({
+            "model": endpoint.model_name(&model),
+            "input": data,
+        });
+        let resp: EmbeddingsResponse = self
+            .post(&endpoint, EMBEDDINGS_PATH, body)
+            .await?
+            .json()
+            .await
+            .map_err(|err| deserialize_error(EMBEDDINGS_PATH, err))?;
+        Ok(resp.into())
+    }
+
+    /// Returns whether there is a server to send requests for the given model to.
+    pub fn serves(&self, model: &str) -> bool {
+        self.endpoint(model).is_ok()
+    }
+
+    /// A human-readable description of the servers requests are sent to.
+    pub fn summary(&self) -> String {
+        match &self.default_endpoint {
+            Some(endpoint) => format!("OpenAI-compatible models at {}", endpoint.url),
+            None => "OpenAI-compatible models".to_string(),
+        }
+    }
+
+    fn endpoint(&self, model: &str) -> Result<OpenAiEndpoint, wasi_llm::Error> {
+        self.model_endpoints
+            .get(model)
+            .or(self.default_endpoint.as_ref())
+            .cloned()
+            .ok_or(wasi_llm::Error::ModelNotSupported)
+    }
+
+    async fn post(
+        &mut self,
+        endpoint: &OpenAiEndpoint,
+        path: &str,
+        body: Value,
+    ) -> Result<reqwest::Response, wasi_llm::Error> {
+        let client = self.client.get_or_insert_with(Default::default);
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &endpoint.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                wasi_llm::Error::RuntimeError(format!("Invalid header name '{name}'"))
+            })?;
+            let value = HeaderValue::from_str(value).map_err(|_| {
+                wasi_llm::Error::RuntimeError(format!("Invalid value for header '{name}'"))
+            })?;
+            headers.insert(name, value);
+        }
+        spin_telemetry::inject_trace_context(&mut headers);
+
+        let url = endpoint_url(&endpoint.url, path)?;
+        tracing::info!("Sending OpenAI-compatible request to {url}");
+
+        let resp = client
+            .post(url)
+            .headers(headers)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|err| {
+                wasi_llm::Error::RuntimeError(format!("POST /{path} request error: {err}"))
+            })?;
+        check_status(path, resp).await
+    }
+}
+
+/// Appends an API path to the base URL of a server, keeping any path the base URL has.
+fn endpoint_url(base: &Url, path: &str) -> Result<Url, wasi_llm::Error> {
+    let mut base = base.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(path)
+        .map_err(|_| wasi_llm::Error::RuntimeError("Failed to create URL".to_string()))
+}
+
+fn deserialize_error(path: &str, err: impl std::fmt::Display) -> wasi_llm::Error {
+    wasi_llm::Error::RuntimeError(format!(
+        "Failed to deserialize response for \"POST /{path}\": {err}"
+    ))
+}
+
+/// A completions or chat completions response, or a chunk of a streamed one.
+#[derive(Deserialize)]
+struct CompletionResponse {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    /// The text of a completion.
+    #[serde(default)]
+    text: Option<String>,
+    /// The message of a chat completion.
+    #[serde(default)]
+    message: Option<ChatMessage>,
+    /// The message of a streamed chat completion chunk.
+    #[serde(default)]
+    delta: Option<ChatMessage>,
+}
+
+impl Choice {
+    fn into_text(self) -> Option<String> {
+        self.text
+            .or_else(|| self.message.and_then(|m| m.content))
+            .or_else(|| self.delta.and_then(|m| m.content))
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CompletionUsage {
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+impl From<CompletionUsage> for wasi_llm::InferencingUsage {
+    fn from(usage: CompletionUsage) -> Self {
+        wasi_llm::InferencingUsage {
+            prompt_token_count: usage.prompt_tokens,
+            generated_token_count: usage.completion_tokens,
+        }
+    }
+}
+
+impl From<CompletionResponse> for wasi_llm::InferencingResult {
+    fn from(resp: CompletionResponse) -> Self {
+        wasi_llm::InferencingResult {
+            text: resp
+                .choices
+                .into_iter()
+                .next()
+                .and_then(Choice::into_text)
+                .unwrap_or_default(),
+            usage: resp
+                .usage
+                .map(Into::into)
+                .unwrap_or(wasi_llm::InferencingUsage {
+                    prompt_token_count: 0,
+                    generated_token_count: 0,
+                }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<Embedding>,
+    #[serde(default)]
+    usage: Option<EmbeddingsUsage>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsUsage {
+    prompt_tokens: u32,
+}
+
+impl From<EmbeddingsResponse> for wasi_llm::EmbeddingsResult {
+    fn from(mut resp: EmbeddingsResponse) -> Self {
+        resp.data.sort_by_key(|e| e.index);
+        wasi_llm::EmbeddingsResult {
+            embeddings: resp.data.into_iter().map(|e| e.embedding).collect(),
+            usage: wasi_llm::EmbeddingsUsage {
+                prompt_token_count: resp.usage.map(|u| u.prompt_tokens).unwrap_or_default(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(url: &str, api: OpenAiApi) -> OpenAiEndpoint {
+        OpenAiEndpoint {
+            url: url.parse().unwrap(),
+            headers: vec![],
+            api,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn endpoint_url_keeps_base_path() {
+        let url = |base: &str| endpoint_url(&base.parse().unwrap(), CHAT_COMPLETIONS_PATH);
+        assert_eq!(
+            url("http://localhost:8000").unwrap().as_str(),
+            "http://localhost:8000/v1/chat/completions"
+        );
+        assert_eq!(
+            url("https://example.com/openai").unwrap().as_str(),
+            "https://example.com/openai/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn infer_body_maps_params() {
+        let params = wasi_llm::InferencingParams {
+            max_tokens: 10,
+            repeat_penalty: 1.1,
+            repeat_penalty_last_n_token_count: 64,
+            temperature: 0.5,
+            top_k: 40,
+            top_p: 0.9,
+        };
+
+        let mut chat = endpoint("http://localhost", OpenAiApi::Chat);
+        chat.model = Some("llama3:8b".into());
+        let body = chat.infer_body("llama3", "hi".into(), &params, true);
+        assert_eq!(body["model"], "llama3:8b");
+        assert_eq!(body["max_tokens"], 10);
+        assert_eq!(body["messages"][0]["content"], "hi");
+        assert_eq!(body["stream"], true);
+
+        let completions = endpoint("http://localhost", OpenAiApi::Completions);
+        let body = completions.infer_body("llama3", "hi".into(), &params, false);
+        assert_eq!(body["model"], "llama3");
+        assert_eq!(body["prompt"], "hi");
+        assert!(body.get("messages").is_none());
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn responses_are_converted() {
+        let chat: CompletionResponse = serde_json::from_value(json!({
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": "hello" } }],
+            "usage": { "prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4 }
+        }))
+        .unwrap();
+        let result = wasi_llm::InferencingResult::from(chat);
+        assert_eq!(result.text, "hello");
+        assert_eq!(result.usage.prompt_token_count, 3);
+        assert_eq!(result.usage.generated_token_count, 1);
+
+        let embeddings: EmbeddingsResponse = serde_json::from_value(json!({
+            "data": [
+                { "embedding": [2.0], "index": 1 },
+                { "embedding": [1.0], "index": 0 }
+            ],
+            "usage": { "prompt_tokens": 2, "total_tokens": 2 }
+        }))
+        .unwrap();
+        let result = wasi_llm::EmbeddingsResult::from(embeddings);
+        assert_eq!(result.embeddings, vec![vec![1.0], vec![2.0]]);
+        assert_eq!(result.usage.prompt_token_count, 2);
+    }
+
+    #[test]
+    fn models_are_routed_to_their_endpoints() {
+        let engine = OpenAiLlmEngine::new(
+            Some(endpoint("http://default", OpenAiApi::Chat)),
+            [(
+                "embedder".to_string(),
+                endpoint("http://embedder", OpenAiApi::Chat),
+            )]
+            .into(),
+        );
+        assert_eq!(
+            engine.endpoint("embedder").unwrap().url.as_str(),
+            "http://embedder/"
+        );
+        assert_eq!(
+            engine.endpoint("other").unwrap().url.as_str(),
+            "http://default/"
+        );
+
+        let engine = OpenAiLlmEngine::new(None, HashMap::new());
+        assert!(matches!(
+            engine.endpoint("other"),
+            Err(wasi_llm::Error::ModelNotSupported)
+        ));
+    }
+}
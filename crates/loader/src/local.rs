@@ -5,6 +5,7 @@ use futures::{future::try_join_all, StreamExt};
 use reqwest::Url;
 use spin_common::{paths::parent_dir, sloth, ui::quoted_path};
 use spin_factor_outbound_networking::SERVICE_CHAINING_DOMAIN_SUFFIX;
+use spin_factor_sqlite::migrations::Migration;
 use spin_locked_app::{
     locked::{
         self, ContentPath, ContentRef, LockedApp, LockedComponent, LockedComponentDependency,
@@ -154,8 +155,13 @@ impl LocalLoader {
             .string("description", component.description)
             .string_array("allowed_outbound_hosts", allowed_outbound_hosts)
             .string_array("key_value_stores", component.key_value_stores)
-            .string_array("databases", component.sqlite_databases)
+            .string_array("databases", component.sqlite_databases.clone())
+            .serializable(
+                "sqlite_migrations",
+                self.load_sqlite_migrations(&component)?,
+            )?
             .string_array("ai_models", component.ai_models)
+            .serializable("limits", component.limits)?
             .serializable("build", component.build)?
             .take();
 
@@ -337,6 +343,32 @@ impl LocalLoader {
         })
     }
 
+    // Read the migration scripts declared for the component's SQLite databases.
+    fn load_sqlite_migrations(
+        &self,
+        component: &v2::Component,
+    ) -> Result<Option<BTreeMap<String, Vec<Migration>>>> {
+        if component.sqlite_migrations.is_empty() {
+            return Ok(None);
+        }
+        let mut migrations = BTreeMap::new();
+        for (label, dir) in &component.sqlite_migrations {
+            ensure!(
+                component.sqlite_databases.contains(label),
+                "`sqlite_migrations` has migrations for database '{label}', which is not in `sqlite_databases`"
+            );
+            let dir = self.app_root.join(dir);
+            let scripts = read_sqlite_migrations(&dir).with_context(|| {
+                format!(
+                    "failed to read migrations for SQLite database '{label}' from {}",
+                    quoted_path(&dir)
+                )
+            })?;
+            migrations.insert(label.clone(), scripts);
+        }
+        Ok(Some(migrations))
+    }
+
     // Load a Wasm source from the given ContentRef and update the source
     // URL with an absolute path to the content.
     async fn load_component_source(
@@ -677,6 +709,49 @@ async fn verified_download(_url: &str, _digest: &str, _dest: &Path) -> Result<()
     panic!("async-io feature is required for downloading Wasm sources")
 }
 
+/// Read the migration scripts in a directory, in version order.
+///
+/// Scripts are named `<version>_<name>.sql`, e.g. `0001_create_users.sql`. Other files are ignored.
+fn read_sqlite_migrations(dir: &Path) -> Result<Vec<Migration>> {
+    let mut migrations = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension() != Some("sql".as_ref()) {
+            continue;
+        }
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let Some((version, name)) = stem
+            .split_once('_')
+            .and_then(|(version, name)| Some((version.parse::<i64>().ok()?, name)))
+        else {
+            bail!(
+                "migration {} is not named `<version>_<name>.sql`",
+                quoted_path(&path)
+            );
+        };
+        let sql = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read migration {}", quoted_path(&path)))?;
+        migrations.push(Migration {
+            version,
+            name: name.to_owned(),
+            sql,
+        });
+    }
+    migrations.sort_by_key(|m| m.version);
+    if let Some(w) = migrations.windows(2).find(|w| w[0].version == w[1].version) {
+        bail!(
+            "migrations '{}' and '{}' have the same version {}",
+            w[0].name,
+            w[1].name,
+            w[0].version
+        );
+    }
+    Ok(migrations)
+}
+
 fn safe_canonicalize(path: &Path) -> std::io::Result<PathBuf> {
     use path_absolutize::Absolutize;
     Ok(path.absolutize()?.into_owned())
@@ -843,4 +918,29 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn sqlite_migrations_are_read_in_version_order() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join("10_add_email.sql"),
+            "ALTER TABLE users ADD email TEXT",
+        )?;
+        std::fs::write(
+            dir.path().join("2_create_users.sql"),
+            "CREATE TABLE users (id INTEGER)",
+        )?;
+        std::fs::write(dir.path().join("README.md"), "ignored")?;
+
+        let migrations = read_sqlite_migrations(dir.path())?;
+        let versions: Vec<_> = migrations
+            .iter()
+            .map(|m| (m.version, m.name.as_str()))
+            .collect();
+        assert_eq!(versions, [(2, "create_users"), (10, "add_email")]);
+
+        std::fs::write(dir.path().join("10_duplicate.sql"), "")?;
+        assert!(read_sqlite_migrations(dir.path()).is_err());
+        Ok(())
+    }
 }
@@ -69,7 +69,9 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
                 exclude_files: component.exclude_files,
                 key_value_stores: component.key_value_stores,
                 sqlite_databases: component.sqlite_databases,
+                sqlite_migrations: Default::default(),
                 ai_models,
+                limits: None,
                 build: component.build,
                 tool: Default::default(),
                 allowed_outbound_hosts,
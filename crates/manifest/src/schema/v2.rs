@@ -187,9 +187,18 @@ pub struct Component {
         skip_serializing_if = "Vec::is_empty"
     )]
     pub sqlite_databases: Vec<String>,
+    /// `sqlite_migrations = { default = "migrations" }`
+    ///
+    /// Maps a label in `sqlite_databases` to a directory of migration scripts,
+    /// relative to the application directory.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub sqlite_migrations: Map<String, String>,
     /// `ai_models = ["llama2-chat"]`
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub ai_models: Vec<KebabId>,
+    /// `[component.<id>.limits]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ComponentLimits>,
     /// Build configuration
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub build: Option<ComponentBuildConfig>,
@@ -204,6 +213,80 @@ pub struct Component {
     pub dependencies: ComponentDependencies,
 }
 
+/// Component resource limits
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentLimits {
+    /// `memory = "64 MiB"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<ByteSize>,
+    /// `table_elements = 10000`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_elements: Option<u32>,
+    /// `instances = 100`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instances: Option<usize>,
+}
+
+/// A size in bytes, given either as a number of bytes (`1048576`) or as a
+/// string with a unit suffix (`"1 MiB"`, `"1MB"`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "ByteSizeRepr", into = "u64")]
+pub struct ByteSize(pub u64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ByteSizeRepr {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<ByteSizeRepr> for ByteSize {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: ByteSizeRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ByteSizeRepr::Bytes(bytes) => Ok(Self(bytes)),
+            ByteSizeRepr::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl std::str::FromStr for ByteSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let number: u64 = number
+            .parse()
+            .with_context(|| format!("invalid size {s:?}: expected a number of bytes"))?;
+        let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "kb" => 1_000,
+            "mb" => 1_000_000,
+            "gb" => 1_000_000_000,
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            other => anyhow::bail!(
+                "invalid size {s:?}: unknown unit {other:?} (expected one of B, KB, MB, GB, KiB, MiB, GiB)"
+            ),
+        };
+        let bytes = number
+            .checked_mul(multiplier)
+            .with_context(|| format!("invalid size {s:?}: too large"))?;
+        Ok(Self(bytes))
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> Self {
+        size.0
+    }
+}
+
 /// Component dependencies
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -542,7 +625,9 @@ mod tests {
             allowed_outbound_hosts: vec![],
             key_value_stores: labels.clone(),
             sqlite_databases: labels,
+            sqlite_migrations: Map::new(),
             ai_models: vec![],
+            limits: None,
             build: None,
             tool: Map::new(),
             dependencies_inherit_configuration: false,
@@ -569,6 +654,65 @@ mod tests {
         assert!(toml::to_string(&component).is_err());
     }
 
+    #[test]
+    fn deserializing_limits() {
+        let manifest = AppManifest::deserialize(toml! {
+            spin_manifest_version = 2
+            [application]
+            name = "limits"
+            [[trigger.fake]]
+            something = "something else"
+            [component.fake]
+            source = "dummy"
+            [component.fake.limits]
+            memory = "64 MiB"
+            table_elements = 1000
+            instances = 10
+        })
+        .unwrap();
+
+        let fake_id: KebabId = "fake".to_owned().try_into().unwrap();
+        let limits = manifest.components[&fake_id].limits.as_ref().unwrap();
+        assert_eq!(limits.memory, Some(ByteSize(64 << 20)));
+        assert_eq!(limits.table_elements, Some(1000));
+        assert_eq!(limits.instances, Some(10));
+    }
+
+    #[test]
+    fn parsing_byte_sizes() {
+        for (input, expected) in [
+            ("1024", 1024),
+            ("10B", 10),
+            ("2 KB", 2_000),
+            ("2KiB", 2048),
+            ("3 mb", 3_000_000),
+            ("3MiB", 3 << 20),
+            ("1GB", 1_000_000_000),
+            ("1 GiB", 1 << 30),
+        ] {
+            assert_eq!(
+                input.parse::<ByteSize>().unwrap(),
+                ByteSize(expected),
+                "{input}"
+            );
+        }
+        for invalid in [
+            "",
+            "MiB",
+            "1.5MiB",
+            "10 parsecs",
+            "-1",
+            "99999999999999999999 GiB",
+        ] {
+            assert!(
+                invalid.parse::<ByteSize>().is_err(),
+                "{invalid} should fail"
+            );
+        }
+        let size = ByteSize::deserialize(toml::Value::Integer(4096)).unwrap();
+        assert_eq!(size, ByteSize(4096));
+    }
+
     #[test]
     fn test_valid_snake_ids() {
         for valid in ["default", "mixed_CASE_words", "letters1_then2_numbers345"] {
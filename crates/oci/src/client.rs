@@ -9,8 +9,12 @@ use futures_util::future;
 use futures_util::stream::{self, StreamExt, TryStreamExt};
 use itertools::Itertools;
 use oci_distribution::{
-    client::ImageLayer, config::ConfigFile, manifest::OciImageManifest, secrets::RegistryAuth,
-    token_cache::RegistryTokenType, Reference, RegistryOperation,
+    client::ImageLayer,
+    config::ConfigFile,
+    manifest::{OciDescriptor, OciImageManifest, IMAGE_MANIFEST_MEDIA_TYPE, OCI_IMAGE_MEDIA_TYPE},
+    secrets::RegistryAuth,
+    token_cache::RegistryTokenType,
+    Reference, RegistryOperation,
 };
 use reqwest::Url;
 use spin_common::sha256;
@@ -23,6 +27,8 @@ use tokio::fs;
 use walkdir::WalkDir;
 
 use crate::auth::AuthConfig;
+use crate::layout::OciLayout;
+use crate::signing::{self, SigningKey, TrustPolicy};
 
 // TODO: the media types for application, data and archive layer are not final
 /// Media type for a layer representing a locked Spin application configuration
@@ -33,6 +39,8 @@ pub const DATA_MEDIATYPE: &str = "application/vnd.wasm.content.layer.v1+data";
 pub const ARCHIVE_MEDIATYPE: &str = "application/vnd.wasm.content.bundle.v1.tar+gzip";
 // Note: this will be updated with a canonical value once defined upstream
 const WASM_LAYER_MEDIA_TYPE: &str = "application/vnd.wasm.content.layer.v1+wasm";
+/// Media type of the empty config of artifacts, such as signatures, that have no config.
+const EMPTY_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
 
 const CONFIG_FILE: &str = "config.json";
 const LATEST_TAG: &str = "latest";
@@ -82,6 +90,10 @@ pub struct Client {
 pub struct ClientOpts {
     /// Inline content into ContentRef iff < this size.
     pub content_ref_inline_max_size: usize,
+    /// If set, pushed applications are signed with this key.
+    pub signing_key: Option<SigningKey>,
+    /// If set, pulled applications are verified against this policy.
+    pub trust_policy: Option<TrustPolicy>,
 }
 
 /// Controls whether predefined annotations are generated when pushing an application.
@@ -102,6 +114,8 @@ impl Client {
         let cache = Cache::new(cache_root).await?;
         let opts = ClientOpts {
             content_ref_inline_max_size: DEFAULT_CONTENT_REF_INLINE_MAX_SIZE,
+            signing_key: None,
+            trust_policy: None,
         };
 
         Ok(Self {
@@ -160,6 +174,64 @@ impl Client {
             .await
     }
 
+    /// Write a Spin application to an OCI image layout directory under the given
+    /// reference name, and return the digest of its manifest.
+    ///
+    /// The directory is created if it does not exist. An application already in the
+    /// layout under the same reference name is replaced.
+    pub async fn push_to_layout(
+        &mut self,
+        manifest_path: &Path,
+        layout_dir: &Path,
+        ref_name: &str,
+        annotations: Option<BTreeMap<String, String>>,
+        infer_annotations: InferPredefinedAnnotations,
+    ) -> Result<String> {
+        let working_dir = tempfile::tempdir()?;
+        let locked = spin_loader::from_file(
+            manifest_path,
+            FilesMountStrategy::Copy(working_dir.path().into()),
+            None,
+        )
+        .await?;
+
+        self.push_locked_to_layout(locked, layout_dir, ref_name, annotations, infer_annotations)
+            .await
+    }
+
+    /// Write a locked Spin application to an OCI image layout directory under the given
+    /// reference name, and return the digest of its manifest.
+    pub async fn push_locked_to_layout(
+        &mut self,
+        locked: LockedApp,
+        layout_dir: &Path,
+        ref_name: &str,
+        annotations: Option<BTreeMap<String, String>>,
+        infer_annotations: InferPredefinedAnnotations,
+    ) -> Result<String> {
+        let (layers, oci_config, manifest) = self
+            .assemble_image(locked, annotations, infer_annotations)
+            .await?;
+
+        let layout = OciLayout::create(layout_dir).await?;
+        for layer in &layers {
+            layout.write_blob(&layer.data).await?;
+        }
+        layout.write_blob(&oci_config.data).await?;
+        let digest = layout
+            .write_manifest(&manifest, ref_name)
+            .await
+            .with_context(|| {
+                format!(
+                    "cannot write Spin application to OCI layout {}",
+                    quoted_path(layout_dir)
+                )
+            })?;
+
+        tracing::info!("Wrote {ref_name}@{digest} to {}", quoted_path(layout_dir));
+        Ok(digest)
+    }
+
     /// Push a Spin application to an OCI registry and return the digest (or None
     /// if the digest cannot be determined).
     async fn push_locked_core(
@@ -170,6 +242,46 @@ impl Client {
         annotations: Option<BTreeMap<String, String>>,
         infer_annotations: InferPredefinedAnnotations,
     ) -> Result<Option<String>> {
+        let (layers, oci_config, manifest) = self
+            .assemble_image(locked, annotations, infer_annotations)
+            .await?;
+
+        let response = self
+            .oci
+            .push(&reference, &layers, oci_config, &auth, Some(manifest))
+            .await
+            .map(|push_response| push_response.manifest_url)
+            .context("cannot push Spin application")?;
+
+        tracing::info!("Pushed {:?}", response);
+
+        let digest = digest_from_url(&response);
+
+        if let Some(key) = self.opts.signing_key.clone() {
+            // Sign the digest the push reported rather than whatever the tag points to now,
+            // which another push may already have changed.
+            let digest = digest.as_deref().context(
+                "cannot sign Spin application: the registry did not report the digest of the pushed manifest",
+            )?;
+            self.push_signature(&reference, &auth, &key, digest)
+                .await
+                .context("cannot sign Spin application")?;
+        }
+
+        Ok(digest)
+    }
+
+    /// Assemble the layers, config and manifest of the OCI image for a locked application.
+    async fn assemble_image(
+        &mut self,
+        locked: LockedApp,
+        annotations: Option<BTreeMap<String, String>>,
+        infer_annotations: InferPredefinedAnnotations,
+    ) -> Result<(
+        Vec<ImageLayer>,
+        oci_distribution::client::Config,
+        OciImageManifest,
+    )> {
         let mut locked_app = locked.clone();
         let mut layers = self
             .assemble_layers(&mut locked_app, AssemblyMode::Simple)
@@ -190,7 +302,7 @@ impl Client {
 
         let annotations = all_annotations(&locked_app, annotations, infer_annotations);
 
-        // Push layer for locked spin application config
+        // Add layer for locked spin application config
         let locked_config_layer = ImageLayer::new(
             serde_json::to_vec(&locked_app).context("could not serialize locked config")?,
             SPIN_APPLICATION_MEDIA_TYPE.to_string(),
@@ -227,17 +339,131 @@ impl Client {
             oci_distribution::client::Config::oci_v1_from_config_file(oci_config_file, None)?;
         let manifest = OciImageManifest::build(&layers, &oci_config, annotations);
 
-        let response = self
+        Ok((layers, oci_config, manifest))
+    }
+
+    /// Sign the manifest with the given digest and push the signature, tagged after the digest.
+    async fn push_signature(
+        &mut self,
+        reference: &Reference,
+        auth: &RegistryAuth,
+        key: &SigningKey,
+        digest: &str,
+    ) -> Result<()> {
+        // The subject of the signature needs the size of the manifest. A manifest fetched by
+        // digest cannot change, but check that the registry returned the right one anyway.
+        let manifest_reference = Reference::with_digest(
+            reference.registry().to_owned(),
+            reference.repository().to_owned(),
+            digest.to_owned(),
+        );
+        let (manifest, _) = self
             .oci
-            .push(&reference, &layers, oci_config, &auth, Some(manifest))
+            .pull_manifest_raw(&manifest_reference, auth, &[OCI_IMAGE_MEDIA_TYPE])
             .await
-            .map(|push_response| push_response.manifest_url)
-            .context("cannot push Spin application")?;
+            .context("cannot fetch pushed manifest")?;
+        let manifest_digest = format!("sha256:{}", sha256::hex_digest_from_bytes(&manifest));
+        if manifest_digest != digest {
+            bail!("registry returned manifest {manifest_digest} for {digest}");
+        }
 
-        tracing::info!("Pushed {:?}", response);
+        let (payload, signature) = key.sign(&signing::repository_name(reference), digest)?;
+        let payload_layer = ImageLayer::new(
+            payload,
+            signing::SIGNATURE_PAYLOAD_MEDIA_TYPE.to_string(),
+            Some(BTreeMap::from([(
+                signing::SIGNATURE_ANNOTATION.to_string(),
+                signature,
+            )])),
+        );
+        let config = oci_distribution::client::Config::new(
+            b"{}".to_vec(),
+            EMPTY_CONFIG_MEDIA_TYPE.to_string(),
+            None,
+        );
+        let layers = [payload_layer];
+        let mut signature_manifest = OciImageManifest::build(&layers, &config, None);
+        signature_manifest.artifact_type = Some(signing::SIGNATURE_ARTIFACT_TYPE.to_string());
+        signature_manifest.subject = Some(OciDescriptor {
+            media_type: OCI_IMAGE_MEDIA_TYPE.to_string(),
+            digest: digest.to_owned(),
+            size: manifest.len().try_into()?,
+            ..Default::default()
+        });
 
-        let digest = digest_from_url(&response);
-        Ok(digest)
+        let signature_reference = Reference::with_tag(
+            reference.registry().to_owned(),
+            reference.repository().to_owned(),
+            signing::signature_tag(digest),
+        );
+        self.oci
+            .push(
+                &signature_reference,
+                &layers,
+                config,
+                auth,
+                Some(signature_manifest),
+            )
+            .await
+            .context("cannot push signature")?;
+        tracing::info!("Pushed signature of {reference}@{digest}");
+        Ok(())
+    }
+
+    /// Check that the manifest with the given digest is signed as the trust policy requires.
+    async fn verify_signature(
+        &self,
+        reference: &Reference,
+        auth: &RegistryAuth,
+        digest: &str,
+        policy: &TrustPolicy,
+    ) -> Result<()> {
+        let Some(keys) = policy.required_keys(reference)? else {
+            tracing::debug!("Trust policy does not require {reference} to be signed");
+            return Ok(());
+        };
+
+        let signature_reference = Reference::with_tag(
+            reference.registry().to_owned(),
+            reference.repository().to_owned(),
+            signing::signature_tag(digest),
+        );
+        let (manifest, _) = self
+            .oci
+            .pull_image_manifest(&signature_reference, auth)
+            .await
+            .with_context(|| format!("no signature found for {reference}@{digest}"))?;
+        if manifest.artifact_type.as_deref() != Some(signing::SIGNATURE_ARTIFACT_TYPE) {
+            bail!("{signature_reference} is not a Spin application signature");
+        }
+        if manifest.subject.as_ref().map(|s| s.digest.as_str()) != Some(digest) {
+            bail!("{signature_reference} is not a signature of {digest}");
+        }
+        let layer = manifest
+            .layers
+            .iter()
+            .find(|layer| layer.media_type == signing::SIGNATURE_PAYLOAD_MEDIA_TYPE)
+            .context("signature has no payload")?;
+        let signature = layer
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(signing::SIGNATURE_ANNOTATION))
+            .context("signature payload has no signature")?;
+
+        let mut payload = Vec::new();
+        self.oci
+            .pull_blob(&signature_reference, layer, &mut payload)
+            .await
+            .context("cannot pull signature payload")?;
+        signing::verify(
+            &payload,
+            signature,
+            keys,
+            &signing::repository_name(reference),
+            digest,
+        )?;
+        tracing::info!("Verified signature of {reference}@{digest}");
+        Ok(())
     }
 
     /// Assemble ImageLayers for a locked application using the provided
@@ -408,15 +634,44 @@ impl Client {
         let reference: Reference = reference.parse().context("cannot parse reference")?;
         let auth = Self::auth(&reference).await?;
 
-        // Pull the manifest from the registry.
-        let (manifest, digest) = self.oci.pull_image_manifest(&reference, &auth).await?;
+        // Pull the manifest from the registry, and check that its content has the digest which
+        // the registry reported, and which the reference names if it names one: that digest is
+        // what signatures are verified against.
+        let (manifest_bytes, reported_digest) = self
+            .oci
+            .pull_manifest_raw(
+                &reference,
+                &auth,
+                &[OCI_IMAGE_MEDIA_TYPE, IMAGE_MANIFEST_MEDIA_TYPE],
+            )
+            .await?;
+        let digest = format!("sha256:{}", sha256::hex_digest_from_bytes(&manifest_bytes));
+        for expected in [Some(reported_digest.as_str()), reference.digest()]
+            .into_iter()
+            .flatten()
+        {
+            if expected != digest {
+                bail!("manifest digest {digest} of {reference} does not match {expected}");
+            }
+        }
 
-        let manifest_json = serde_json::to_string(&manifest)?;
-        tracing::debug!("Pulled manifest: {}", manifest_json);
+        // Verify the manifest before writing anything it describes to the cache.
+        if let Some(policy) = &self.opts.trust_policy {
+            self.verify_signature(&reference, &auth, &digest, policy)
+                .await
+                .with_context(|| format!("signature verification failed for {reference}"))?;
+        }
+
+        let manifest: OciImageManifest =
+            serde_json::from_slice(&manifest_bytes).context("invalid OCI image manifest")?;
+        tracing::debug!(
+            "Pulled manifest: {}",
+            String::from_utf8_lossy(&manifest_bytes)
+        );
 
         // Write the manifest in `<cache_root>/registry/oci/manifests/repository:<tag_or_latest>/manifest.json`
         let m = self.manifest_path(&reference.to_string()).await?;
-        fs::write(&m, &manifest_json).await?;
+        fs::write(&m, &manifest_bytes).await?;
 
         // Older published Spin apps feature the locked app config *as* the OCI manifest config layer,
         // while newer versions publish the locked app config as a generic layer alongside others.
@@ -447,21 +702,12 @@ impl Client {
                     tracing::debug!("Pulling layer {}", &layer.digest);
                     let mut bytes = Vec::with_capacity(layer.size.try_into()?);
                     this.oci.pull_blob(&reference, &layer, &mut bytes).await?;
-                    match layer.media_type.as_str() {
-                        SPIN_APPLICATION_MEDIA_TYPE => {
-                            this.write_locked_app_config(&reference.to_string(), &bytes)
-                                .await
-                                .with_context(|| "unable to write locked app config to cache")?;
-                        }
-                        WASM_LAYER_MEDIA_TYPE => {
-                            this.cache.write_wasm(&bytes, &layer.digest).await?;
-                        }
-                        ARCHIVE_MEDIATYPE => {
-                            unpack_archive_layer(&this.cache, &bytes, &layer.digest).await?;
-                        }
-                        _ => {
-                            this.cache.write_data(&bytes, &layer.digest).await?;
-                        }
+                    if layer.media_type == SPIN_APPLICATION_MEDIA_TYPE {
+                        this.write_locked_app_config(&reference.to_string(), &bytes)
+                            .await
+                            .with_context(|| "unable to write locked app config to cache")?;
+                    } else {
+                        this.write_layer_to_cache(&layer, &bytes).await?;
                     }
                     Ok(())
                 }
@@ -474,6 +720,68 @@ impl Client {
         Ok(())
     }
 
+    /// Load a Spin application from an OCI image layout directory, writing its
+    /// content to the cache, and return the serialized locked application.
+    ///
+    /// If `ref_name` is not given, the layout must contain exactly one manifest.
+    ///
+    /// Layouts hold no signatures, so this fails if a rule of the trust policy requires the
+    /// application's reference name to be signed.
+    pub async fn pull_from_layout(
+        &mut self,
+        layout_dir: &Path,
+        ref_name: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let layout = OciLayout::open(layout_dir).await?;
+        let (manifest, digest) = layout.read_manifest(ref_name).await?;
+        if let Some(policy) = &self.opts.trust_policy {
+            let ref_name = match ref_name {
+                Some(ref_name) => Some(ref_name.to_owned()),
+                None => layout.ref_name(&digest).await?,
+            };
+            if policy.requires_signed_layout(ref_name.as_deref().unwrap_or_default()) {
+                bail!(
+                    "cannot verify the signature of an application in OCI layout {}: layouts do not hold signatures, but the trust policy requires {} to be signed",
+                    quoted_path(layout_dir),
+                    ref_name.as_deref().unwrap_or("it"),
+                );
+            }
+        }
+
+        let mut locked_app = None;
+        for layer in &manifest.layers {
+            if layer.media_type == SPIN_APPLICATION_MEDIA_TYPE {
+                locked_app = Some(layout.read_blob(&layer.digest).await?);
+                continue;
+            }
+            // Skip reading if the digest already exists in the wasm or data directories.
+            if self.cache.wasm_file(&layer.digest).is_ok()
+                || self.cache.data_file(&layer.digest).is_ok()
+            {
+                tracing::debug!("Layer {} already exists in cache", &layer.digest);
+                continue;
+            }
+            tracing::debug!("Reading layer {}", &layer.digest);
+            let bytes = layout.read_blob(&layer.digest).await?;
+            self.write_layer_to_cache(layer, &bytes).await?;
+        }
+        tracing::info!("Loaded {digest} from {}", quoted_path(layout_dir));
+
+        locked_app.with_context(|| {
+            format!("OCI manifest {digest} does not contain a locked Spin application")
+        })
+    }
+
+    /// Write the content of a layer to the Wasm or data directory of the cache,
+    /// unpacking it first if it is an archive layer.
+    async fn write_layer_to_cache(&self, layer: &OciDescriptor, bytes: &[u8]) -> Result<()> {
+        match layer.media_type.as_str() {
+            WASM_LAYER_MEDIA_TYPE => self.cache.write_wasm(bytes, &layer.digest).await,
+            ARCHIVE_MEDIATYPE => unpack_archive_layer(&self.cache, bytes, &layer.digest).await,
+            _ => self.cache.write_data(bytes, &layer.digest).await,
+        }
+    }
+
     /// Get the file path to an OCI manifest given a reference.
     /// If the directory for the manifest does not exist, this will create it.
     async fn manifest_path(&self, reference: impl AsRef<str>) -> Result<PathBuf> {
@@ -709,6 +1017,15 @@ pub async fn unpack_archive_layer(
     Ok(())
 }
 
+/// The name under which an application with the given reference is written to an OCI
+/// image layout: the tag of the reference, or `latest` if it has none.
+pub fn layout_ref_name(reference: &str) -> Result<String> {
+    let reference: Reference = reference
+        .parse()
+        .with_context(|| format!("cannot parse reference {reference}"))?;
+    Ok(reference.tag().unwrap_or(LATEST_TAG).to_owned())
+}
+
 fn digest_from_url(manifest_url: &str) -> Option<String> {
     // The URL is in the form "https://host/v2/refname/manifests/sha256:..."
     let manifest_url = Url::parse(manifest_url).ok()?;
@@ -890,6 +1207,111 @@ mod test {
         };
     }
 
+    #[tokio::test]
+    async fn manifests_must_match_their_digest() -> Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A registry which reports a different digest from that of the manifest it serves
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_IMAGE_MEDIA_TYPE,
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": format!("sha256:{}", "0".repeat(64)),
+                "size": 2,
+            },
+            "layers": [],
+        }))?;
+        let reported_digest = format!("sha256:{}", sha256::hex_digest_from_bytes(b"{}"));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let (content_type, body) =
+                    if String::from_utf8_lossy(&request).contains("/manifests/") {
+                        (OCI_IMAGE_MEDIA_TYPE, manifest.as_slice())
+                    } else {
+                        ("application/json", b"{}".as_slice())
+                    };
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nDocker-Content-Digest: {reported_digest}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(body).await;
+            }
+        });
+
+        let dir = tempfile::tempdir()?;
+        let mut client = Client::new(true, Some(dir.path().join("cache"))).await?;
+        let reference = format!("{addr}/test/app:v1");
+        let err = client.pull(&reference).await.unwrap_err();
+        assert!(
+            format!("{err:#}").to_lowercase().contains("digest"),
+            "{err:#}"
+        );
+        assert!(!client.manifest_path(&reference).await?.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn layouts_are_rejected_when_trust_policy_requires_signature() -> Result<()> {
+        use ed25519_dalek::pkcs8::{EncodePublicKey, LineEnding};
+
+        let dir = tempfile::tempdir()?;
+        let key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let pem = key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        std::fs::write(dir.path().join("org.pub"), pem)?;
+
+        let layout_dir = dir.path().join("layout");
+        let layout = OciLayout::create(&layout_dir).await?;
+        let locked_app =
+            ImageLayer::new(b"{}".to_vec(), SPIN_APPLICATION_MEDIA_TYPE.to_owned(), None);
+        let config = oci_distribution::client::Config::new(
+            b"{}".to_vec(),
+            "application/json".to_owned(),
+            None,
+        );
+        layout.write_blob(&locked_app.data).await?;
+        layout.write_blob(&config.data).await?;
+        let manifest = OciImageManifest::build(&[locked_app], &config, None);
+        layout.write_manifest(&manifest, "v1").await?;
+
+        let mut client = Client::new(false, Some(dir.path().join("cache"))).await?;
+        let policy = |rules: &str| -> Result<TrustPolicy> {
+            let path = dir.path().join("trust-policy.toml");
+            std::fs::write(&path, rules)?;
+            TrustPolicy::from_file(&path)
+        };
+
+        // A policy for registry applications only doesn't apply to layouts
+        client.opts.trust_policy = Some(policy(
+            "[[rule]]\nreference = \"ghcr.io/org/*\"\npublic_keys = [\"org.pub\"]\n",
+        )?);
+        assert_eq!(client.pull_from_layout(&layout_dir, None).await?, b"{}");
+
+        client.opts.trust_policy = Some(policy(
+            "[[rule]]\nreference = \"*\"\npublic_keys = [\"org.pub\"]\n",
+        )?);
+        let err = client
+            .pull_from_layout(&layout_dir, Some("v1"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("trust policy"), "{err}");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn can_assemble_layers() {
         use spin_locked_app::locked::LockedComponent;
@@ -971,7 +1393,7 @@ mod test {
             },
             TestCase {
                 name: "One component layer and two file layers",
-                opts: Some(ClientOpts{content_ref_inline_max_size: 0}),
+                opts: Some(ClientOpts{content_ref_inline_max_size: 0, signing_key: None, trust_policy: None}),
                 locked_components: from_json!([{
                 "id": "component1",
                 "source": {
@@ -1015,7 +1437,7 @@ mod test {
             },
             TestCase {
                 name: "One component layer and one dependency component layer",
-                opts: Some(ClientOpts{content_ref_inline_max_size: 0}),
+                opts: Some(ClientOpts{content_ref_inline_max_size: 0, signing_key: None, trust_policy: None}),
                 locked_components: from_json!([{
                 "id": "component1",
                 "source": {
@@ -1073,7 +1495,7 @@ mod test {
             },
             TestCase {
                 name: "Duplicate file paths",
-                opts: Some(ClientOpts{content_ref_inline_max_size: 0}),
+                opts: Some(ClientOpts{content_ref_inline_max_size: 0, signing_key: None, trust_policy: None}),
                 locked_components: from_json!([{
                 "id": "component1",
                 "source": {
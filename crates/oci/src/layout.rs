@@ -0,0 +1,300 @@
+//! Reading and writing Spin applications as OCI image layout directories.
+//!
+//! An image layout is the on-disk form of an OCI repository: an `oci-layout` marker file,
+//! an `index.json` listing manifests by reference name, and content-addressed blobs under
+//! `blobs/sha256`. (See https://github.com/opencontainers/image-spec/blob/main/image-layout.md)
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, ensure, Context, Result};
+use oci_distribution::manifest::{OciImageManifest, OCI_IMAGE_MEDIA_TYPE};
+use serde::{Deserialize, Serialize};
+use spin_common::sha256;
+use spin_common::ui::quoted_path;
+use tokio::fs;
+
+const LAYOUT_FILE: &str = "oci-layout";
+const INDEX_FILE: &str = "index.json";
+const BLOBS_DIR: &str = "blobs";
+const LAYOUT_VERSION: &str = "1.0.0";
+const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+/// Annotation naming the reference of a manifest in the index of a layout.
+const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+
+/// An OCI image layout directory.
+pub(crate) struct OciLayout {
+    root: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayoutMarker {
+    image_layout_version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageIndex {
+    schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media_type: Option<String>,
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    size: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<String, String>,
+}
+
+impl Descriptor {
+    fn ref_name(&self) -> Option<&str> {
+        self.annotations
+            .get(REF_NAME_ANNOTATION)
+            .map(|s| s.as_str())
+    }
+}
+
+impl OciLayout {
+    /// Opens the layout in the given directory, creating it if it does not exist.
+    pub async fn create(root: impl Into<PathBuf>) -> Result<Self> {
+        let layout = Self { root: root.into() };
+        if layout.root.join(LAYOUT_FILE).exists() {
+            layout.check_version().await?;
+        } else {
+            fs::create_dir_all(layout.blobs_dir())
+                .await
+                .with_context(|| {
+                    format!("cannot create OCI layout {}", quoted_path(&layout.root))
+                })?;
+            let marker = LayoutMarker {
+                image_layout_version: LAYOUT_VERSION.to_owned(),
+            };
+            fs::write(layout.root.join(LAYOUT_FILE), serde_json::to_vec(&marker)?).await?;
+        }
+        Ok(layout)
+    }
+
+    /// Opens an existing layout in the given directory.
+    pub async fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let layout = Self { root: root.into() };
+        layout.check_version().await?;
+        Ok(layout)
+    }
+
+    async fn check_version(&self) -> Result<()> {
+        let path = self.root.join(LAYOUT_FILE);
+        let marker = fs::read(&path)
+            .await
+            .with_context(|| format!("{} is not an OCI image layout", quoted_path(&self.root)))?;
+        let marker: LayoutMarker = serde_json::from_slice(&marker)
+            .with_context(|| format!("invalid OCI layout marker {}", quoted_path(&path)))?;
+        ensure!(
+            marker.image_layout_version == LAYOUT_VERSION,
+            "unsupported OCI image layout version {:?}",
+            marker.image_layout_version
+        );
+        Ok(())
+    }
+
+    /// Writes a blob to the layout and returns its digest.
+    pub async fn write_blob(&self, bytes: &[u8]) -> Result<String> {
+        let hex = sha256::hex_digest_from_bytes(bytes);
+        let path = self.blobs_dir().join(&hex);
+        // Blobs are content-addressed, so one that already exists is already correct.
+        if !path.exists() {
+            fs::write(&path, bytes)
+                .await
+                .with_context(|| format!("cannot write blob {}", quoted_path(&path)))?;
+        }
+        Ok(format!("sha256:{hex}"))
+    }
+
+    /// Reads a blob from the layout, checking that its content matches its digest.
+    pub async fn read_blob(&self, digest: &str) -> Result<Vec<u8>> {
+        let Some(hex) = digest.strip_prefix("sha256:") else {
+            bail!("unsupported digest {digest:?}: only sha256 digests are supported");
+        };
+        ensure!(
+            !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            "invalid digest {digest:?}"
+        );
+        let path = self.blobs_dir().join(hex);
+        let bytes = fs::read(&path)
+            .await
+            .with_context(|| format!("cannot read blob {}", quoted_path(&path)))?;
+        ensure!(
+            sha256::hex_digest_from_bytes(&bytes) == hex,
+            "content of blob {} does not match its digest",
+            quoted_path(&path)
+        );
+        Ok(bytes)
+    }
+
+    /// Writes an image manifest to the layout and lists it in the index under the given
+    /// reference name, replacing any manifest previously listed under that name. Returns
+    /// the digest of the manifest.
+    pub async fn write_manifest(
+        &self,
+        manifest: &OciImageManifest,
+        ref_name: &str,
+    ) -> Result<String> {
+        let bytes = serde_json::to_vec(manifest).context("cannot serialize OCI manifest")?;
+        let digest = self.write_blob(&bytes).await?;
+
+        let mut index = self.read_index().await?;
+        index
+            .manifests
+            .retain(|descriptor| descriptor.ref_name() != Some(ref_name));
+        index.manifests.push(Descriptor {
+            media_type: OCI_IMAGE_MEDIA_TYPE.to_owned(),
+            digest: digest.clone(),
+            size: bytes.len().try_into()?,
+            annotations: [(REF_NAME_ANNOTATION.to_owned(), ref_name.to_owned())].into(),
+        });
+        let path = self.root.join(INDEX_FILE);
+        fs::write(&path, serde_json::to_vec_pretty(&index)?)
+            .await
+            .with_context(|| format!("cannot write {}", quoted_path(&path)))?;
+
+        Ok(digest)
+    }
+
+    /// Reads the image manifest listed in the index under the given reference name, and
+    /// returns it with its digest. If no name is given, the index must list exactly one
+    /// manifest.
+    pub async fn read_manifest(
+        &self,
+        ref_name: Option<&str>,
+    ) -> Result<(OciImageManifest, String)> {
+        let index = self.read_index().await?;
+        let descriptor = match ref_name {
+            Some(name) => index
+                .manifests
+                .iter()
+                .find(|descriptor| descriptor.ref_name() == Some(name))
+                .with_context(|| {
+                    format!(
+                        "OCI layout {} has no manifest named {name:?}",
+                        quoted_path(&self.root)
+                    )
+                })?,
+            None => match index.manifests.as_slice() {
+                [descriptor] => descriptor,
+                [] => bail!("OCI layout {} has no manifests", quoted_path(&self.root)),
+                _ => bail!(
+                    "OCI layout {} has more than one manifest; specify which to use by reference name",
+                    quoted_path(&self.root)
+                ),
+            },
+        };
+        ensure!(
+            descriptor.media_type == OCI_IMAGE_MEDIA_TYPE,
+            "unsupported OCI manifest media type {:?}",
+            descriptor.media_type
+        );
+
+        let bytes = self.read_blob(&descriptor.digest).await?;
+        let manifest = serde_json::from_slice(&bytes)
+            .with_context(|| format!("invalid OCI manifest {}", descriptor.digest))?;
+        Ok((manifest, descriptor.digest.clone()))
+    }
+
+    /// Returns the reference name under which the index lists the manifest with the given
+    /// digest, if any.
+    pub async fn ref_name(&self, digest: &str) -> Result<Option<String>> {
+        let index = self.read_index().await?;
+        Ok(index
+            .manifests
+            .iter()
+            .find(|descriptor| descriptor.digest == digest)
+            .and_then(|descriptor| descriptor.ref_name())
+            .map(ToOwned::to_owned))
+    }
+
+    async fn read_index(&self) -> Result<ImageIndex> {
+        let path = self.root.join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(ImageIndex {
+                schema_version: 2,
+                media_type: Some(INDEX_MEDIA_TYPE.to_owned()),
+                manifests: vec![],
+            });
+        }
+        let bytes = fs::read(&path)
+            .await
+            .with_context(|| format!("cannot read {}", quoted_path(&path)))?;
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("invalid OCI image index {}", quoted_path(&path)))
+    }
+
+    fn blobs_dir(&self) -> PathBuf {
+        self.root.join(BLOBS_DIR).join("sha256")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oci_distribution::client::{Config, ImageLayer};
+
+    use super::*;
+
+    fn manifest(content: &[u8]) -> OciImageManifest {
+        let layers = vec![ImageLayer::new(
+            content.to_vec(),
+            "application/octet-stream".to_owned(),
+            None,
+        )];
+        let config = Config::new(b"{}".to_vec(), "application/json".to_owned(), None);
+        OciImageManifest::build(&layers, &config, None)
+    }
+
+    #[tokio::test]
+    async fn manifests_round_trip_by_ref_name() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let layout = OciLayout::create(dir.path()).await?;
+        let first = layout.write_manifest(&manifest(b"one"), "v1").await?;
+        let second = layout.write_manifest(&manifest(b"two"), "v2").await?;
+
+        let layout = OciLayout::open(dir.path()).await?;
+        let (_, digest) = layout.read_manifest(Some("v1")).await?;
+        assert_eq!(digest, first);
+        let (_, digest) = layout.read_manifest(Some("v2")).await?;
+        assert_eq!(digest, second);
+        assert!(layout.read_manifest(None).await.is_err());
+        assert!(layout.read_manifest(Some("v3")).await.is_err());
+
+        // Writing under an existing name replaces the manifest.
+        let replaced = layout.write_manifest(&manifest(b"three"), "v1").await?;
+        let (_, digest) = layout.read_manifest(Some("v1")).await?;
+        assert_eq!(digest, replaced);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blobs_are_checked_against_their_digest() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let layout = OciLayout::create(dir.path()).await?;
+        let digest = layout.write_blob(b"content").await?;
+        assert_eq!(layout.read_blob(&digest).await?, b"content");
+
+        let hex = digest.strip_prefix("sha256:").unwrap();
+        std::fs::write(layout.blobs_dir().join(hex), b"tampered")?;
+        assert!(layout.read_blob(&digest).await.is_err());
+        assert!(layout.read_blob("sha256:../../oci-layout").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn open_requires_a_layout() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(OciLayout::open(dir.path()).await.is_err());
+        Ok(())
+    }
+}
@@ -3,7 +3,9 @@
 
 mod auth;
 pub mod client;
+mod layout;
 mod loader;
+pub mod signing;
 pub mod utils;
 
 pub use client::Client;
@@ -12,6 +14,10 @@ pub use loader::OciLoader;
 /// URL scheme used for the locked app "origin" metadata field for OCI-sourced apps.
 pub const ORIGIN_URL_SCHEME: &str = "vnd.fermyon.origin-oci";
 
+/// URL scheme used for the locked app "origin" metadata field for apps loaded from an
+/// OCI image layout directory.
+pub const ORIGIN_LAYOUT_URL_SCHEME: &str = "vnd.fermyon.origin-oci-layout";
+
 /// Applies heuristics to check if the given string "looks like" it may be
 /// an OCI reference.
 ///
@@ -7,7 +7,7 @@ use spin_common::ui::quoted_path;
 use spin_loader::cache::Cache;
 use spin_locked_app::locked::{ContentPath, ContentRef, LockedApp, LockedComponent};
 
-use crate::{Client, ORIGIN_URL_SCHEME};
+use crate::{Client, ORIGIN_LAYOUT_URL_SCHEME, ORIGIN_URL_SCHEME};
 
 /// OciLoader loads an OCI app in preparation for running with Spin.
 pub struct OciLoader {
@@ -48,16 +48,55 @@ impl OciLoader {
         let locked_content = tokio::fs::read(&lockfile_path)
             .await
             .with_context(|| format!("failed to read from {}", quoted_path(&lockfile_path)))?;
-        let mut locked_app = LockedApp::from_json(&locked_content).with_context(|| {
+        let locked_app = LockedApp::from_json(&locked_content).with_context(|| {
             format!(
                 "failed to decode locked app from {}",
                 quoted_path(&lockfile_path)
             )
         })?;
 
-        // Update origin metadata
         let resolved_reference = Reference::try_from(reference).context("invalid reference")?;
         let origin_uri = format!("{ORIGIN_URL_SCHEME}:{resolved_reference}");
+        self.resolve_locked_app(locked_app, origin_uri, cache).await
+    }
+
+    /// Loads an app from an OCI image layout directory and returns a LockedApp. If
+    /// `ref_name` is not given, the layout must contain exactly one app.
+    pub async fn load_from_layout(
+        &self,
+        client: &mut Client,
+        layout_dir: &Path,
+        ref_name: Option<&str>,
+    ) -> Result<LockedApp> {
+        let locked_content = client
+            .pull_from_layout(layout_dir, ref_name)
+            .await
+            .with_context(|| {
+                format!(
+                    "cannot load Spin application from OCI layout {}",
+                    quoted_path(layout_dir)
+                )
+            })?;
+        let locked_app = LockedApp::from_json(&locked_content).with_context(|| {
+            format!(
+                "failed to decode locked app from OCI layout {}",
+                quoted_path(layout_dir)
+            )
+        })?;
+
+        let layout_dir = std::fs::canonicalize(layout_dir)?;
+        let origin_uri = format!("{ORIGIN_LAYOUT_URL_SCHEME}:{}", layout_dir.display());
+        self.resolve_locked_app(locked_app, origin_uri, &client.cache)
+            .await
+    }
+
+    async fn resolve_locked_app(
+        &self,
+        mut locked_app: LockedApp,
+        origin_uri: String,
+        cache: &Cache,
+    ) -> Result<LockedApp> {
+        // Update origin metadata
         locked_app
             .metadata
             .insert("origin".to_string(), origin_uri.into());
@@ -0,0 +1,412 @@
+//! Signing of pushed applications and verification of pulled ones.
+//!
+//! A signature is an OCI artifact tagged `sha256-<hex>.spin-sig` after the digest of the signed
+//! application manifest, which is how it is found on pull. The tag differs from the one cosign
+//! uses, so that an application can be signed with both. The artifact also names the manifest
+//! as its `subject`, but signatures are not looked up through the referrers API.
+//!
+//! The artifact has a single layer: a JSON payload naming the repository and manifest digest,
+//! with the Ed25519 signature of the payload in an annotation.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use base64::Engine as _;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signer, Verifier};
+use oci_distribution::Reference;
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+
+/// Artifact type of the signature of an application.
+pub const SIGNATURE_ARTIFACT_TYPE: &str = "application/vnd.fermyon.spin.signature.v1+json";
+/// Media type of the layer holding the signed payload.
+pub const SIGNATURE_PAYLOAD_MEDIA_TYPE: &str =
+    "application/vnd.fermyon.spin.signature.payload.v1+json";
+/// Annotation of the payload layer holding the base64-encoded signature of the payload.
+pub const SIGNATURE_ANNOTATION: &str = "com.fermyon.spin.signature";
+
+/// Env var naming the trust policy file used when none is given explicitly.
+pub const TRUST_POLICY_ENV: &str = "SPIN_OCI_TRUST_POLICY";
+
+/// A key for signing applications as they are pushed.
+#[derive(Clone)]
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl SigningKey {
+    /// Loads an Ed25519 private key from a PKCS#8 PEM file, such as one created with
+    /// `openssl genpkey -algorithm ed25519`.
+    pub fn from_pem_file(path: &Path) -> Result<Self> {
+        let pem = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read signing key {}", quoted_path(path)))?;
+        let key = ed25519_dalek::SigningKey::from_pkcs8_pem(&pem).map_err(|e| {
+            anyhow::anyhow!(
+                "{} is not an Ed25519 private key in PKCS#8 PEM format: {e}",
+                quoted_path(path)
+            )
+        })?;
+        Ok(Self(key))
+    }
+
+    /// Signs the manifest with the given digest in the given repository, returning the payload
+    /// and its base64-encoded signature.
+    pub(crate) fn sign(&self, repository: &str, digest: &str) -> Result<(Vec<u8>, String)> {
+        let payload = serde_json::to_vec(&SignaturePayload {
+            repository: repository.to_owned(),
+            digest: digest.to_owned(),
+        })?;
+        let signature = self.0.sign(&payload);
+        Ok((payload, base64_engine().encode(signature.to_bytes())))
+    }
+}
+
+/// What is signed: a manifest digest in a repository.
+#[derive(Debug, Serialize, Deserialize)]
+struct SignaturePayload {
+    repository: String,
+    digest: String,
+}
+
+/// A policy for which applications must be signed, and by whom, to be pulled.
+///
+/// A policy is a TOML file of rules; the first rule whose `reference` matches the repository of
+/// the application applies:
+///
+/// ```toml
+/// [[rule]]
+/// reference = "ghcr.io/my-org/*"
+/// public_keys = ["my-org.pub"]
+///
+/// [[rule]]
+/// reference = "*"
+/// skip = true
+/// ```
+///
+/// A `reference` ending in `*` matches any repository starting with the rest of it. Public keys
+/// are Ed25519 keys in SPKI PEM format; relative paths are relative to the policy file. An
+/// application matching no rule is rejected.
+#[derive(Clone, Debug)]
+pub struct TrustPolicy {
+    rules: Vec<TrustRule>,
+}
+
+#[derive(Clone, Debug)]
+struct TrustRule {
+    reference: String,
+    requirement: Requirement,
+}
+
+#[derive(Clone, Debug)]
+enum Requirement {
+    /// The application need not be signed.
+    Skip,
+    /// The application must be signed by one of the keys.
+    SignedBy(Vec<ed25519_dalek::VerifyingKey>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlTrustPolicy {
+    #[serde(default)]
+    rule: Vec<TomlTrustRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlTrustRule {
+    reference: String,
+    #[serde(default)]
+    public_keys: Vec<PathBuf>,
+    #[serde(default)]
+    skip: bool,
+}
+
+impl TrustPolicy {
+    /// Loads the trust policy from the given file or, if none is given, from the file named by
+    /// the `SPIN_OCI_TRUST_POLICY` env var or the default location, if it exists.
+    ///
+    /// Returns `None` if no policy is configured, in which case pulled applications are not
+    /// verified.
+    pub fn load(path: Option<&Path>) -> Result<Option<Self>> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => match std::env::var_os(TRUST_POLICY_ENV) {
+                Some(path) => PathBuf::from(path),
+                None => match Self::default_path() {
+                    Some(path) if path.exists() => path,
+                    _ => return Ok(None),
+                },
+            },
+        };
+        Self::from_file(&path).map(Some)
+    }
+
+    /// Loads a trust policy from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read trust policy {}", quoted_path(path)))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::from_toml(&contents, base_dir)
+            .with_context(|| format!("invalid trust policy {}", quoted_path(path)))
+    }
+
+    fn from_toml(contents: &str, base_dir: &Path) -> Result<Self> {
+        let policy: TomlTrustPolicy = toml::from_str(contents)?;
+        let rules = policy
+            .rule
+            .into_iter()
+            .map(|rule| {
+                let requirement = match (rule.skip, rule.public_keys.is_empty()) {
+                    (true, true) => Requirement::Skip,
+                    (false, false) => Requirement::SignedBy(
+                        rule.public_keys
+                            .iter()
+                            .map(|path| load_public_key(&base_dir.join(path)))
+                            .collect::<Result<_>>()?,
+                    ),
+                    (true, false) => bail!(
+                        "rule for {:?} cannot both skip verification and list public keys",
+                        rule.reference
+                    ),
+                    (false, true) => bail!(
+                        "rule for {:?} must list public keys or skip verification",
+                        rule.reference
+                    ),
+                };
+                Ok(TrustRule {
+                    reference: rule.reference,
+                    requirement,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("fermyon")
+                .join("registry-trust-policy.toml"),
+        )
+    }
+
+    /// Returns the keys one of which must have signed the given application, or `None` if it
+    /// need not be signed.
+    pub(crate) fn required_keys(
+        &self,
+        reference: &Reference,
+    ) -> Result<Option<&[ed25519_dalek::VerifyingKey]>> {
+        let repository = repository_name(reference);
+        let rule = self
+            .matching_rule(&repository)
+            .with_context(|| format!("no rule of the trust policy applies to {repository}"))?;
+        Ok(match &rule.requirement {
+            Requirement::Skip => None,
+            Requirement::SignedBy(keys) => Some(keys),
+        })
+    }
+
+    /// Returns whether a rule requires the application in an OCI layout with the given
+    /// reference name to be signed. Unlike registry applications, an application in a layout
+    /// matching no rule need not be signed.
+    pub(crate) fn requires_signed_layout(&self, ref_name: &str) -> bool {
+        matches!(
+            self.matching_rule(ref_name),
+            Some(TrustRule {
+                requirement: Requirement::SignedBy(_),
+                ..
+            })
+        )
+    }
+
+    fn matching_rule(&self, name: &str) -> Option<&TrustRule> {
+        self.rules
+            .iter()
+            .find(|rule| match rule.reference.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == rule.reference,
+            })
+    }
+}
+
+fn load_public_key(path: &Path) -> Result<ed25519_dalek::VerifyingKey> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read public key {}", quoted_path(path)))?;
+    ed25519_dalek::VerifyingKey::from_public_key_pem(&pem).map_err(|e| {
+        anyhow::anyhow!(
+            "{} is not an Ed25519 public key in SPKI PEM format: {e}",
+            quoted_path(path)
+        )
+    })
+}
+
+/// Checks that the payload and signature sign the given manifest digest in the given
+/// repository with one of the keys.
+pub(crate) fn verify(
+    payload: &[u8],
+    signature: &str,
+    keys: &[ed25519_dalek::VerifyingKey],
+    repository: &str,
+    digest: &str,
+) -> Result<()> {
+    let signature = base64_engine()
+        .decode(signature)
+        .context("signature is not valid base64")?;
+    let signature =
+        ed25519_dalek::Signature::from_slice(&signature).context("malformed signature")?;
+    if !keys
+        .iter()
+        .any(|key| key.verify(payload, &signature).is_ok())
+    {
+        bail!("signature was not made by any key trusted for {repository}");
+    }
+    let payload: SignaturePayload =
+        serde_json::from_slice(payload).context("malformed signature payload")?;
+    if payload.repository != repository || payload.digest != digest {
+        bail!(
+            "signature is for {}@{}, not {repository}@{digest}",
+            payload.repository,
+            payload.digest
+        );
+    }
+    Ok(())
+}
+
+/// The `<registry>/<repository>` of a reference, which is what signatures and policy rules
+/// apply to.
+pub(crate) fn repository_name(reference: &Reference) -> String {
+    format!("{}/{}", reference.registry(), reference.repository())
+}
+
+/// The tag of the signature of the manifest with the given digest.
+pub(crate) fn signature_tag(digest: &str) -> String {
+    format!("{}.spin-sig", digest.replace(':', "-"))
+}
+
+fn base64_engine() -> base64::engine::GeneralPurpose {
+    base64::engine::general_purpose::STANDARD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:0123456789abcdef";
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey(ed25519_dalek::SigningKey::from_bytes(&[seed; 32]))
+    }
+
+    fn public_key(key: &SigningKey) -> ed25519_dalek::VerifyingKey {
+        key.0.verifying_key()
+    }
+
+    #[test]
+    fn signatures_verify_with_trusted_keys_only() -> Result<()> {
+        let signer = key(1);
+        let (payload, signature) = signer.sign("ghcr.io/org/app", DIGEST)?;
+
+        let trusted = [public_key(&key(2)), public_key(&signer)];
+        verify(&payload, &signature, &trusted, "ghcr.io/org/app", DIGEST)?;
+
+        let untrusted = [public_key(&key(2))];
+        assert!(verify(&payload, &signature, &untrusted, "ghcr.io/org/app", DIGEST).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn signatures_are_bound_to_repository_and_digest() -> Result<()> {
+        let signer = key(1);
+        let trusted = [public_key(&signer)];
+        let (payload, signature) = signer.sign("ghcr.io/org/app", DIGEST)?;
+
+        assert!(verify(&payload, &signature, &trusted, "ghcr.io/org/other", DIGEST).is_err());
+        assert!(verify(
+            &payload,
+            &signature,
+            &trusted,
+            "ghcr.io/org/app",
+            "sha256:f00"
+        )
+        .is_err());
+
+        let mut tampered = payload.clone();
+        tampered[2] ^= 1;
+        assert!(verify(&tampered, &signature, &trusted, "ghcr.io/org/app", DIGEST).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn first_matching_policy_rule_applies() -> Result<()> {
+        use ed25519_dalek::pkcs8::{EncodePublicKey, LineEnding};
+
+        let dir = tempfile::tempdir()?;
+        let pem = public_key(&key(1))
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        std::fs::write(dir.path().join("org.pub"), pem)?;
+
+        let policy = TrustPolicy::from_toml(
+            r#"
+            [[rule]]
+            reference = "ghcr.io/org/*"
+            public_keys = ["org.pub"]
+
+            [[rule]]
+            reference = "localhost:5000/scratch"
+            skip = true
+            "#,
+            dir.path(),
+        )?;
+
+        let reference = |r: &str| -> Reference { r.parse().unwrap() };
+        assert_eq!(
+            policy
+                .required_keys(&reference("ghcr.io/org/app:v1"))?
+                .unwrap(),
+            &[public_key(&key(1))]
+        );
+        assert!(policy
+            .required_keys(&reference("localhost:5000/scratch:latest"))?
+            .is_none());
+        assert!(policy
+            .required_keys(&reference("ghcr.io/other/app:v1"))
+            .is_err());
+
+        assert!(TrustPolicy::from_toml("[[rule]]\nreference = \"*\"\n", dir.path()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn layouts_need_signatures_only_when_a_rule_requires_them() -> Result<()> {
+        use ed25519_dalek::pkcs8::{EncodePublicKey, LineEnding};
+
+        let dir = tempfile::tempdir()?;
+        let pem = public_key(&key(1))
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        std::fs::write(dir.path().join("org.pub"), pem)?;
+
+        let registry_only = TrustPolicy::from_toml(
+            "[[rule]]\nreference = \"ghcr.io/org/*\"\npublic_keys = [\"org.pub\"]\n",
+            dir.path(),
+        )?;
+        assert!(!registry_only.requires_signed_layout("v1"));
+
+        let everything = TrustPolicy::from_toml(
+            "[[rule]]\nreference = \"ghcr.io/org/*\"\nskip = true\n\n[[rule]]\nreference = \"*\"\npublic_keys = [\"org.pub\"]\n",
+            dir.path(),
+        )?;
+        assert!(everything.requires_signed_layout("v1"));
+
+        let skipped =
+            TrustPolicy::from_toml("[[rule]]\nreference = \"*\"\nskip = true\n", dir.path())?;
+        assert!(!skipped.requires_signed_layout("v1"));
+        Ok(())
+    }
+
+    #[test]
+    fn signature_tag_replaces_digest_separator() {
+        assert_eq!(signature_tag(DIGEST), "sha256-0123456789abcdef.spin-sig");
+    }
+}
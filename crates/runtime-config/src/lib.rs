@@ -72,6 +72,8 @@ impl<T> ResolvedRuntimeConfig<T> {
         if let Some(table) = self.toml.get("llm_compute").and_then(Value::as_table) {
             if let Some(ty) = table.get("type").and_then(Value::as_str) {
                 summaries.push(format!("[llm_compute: {ty}"));
+            } else {
+                summaries.push("[llm_compute: per model]".to_string());
             }
         }
         if !summaries.is_empty() {
@@ -326,14 +328,18 @@ impl FactorRuntimeConfigSource<VariablesFactor> for TomlRuntimeConfigSource<'_,
 }
 
 impl FactorRuntimeConfigSource<OutboundPgFactor> for TomlRuntimeConfigSource<'_, '_> {
-    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
-        Ok(None)
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_pg::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_pg::runtime_config::runtime_config_from_toml(&self.toml.table)
     }
 }
 
 impl FactorRuntimeConfigSource<OutboundMysqlFactor> for TomlRuntimeConfigSource<'_, '_> {
-    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
-        Ok(None)
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_mysql::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_mysql::runtime_config::runtime_config_from_toml(&self.toml.table)
     }
 }
 
@@ -6,8 +6,9 @@ use anyhow::Context as _;
 use spin_factors_executor::FactorsExecutor;
 use spin_runtime_config::ResolvedRuntimeConfig;
 use spin_trigger::cli::{
-    FactorsConfig, InitialKvSetterHook, KeyValueDefaultStoreSummaryHook, RuntimeFactorsBuilder,
-    SqlStatementExecutorHook, SqliteDefaultStoreSummaryHook, StdioLoggingExecutorHooks,
+    FactorsConfig, InitialKvSetterHook, KeyValueDefaultStoreSummaryHook, LogFormat,
+    RuntimeFactorsBuilder, SqlStatementExecutorHook, SqliteDefaultStoreSummaryHook,
+    SqliteMigrationsExecutorHook, StdioLoggingExecutorHooks,
 };
 
 /// A [`RuntimeFactorsBuilder`] for [`TriggerFactors`].
@@ -46,10 +47,15 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
         config: &FactorsConfig,
         args: &Self::CliArgs,
     ) -> anyhow::Result<()> {
-        executor.add_hooks(StdioLoggingExecutorHooks::new(
+        let mut stdio_hooks = StdioLoggingExecutorHooks::new(
             config.follow_components.clone(),
             runtime_config.log_dir(),
-        ));
+        );
+        if config.log_format == LogFormat::Json {
+            stdio_hooks = stdio_hooks.with_json_logs(&config.trigger_type);
+        }
+        executor.add_hooks(stdio_hooks);
+        executor.add_hooks(SqliteMigrationsExecutorHook);
         executor.add_hooks(SqlStatementExecutorHook::new(
             args.sqlite_statements.clone(),
         ));
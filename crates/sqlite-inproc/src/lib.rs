@@ -1,13 +1,16 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::OnceLock,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use anyhow::Context as _;
 use async_trait::async_trait;
+use rusqlite::OpenFlags;
 use spin_factor_sqlite::Connection;
 use spin_world::v2::sqlite;
+use tokio::sync::{OwnedMutexGuard, Semaphore};
 
 /// The location of an in-process sqlite database.
 #[derive(Debug, Clone)]
@@ -42,38 +45,189 @@ impl InProcDatabaseLocation {
     }
 }
 
-/// A connection to a sqlite database
-pub struct InProcConnection {
+/// The maximum number of read-only connections an [`InProcDatabase`] opens.
+const MAX_READERS: usize = 8;
+
+/// How long to wait for a lock on the database before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An in-process sqlite database, shared by all connections to it.
+///
+/// File-backed databases are opened in WAL mode with a single writer connection and a pool of
+/// read-only connections, so that reads neither wait on each other nor on writes. In-memory
+/// databases are private to a single connection (see [`InProcDatabase::connect`]), so everything
+/// goes through the writer.
+pub struct InProcDatabase {
     location: InProcDatabaseLocation,
-    connection: OnceLock<Arc<Mutex<rusqlite::Connection>>>,
+    writer: OnceLock<Arc<tokio::sync::Mutex<rusqlite::Connection>>>,
+    idle_readers: Mutex<Vec<rusqlite::Connection>>,
+    reader_permits: Arc<Semaphore>,
 }
 
-impl InProcConnection {
-    pub fn new(location: InProcDatabaseLocation) -> Result<Self, sqlite::Error> {
-        let connection = OnceLock::new();
-        Ok(Self {
+impl InProcDatabase {
+    /// Create a new database. No connections are opened until the database is first used.
+    pub fn new(location: InProcDatabaseLocation) -> Self {
+        Self {
             location,
-            connection,
-        })
+            writer: OnceLock::new(),
+            idle_readers: Mutex::new(Vec::new()),
+            reader_permits: Arc::new(Semaphore::new(MAX_READERS)),
+        }
     }
 
-    pub fn db_connection(&self) -> Result<Arc<Mutex<rusqlite::Connection>>, sqlite::Error> {
-        if let Some(c) = self.connection.get() {
+    /// Open a connection to the database.
+    ///
+    /// In-memory databases are not shared: each connection to one gets a new, empty database of
+    /// its own, which is discarded when the connection is dropped.
+    pub fn connect(self: &Arc<Self>) -> InProcConnection {
+        let database = match &self.location {
+            InProcDatabaseLocation::InMemory => {
+                Arc::new(Self::new(InProcDatabaseLocation::InMemory))
+            }
+            InProcDatabaseLocation::Path(_) => self.clone(),
+        };
+        InProcConnection::new(database)
+    }
+
+    fn writer(&self) -> Result<Arc<tokio::sync::Mutex<rusqlite::Connection>>, sqlite::Error> {
+        if let Some(c) = self.writer.get() {
             return Ok(c.clone());
         }
         // Only create the connection if we failed to get it.
         // We might do duplicate work here if there's a race, but that's fine.
-        let new = self.create_connection()?;
-        Ok(self.connection.get_or_init(|| new)).cloned()
+        let new = Arc::new(tokio::sync::Mutex::new(self.open_writer()?));
+        Ok(self.writer.get_or_init(|| new)).cloned()
     }
 
-    fn create_connection(&self) -> Result<Arc<Mutex<rusqlite::Connection>>, sqlite::Error> {
+    fn open_writer(&self) -> Result<rusqlite::Connection, sqlite::Error> {
         let connection = match &self.location {
             InProcDatabaseLocation::InMemory => rusqlite::Connection::open_in_memory(),
             InProcDatabaseLocation::Path(path) => rusqlite::Connection::open(path),
         }
+        .map_err(io_error)?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(io_error)?;
+        if let InProcDatabaseLocation::Path(_) = &self.location {
+            // The journal mode is persistent, so readers opened later see it too.
+            connection
+                .query_row("PRAGMA journal_mode = WAL", [], |row| {
+                    row.get::<_, String>(0)
+                })
+                .map_err(io_error)?;
+        }
+        Ok(connection)
+    }
+
+    /// Lock the writer connection, waiting at most the busy timeout.
+    async fn lock_writer(&self) -> Result<OwnedMutexGuard<rusqlite::Connection>, sqlite::Error> {
+        let writer = self.writer()?;
+        tokio::time::timeout(BUSY_TIMEOUT, writer.lock_owned())
+            .await
+            .map_err(|_| sqlite::Error::Io("database is locked".to_string()))
+    }
+
+    /// Run a query on a read-only connection.
+    ///
+    /// Returns `None` without running the query if it is not read-only, or if the database
+    /// does not use read-only connections.
+    async fn query_reader(
+        self: &Arc<Self>,
+        query: &str,
+        parameters: Vec<sqlite::Value>,
+    ) -> Result<Option<sqlite::QueryResult>, sqlite::Error> {
+        let InProcDatabaseLocation::Path(path) = &self.location else {
+            return Ok(None);
+        };
+        // Transaction control statements count as read-only, but must go to the writer.
+        if is_transaction_control(query) {
+            return Ok(None);
+        }
+        // Make sure the database file exists and is in WAL mode before opening readers.
+        self.writer()?;
+        let permit = self
+            .reader_permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(io_error)?;
+        let this = self.clone();
+        let path = path.clone();
+        let query = query.to_owned();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let reader = match this.idle_readers.lock().unwrap().pop() {
+                Some(reader) => reader,
+                None => open_reader(&path)?,
+            };
+            let result = prepare_query(&reader, &query).and_then(|mut statement| {
+                if !statement.readonly() {
+                    return Ok(None);
+                }
+                execute_statement(&mut statement, parameters).map(Some)
+            });
+            this.idle_readers.lock().unwrap().push(reader);
+            result
+        })
+        .await
+        .context("internal runtime error")
+        .map_err(|e| sqlite::Error::Io(e.to_string()))?
+    }
+}
+
+fn open_reader(path: &Path) -> Result<rusqlite::Connection, sqlite::Error> {
+    let connection = rusqlite::Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(io_error)?;
+    connection.busy_timeout(BUSY_TIMEOUT).map_err(io_error)?;
+    Ok(connection)
+}
+
+/// A connection to a sqlite database
+pub struct InProcConnection {
+    database: Arc<InProcDatabase>,
+    /// The locked writer connection while a transaction is in progress.
+    transaction: tokio::sync::Mutex<Option<OwnedMutexGuard<rusqlite::Connection>>>,
+}
+
+impl InProcConnection {
+    pub fn new(database: Arc<InProcDatabase>) -> Self {
+        Self {
+            database,
+            transaction: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Run `f` on the writer connection: either the connection of the transaction in progress, or
+    /// a newly locked one.
+    ///
+    /// If the writer is inside a transaction afterwards (whether through [`Connection::begin_transaction`]
+    /// or a `BEGIN` statement), it stays locked for this connection until the transaction ends.
+    async fn with_writer<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&rusqlite::Connection) -> Result<T, sqlite::Error> + Send + 'static,
+    ) -> Result<T, sqlite::Error> {
+        let mut transaction = self.transaction.lock().await;
+        let writer = match transaction.take() {
+            Some(writer) => writer,
+            None => self.database.lock_writer().await?,
+        };
+        // Tell the tokio runtime that we're going to block while using the connection
+        let (writer, result) = tokio::task::spawn_blocking(move || {
+            let result = f(&writer);
+            (writer, result)
+        })
+        .await
+        .context("internal runtime error")
         .map_err(|e| sqlite::Error::Io(e.to_string()))?;
-        Ok(Arc::new(Mutex::new(connection)))
+        if !writer.is_autocommit() {
+            *transaction = Some(writer);
+        }
+        result
+    }
+
+    async fn in_transaction(&self) -> bool {
+        self.transaction.lock().await.is_some()
     }
 }
 
@@ -84,46 +238,108 @@ impl Connection for InProcConnection {
         query: &str,
         parameters: Vec<sqlite::Value>,
     ) -> Result<sqlite::QueryResult, sqlite::Error> {
-        let connection = self.db_connection()?;
+        // Queries inside a transaction must see its uncommitted writes, so they go to the writer.
+        if !self.in_transaction().await {
+            if let Some(result) = self
+                .database
+                .query_reader(query, parameters.clone())
+                .await?
+            {
+                return Ok(result);
+            }
+        }
         let query = query.to_owned();
-        // Tell the tokio runtime that we're going to block while making the query
-        tokio::task::spawn_blocking(move || execute_query(&connection, &query, parameters))
-            .await
-            .context("internal runtime error")
-            .map_err(|e| sqlite::Error::Io(e.to_string()))?
+        self.with_writer(move |conn| {
+            let mut statement = prepare_query(conn, &query)?;
+            execute_statement(&mut statement, parameters)
+        })
+        .await
     }
 
     async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
-        let connection = self.db_connection()?;
         let statements = statements.to_owned();
-        tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
-            conn.execute_batch(&statements)
-                .context("failed to execute batch statements")
-        })
-        .await?
-        .context("failed to spawn blocking task")?;
+        self.with_writer(move |conn| conn.execute_batch(&statements).map_err(io_error))
+            .await
+            .context("failed to execute batch statements")?;
         Ok(())
     }
 
+    async fn begin_transaction(&self) -> Result<(), sqlite::Error> {
+        if self.in_transaction().await {
+            return Err(sqlite::Error::Io(
+                "a transaction is already in progress".to_string(),
+            ));
+        }
+        // Take the write lock immediately, so that the transaction can't fail to upgrade later.
+        self.with_writer(|conn| conn.execute_batch("BEGIN IMMEDIATE").map_err(io_error))
+            .await
+    }
+
+    async fn commit(&self) -> Result<(), sqlite::Error> {
+        if !self.in_transaction().await {
+            return Err(sqlite::Error::Io(
+                "no transaction is in progress".to_string(),
+            ));
+        }
+        self.with_writer(|conn| conn.execute_batch("COMMIT").map_err(io_error))
+            .await
+    }
+
+    async fn rollback(&self) -> Result<(), sqlite::Error> {
+        if !self.in_transaction().await {
+            return Err(sqlite::Error::Io(
+                "no transaction is in progress".to_string(),
+            ));
+        }
+        self.with_writer(|conn| conn.execute_batch("ROLLBACK").map_err(io_error))
+            .await
+    }
+
     fn summary(&self) -> Option<String> {
-        Some(match &self.location {
+        Some(match &self.database.location {
             InProcDatabaseLocation::InMemory => "a temporary in-memory database".to_string(),
             InProcDatabaseLocation::Path(path) => format!("\"{}\"", path.display()),
         })
     }
 }
 
-// This function lives outside the query function to make it more readable.
-fn execute_query(
-    connection: &Mutex<rusqlite::Connection>,
+impl Drop for InProcConnection {
+    fn drop(&mut self) {
+        // Roll back any transaction still in progress, which also releases the writer.
+        if let Some(writer) = self.transaction.get_mut().take() {
+            let _ = writer.execute_batch("ROLLBACK");
+        }
+    }
+}
+
+/// Returns whether the statement begins or ends a transaction.
+fn is_transaction_control(query: &str) -> bool {
+    let keyword = query
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    ["BEGIN", "COMMIT", "END", "ROLLBACK", "SAVEPOINT", "RELEASE"]
+        .iter()
+        .any(|k| keyword.eq_ignore_ascii_case(k))
+}
+
+fn io_error(e: impl std::fmt::Display) -> sqlite::Error {
+    sqlite::Error::Io(e.to_string())
+}
+
+fn prepare_query<'conn>(
+    conn: &'conn rusqlite::Connection,
     query: &str,
+) -> Result<rusqlite::CachedStatement<'conn>, sqlite::Error> {
+    conn.prepare_cached(query).map_err(io_error)
+}
+
+// This function lives outside the query function to make it more readable.
+fn execute_statement(
+    statement: &mut rusqlite::Statement,
     parameters: Vec<sqlite::Value>,
 ) -> Result<sqlite::QueryResult, sqlite::Error> {
-    let conn = connection.lock().unwrap();
-    let mut statement = conn
-        .prepare_cached(query)
-        .map_err(|e| sqlite::Error::Io(e.to_string()))?;
     let columns = statement
         .column_names()
         .into_iter()
@@ -145,10 +361,10 @@ fn execute_query(
                 Ok(sqlite::RowResult { values })
             },
         )
-        .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+        .map_err(io_error)?;
     let rows = rows
         .into_iter()
-        .map(|r| r.map_err(|e| sqlite::Error::Io(e.to_string())))
+        .map(|r| r.map_err(io_error))
         .collect::<Result<_, sqlite::Error>>()?;
     Ok(sqlite::QueryResult { columns, rows })
 }
@@ -182,3 +398,125 @@ impl rusqlite::types::FromSql for ValueWrapper {
         Ok(ValueWrapper(value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(dir: &tempfile::TempDir) -> Arc<InProcDatabase> {
+        let location = InProcDatabaseLocation::Path(dir.path().join("test.db"));
+        Arc::new(InProcDatabase::new(location))
+    }
+
+    async fn count(conn: &InProcConnection) -> i64 {
+        let result = conn.query("SELECT COUNT(*) FROM t", vec![]).await.unwrap();
+        match result.rows[0].values[0] {
+            sqlite::Value::Integer(n) => n,
+            ref other => panic!("unexpected value {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn transactions_commit_and_roll_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = database(&dir);
+        let conn = InProcConnection::new(database.clone());
+        let other = InProcConnection::new(database);
+        conn.execute_batch("CREATE TABLE t (x INTEGER)")
+            .await
+            .unwrap();
+
+        conn.begin_transaction().await.unwrap();
+        conn.query("INSERT INTO t VALUES (1)", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count(&conn).await, 1);
+        // Uncommitted writes are invisible to other connections
+        assert_eq!(count(&other).await, 0);
+        conn.rollback().await.unwrap();
+        assert_eq!(count(&conn).await, 0);
+
+        conn.begin_transaction().await.unwrap();
+        assert!(conn.begin_transaction().await.is_err());
+        conn.query("INSERT INTO t VALUES (1)", vec![])
+            .await
+            .unwrap();
+        conn.commit().await.unwrap();
+        assert_eq!(count(&other).await, 1);
+        assert!(conn.commit().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn dropping_connection_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = database(&dir);
+        let conn = InProcConnection::new(database.clone());
+        conn.execute_batch("CREATE TABLE t (x INTEGER)")
+            .await
+            .unwrap();
+        conn.begin_transaction().await.unwrap();
+        conn.query("INSERT INTO t VALUES (1)", vec![])
+            .await
+            .unwrap();
+        drop(conn);
+
+        let conn = InProcConnection::new(database);
+        assert_eq!(count(&conn).await, 0);
+        // The writer was released by the rollback
+        conn.query("INSERT INTO t VALUES (1)", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count(&conn).await, 1);
+    }
+
+    #[tokio::test]
+    async fn statement_transactions_use_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = InProcConnection::new(database(&dir));
+        conn.execute_batch("CREATE TABLE t (x INTEGER)")
+            .await
+            .unwrap();
+        conn.query("BEGIN", vec![]).await.unwrap();
+        conn.query("INSERT INTO t VALUES (1)", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count(&conn).await, 1);
+        conn.query("ROLLBACK", vec![]).await.unwrap();
+        assert_eq!(count(&conn).await, 0);
+    }
+
+    #[tokio::test]
+    async fn in_memory_databases_are_private_to_each_connection() {
+        let database = Arc::new(InProcDatabase::new(InProcDatabaseLocation::InMemory));
+        let conn = database.connect();
+        let other = database.connect();
+        conn.execute_batch("CREATE TABLE t (x INTEGER)")
+            .await
+            .unwrap();
+        assert_eq!(count(&conn).await, 0);
+        assert!(other.query("SELECT COUNT(*) FROM t", vec![]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn file_databases_are_shared_between_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = database(&dir);
+        let conn = database.connect();
+        let other = database.connect();
+        conn.execute_batch("CREATE TABLE t (x INTEGER)")
+            .await
+            .unwrap();
+        conn.query("INSERT INTO t VALUES (1)", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count(&other).await, 1);
+    }
+
+    #[test]
+    fn detects_transaction_control() {
+        assert!(is_transaction_control("  begin immediate"));
+        assert!(is_transaction_control("COMMIT;"));
+        assert!(!is_transaction_control("SELECT 1"));
+        assert!(!is_transaction_control("BEGINNING"));
+    }
+}
@@ -3,7 +3,7 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use serde::Deserialize;
@@ -124,10 +124,19 @@ impl RuntimeConfigResolver {
             .default_database_dir
             .as_deref()
             .map(|p| p.join(DEFAULT_SQLITE_DB_FILENAME));
+        // The database is shared by all connections (unless it's in memory), but only created
+        // once first used.
+        let database = OnceLock::new();
         let factory = move || {
-            let location = InProcDatabaseLocation::from_path(path.clone())?;
-            let connection = spin_sqlite_inproc::InProcConnection::new(location)?;
-            Ok(Box::new(connection) as _)
+            let database = match database.get() {
+                Some(database) => Arc::clone(database),
+                None => {
+                    let location = InProcDatabaseLocation::from_path(path.clone())?;
+                    let new = Arc::new(spin_sqlite_inproc::InProcDatabase::new(location));
+                    Arc::clone(database.get_or_init(|| new))
+                }
+            };
+            Ok(Box::new(database.connect()) as _)
         };
         Arc::new(factory)
     }
@@ -152,10 +161,8 @@ impl InProcDatabase {
             .as_ref()
             .map(|p| resolve_relative_path(p, base_dir));
         let location = InProcDatabaseLocation::from_path(path)?;
-        let factory = move || {
-            let connection = spin_sqlite_inproc::InProcConnection::new(location.clone())?;
-            Ok(Box::new(connection) as _)
-        };
+        let database = Arc::new(spin_sqlite_inproc::InProcDatabase::new(location));
+        let factory = move || Ok(Box::new(database.connect()) as _);
         Ok(factory)
     }
 }
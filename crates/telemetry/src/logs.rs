@@ -1,13 +1,18 @@
 use std::{ascii::escape_default, sync::OnceLock, time::Duration};
 
 use anyhow::bail;
-use opentelemetry::logs::{LogRecord, Logger, LoggerProvider};
+use opentelemetry::{
+    logs::{LogRecord, Logger, LoggerProvider},
+    trace::{SpanContext, TraceContextExt},
+};
 use opentelemetry_sdk::{
     logs::{BatchConfigBuilder, BatchLogProcessor, Logger as SdkLogger},
     resource::{EnvResourceDetector, TelemetryResourceDetector},
     Resource,
 };
 
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
 use crate::{
     detector::SpinResourceDetector,
     env::{self, otel_logs_enabled, OtlpProtocol},
@@ -22,6 +27,68 @@ pub fn handle_app_log(buf: &[u8]) {
     app_log_to_tracing_event(buf);
 }
 
+/// Where a structured application log line came from.
+pub struct AppLogContext<'a> {
+    /// The ID of the component which wrote the line.
+    pub component_id: &'a str,
+    /// The type of the trigger which invoked the component.
+    pub trigger_type: &'a str,
+    /// The route the component serves, if it serves just one.
+    pub route: Option<&'a str>,
+    /// The stream the line was written to, `stdout` or `stderr`.
+    pub stream: &'a str,
+}
+
+/// Handle a line of an application log in structured mode. Forwards the line to OTel with its
+/// context as attributes and correlated with the current trace, and emits it as a tracing event.
+pub fn handle_structured_app_log(line: &str, context: &AppLogContext) {
+    structured_app_log_to_otel(line, context);
+    app_log_to_tracing_event(line.as_bytes());
+}
+
+/// The trace ID and span ID of the current span, in hex, if it is part of a trace.
+pub fn current_trace_ids() -> Option<(String, String)> {
+    let span_context = current_span_context()?;
+    Some((
+        span_context.trace_id().to_string(),
+        span_context.span_id().to_string(),
+    ))
+}
+
+fn current_span_context() -> Option<SpanContext> {
+    let context = tracing::Span::current().context();
+    let span_context = context.span().span_context().clone();
+    span_context.is_valid().then_some(span_context)
+}
+
+/// Forward a structured app log line to OTel.
+fn structured_app_log_to_otel(line: &str, context: &AppLogContext) {
+    if !otel_logs_enabled() {
+        return;
+    }
+
+    if let Some(logger) = LOGGER.get() {
+        let mut record = logger.create_log_record();
+        record.set_body(line.to_string().into());
+        record.add_attribute("component_id", context.component_id.to_string());
+        record.add_attribute("trigger_type", context.trigger_type.to_string());
+        if let Some(route) = context.route {
+            record.add_attribute("route", route.to_string());
+        }
+        record.add_attribute("stream", context.stream.to_string());
+        if let Some(span_context) = current_span_context() {
+            record.set_trace_context(
+                span_context.trace_id(),
+                span_context.span_id(),
+                Some(span_context.trace_flags()),
+            );
+        }
+        logger.emit(record);
+    } else {
+        tracing::trace!("OTel logger not initialized, failed to log");
+    }
+}
+
 /// Forward the app log to OTel.
 fn app_log_to_otel(buf: &[u8]) {
     if !otel_logs_enabled() {
@@ -0,0 +1,463 @@
+//! The Spin cron trigger, which invokes components on a schedule.
+
+use std::{future::Future, sync::Arc};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{cli::NoCliArgs, App, ShutdownSignal, Trigger, TriggerApp};
+use spin_world::exports::spin::cron::inbound_cron;
+use tokio::sync::Semaphore;
+use tracing::{instrument, Level};
+
+/// The Spin cron trigger.
+pub struct CronTrigger;
+
+/// Cron trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// When to invoke the component, with fields for the second, minute, hour, day of
+    /// month, month, day of week and (optionally) year, such as `0 */5 * * * *`
+    cron_expression: String,
+    /// IANA time zone in which to evaluate the cron expression, such as
+    /// `Europe/London`. Defaults to UTC.
+    time_zone: Option<String>,
+    /// What to do when an occurrence is due while the previous one is still running
+    #[serde(default)]
+    overlap: OverlapPolicy,
+}
+
+/// What to do when an occurrence of a schedule is due while the component is still
+/// handling the previous one.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum OverlapPolicy {
+    /// Skip the occurrence.
+    #[default]
+    Skip,
+    /// Invoke the component for the occurrence once the previous one has been handled.
+    /// At most one occurrence is queued: further occurrences due meanwhile are skipped.
+    Queue,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for CronTrigger {
+    const TYPE: &'static str = "cron";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        self.run_with_shutdown(trigger_app, ShutdownSignal::new())
+            .await
+    }
+
+    async fn run_with_shutdown(
+        self,
+        trigger_app: TriggerApp<Self, F>,
+        shutdown: ShutdownSignal,
+    ) -> anyhow::Result<()> {
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+
+        // Parse all schedules before starting any schedulers
+        let schedules = trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .map(|(_, config)| CronSchedule::new(config))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        println!("Active Schedules:");
+        for schedule in &schedules {
+            println!(
+                "\t{}: {} ({})",
+                schedule.component_id, schedule.expression, schedule.time_zone
+            );
+        }
+
+        let trigger_app = Arc::new(trigger_app);
+        let scheduler_tasks = schedules.into_iter().map(|schedule| {
+            let scheduler = Arc::new(Scheduler {
+                trigger_app: trigger_app.clone(),
+                schedule,
+            });
+            tokio::spawn(scheduler.run(shutdown.clone()))
+        });
+        for res in futures::future::join_all(scheduler_tasks).await {
+            res?;
+        }
+        Ok(())
+    }
+}
+
+/// A component's schedule, parsed from its trigger configuration.
+struct CronSchedule {
+    component_id: String,
+    expression: String,
+    schedule: cron::Schedule,
+    time_zone: Tz,
+    overlap: OverlapPolicy,
+}
+
+impl CronSchedule {
+    fn new(config: TriggerConfig) -> anyhow::Result<Self> {
+        let component_id = config.component;
+        let expression = config.cron_expression;
+        let schedule = expression.parse().with_context(|| {
+            format!("invalid cron expression {expression:?} for component {component_id}")
+        })?;
+        let time_zone = match &config.time_zone {
+            Some(time_zone) => time_zone.parse().map_err(|err| {
+                anyhow::anyhow!(
+                    "invalid time zone {time_zone:?} for component {component_id}: {err}"
+                )
+            })?,
+            None => Tz::UTC,
+        };
+        Ok(Self {
+            component_id,
+            expression,
+            schedule,
+            time_zone,
+            overlap: config.overlap,
+        })
+    }
+
+    /// Returns the first occurrence of the schedule after the given time, if any.
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule
+            .after(&after.with_timezone(&self.time_zone))
+            .next()
+            .map(|occurrence| occurrence.with_timezone(&Utc))
+    }
+}
+
+/// Invokes a component on each occurrence of its schedule.
+struct Scheduler<F: RuntimeFactors> {
+    trigger_app: Arc<TriggerApp<CronTrigger, F>>,
+    schedule: CronSchedule,
+}
+
+impl<F: RuntimeFactors> Scheduler<F> {
+    async fn run(self: Arc<Self>, shutdown: ShutdownSignal) {
+        let CronSchedule {
+            component_id,
+            expression,
+            overlap,
+            ..
+        } = &self.schedule;
+
+        let occurrences = Occurrences::new(*overlap);
+        let mut last = Utc::now();
+        loop {
+            // Occurrences missed while the process was suspended are not made up for
+            let Some(next) = self.schedule.next_after(last.max(Utc::now())) else {
+                tracing::info!(
+                    "Schedule {expression:?} for component {component_id} has no more occurrences"
+                );
+                break;
+            };
+            let delay = (next - Utc::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown.requested() => break,
+            }
+            last = next;
+
+            let scheduler = self.clone();
+            let occurrence = async move { scheduler.handle_occurrence(next).await };
+            if !occurrences.start(&shutdown, occurrence) {
+                tracing::warn!(
+                    "Skipping occurrence at {next} for component {component_id}: the previous occurrence is still being handled"
+                );
+            }
+        }
+
+        // Let the occurrence in progress finish. Any queued behind it are dropped.
+        occurrences.finish().await;
+    }
+
+    async fn handle_occurrence(&self, scheduled_time: DateTime<Utc>) {
+        let component_id = &self.schedule.component_id;
+        if let Err(err) = self.dispatch_handler(scheduled_time).await {
+            tracing::info!("Component {component_id} handler failed: {err}");
+        }
+    }
+
+    #[instrument(name = "spin_trigger_cron.handle_scheduled_event", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("cron {}", self.schedule.expression),
+        component_id = %self.schedule.component_id,
+        scheduled_time = %scheduled_time,
+    ))]
+    async fn dispatch_handler(&self, scheduled_time: DateTime<Utc>) -> anyhow::Result<()> {
+        let component_id = &self.schedule.component_id;
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "cron",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id.as_str()
+        );
+
+        let (instance, mut store) = self
+            .trigger_app
+            .prepare(component_id)?
+            .instantiate(())
+            .await?;
+
+        let guest_indices = inbound_cron::GuestIndices::new_instance(&mut store, &instance)?;
+        let guest = guest_indices.load(&mut store, &instance)?;
+
+        let event = inbound_cron::ScheduledEvent {
+            scheduled_time: scheduled_time.timestamp_millis().try_into()?,
+            cron_expression: self.schedule.expression.clone(),
+            time_zone: self.schedule.time_zone.name().to_owned(),
+        };
+        match guest
+            .call_handle_scheduled_event(&mut store, &event)
+            .await?
+        {
+            Ok(()) => Ok(()),
+            Err(inbound_cron::Error::Other(err)) => {
+                anyhow::bail!("Cron handler returned an error: {err}")
+            }
+        }
+    }
+}
+
+/// Starts handling the occurrences of a schedule according to its overlap policy.
+struct Occurrences {
+    overlap: OverlapPolicy,
+    /// Held by the occurrence being handled, if any
+    in_progress: Arc<Semaphore>,
+    /// Held by the occurrence queued behind it, if any
+    queued: Arc<Semaphore>,
+}
+
+impl Occurrences {
+    fn new(overlap: OverlapPolicy) -> Self {
+        Self {
+            overlap,
+            in_progress: Arc::new(Semaphore::new(1)),
+            queued: Arc::new(Semaphore::new(1)),
+        }
+    }
+
+    /// Starts handling an occurrence in the background, or queues it behind the
+    /// occurrence being handled. Returns `false` if the occurrence is skipped instead.
+    fn start(
+        &self,
+        shutdown: &ShutdownSignal,
+        occurrence: impl Future<Output = ()> + Send + 'static,
+    ) -> bool {
+        match self.overlap {
+            OverlapPolicy::Skip => {
+                let Ok(permit) = self.in_progress.clone().try_acquire_owned() else {
+                    return false;
+                };
+                tokio::spawn(async move {
+                    occurrence.await;
+                    drop(permit);
+                });
+            }
+            OverlapPolicy::Queue => {
+                let Ok(queued) = self.queued.clone().try_acquire_owned() else {
+                    return false;
+                };
+                let in_progress = self.in_progress.clone();
+                let shutdown = shutdown.clone();
+                tokio::spawn(async move {
+                    // Queued occurrences are dropped if shutdown is requested
+                    let permit = tokio::select! {
+                        biased;
+                        _ = shutdown.requested() => return,
+                        permit = in_progress.acquire_owned() => permit,
+                    };
+                    drop(queued);
+                    occurrence.await;
+                    drop(permit);
+                });
+            }
+        }
+        true
+    }
+
+    /// Waits until the occurrence being handled, if any, has finished.
+    async fn finish(&self) {
+        let _ = self.in_progress.acquire().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    fn schedule(config: &str) -> anyhow::Result<CronSchedule> {
+        let config: TriggerConfig = toml::from_str(config)?;
+        CronSchedule::new(config)
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    /// An occurrence which takes ten seconds to handle, and then counts itself as handled.
+    fn occurrence(handled: &Arc<AtomicU32>) -> impl Future<Output = ()> + Send + 'static {
+        let handled = handled.clone();
+        async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            handled.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    async fn sleep_secs(secs: u64) {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+    }
+
+    #[test]
+    fn config_defaults() {
+        let schedule = schedule(
+            r#"
+            component = "tick"
+            cron_expression = "0 */5 * * * *"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(schedule.time_zone, Tz::UTC);
+        assert_eq!(schedule.overlap, OverlapPolicy::Skip);
+        assert_eq!(
+            schedule.next_after(utc("2024-01-01T10:02:30Z")),
+            Some(utc("2024-01-01T10:05:00Z"))
+        );
+    }
+
+    #[test]
+    fn occurrences_follow_time_zone() {
+        let schedule = schedule(
+            r#"
+            component = "tick"
+            cron_expression = "0 0 9 * * *"
+            time_zone = "Europe/London"
+            overlap = "queue"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(schedule.overlap, OverlapPolicy::Queue);
+        // GMT in winter, BST in summer
+        assert_eq!(
+            schedule.next_after(utc("2024-01-15T00:00:00Z")),
+            Some(utc("2024-01-15T09:00:00Z"))
+        );
+        assert_eq!(
+            schedule.next_after(utc("2024-07-15T00:00:00Z")),
+            Some(utc("2024-07-15T08:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn schedules_can_end() {
+        let schedule = schedule(
+            r#"
+            component = "tick"
+            cron_expression = "0 0 0 1 1 * 2020"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(schedule.next_after(utc("2024-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        for invalid in [
+            r#"
+            component = "tick"
+            cron_expression = "every five minutes"
+            "#,
+            r#"
+            component = "tick"
+            cron_expression = "0 */5 * * * *"
+            time_zone = "Mars/Olympus_Mons"
+            "#,
+            r#"
+            component = "tick"
+            cron_expression = "0 */5 * * * *"
+            overlap = "parallel"
+            "#,
+        ] {
+            assert!(schedule(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn overlapping_occurrences_are_skipped() {
+        let occurrences = Occurrences::new(OverlapPolicy::Skip);
+        let shutdown = ShutdownSignal::new();
+        let handled = Arc::new(AtomicU32::new(0));
+
+        assert!(occurrences.start(&shutdown, occurrence(&handled)));
+        sleep_secs(1).await;
+        assert!(!occurrences.start(&shutdown, occurrence(&handled)));
+        sleep_secs(10).await;
+        assert_eq!(handled.load(Ordering::SeqCst), 1);
+
+        // Once the previous occurrence has been handled, the next one starts
+        assert!(occurrences.start(&shutdown, occurrence(&handled)));
+        occurrences.finish().await;
+        assert_eq!(handled.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn one_overlapping_occurrence_is_queued() {
+        let occurrences = Occurrences::new(OverlapPolicy::Queue);
+        let shutdown = ShutdownSignal::new();
+        let handled = Arc::new(AtomicU32::new(0));
+
+        assert!(occurrences.start(&shutdown, occurrence(&handled)));
+        sleep_secs(1).await;
+        assert!(occurrences.start(&shutdown, occurrence(&handled)));
+        sleep_secs(1).await;
+        assert!(!occurrences.start(&shutdown, occurrence(&handled)));
+
+        // The queued occurrence starts once the first has been handled, at 10s
+        sleep_secs(10).await;
+        assert_eq!(handled.load(Ordering::SeqCst), 1);
+        assert!(occurrences.start(&shutdown, occurrence(&handled)));
+        sleep_secs(10).await;
+        assert_eq!(handled.load(Ordering::SeqCst), 2);
+        occurrences.finish().await;
+        assert_eq!(handled.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queued_occurrences_are_dropped_on_shutdown() {
+        let occurrences = Occurrences::new(OverlapPolicy::Queue);
+        let shutdown = ShutdownSignal::new();
+        let handled = Arc::new(AtomicU32::new(0));
+
+        assert!(occurrences.start(&shutdown, occurrence(&handled)));
+        sleep_secs(1).await;
+        assert!(occurrences.start(&shutdown, occurrence(&handled)));
+        shutdown.request();
+
+        // The occurrence in progress is handled, but the queued one isn't
+        let started = tokio::time::Instant::now();
+        occurrences.finish().await;
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(handled.load(Ordering::SeqCst), 1);
+        sleep_secs(60).await;
+        assert_eq!(handled.load(Ordering::SeqCst), 1);
+    }
+}
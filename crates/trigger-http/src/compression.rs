@@ -0,0 +1,321 @@
+use async_compression::tokio::write::{BrotliEncoder, GzipEncoder, ZstdEncoder};
+use futures::{future, stream, TryStreamExt};
+use http::{header, HeaderMap, HeaderValue, Method, Response, StatusCode};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Bytes, Frame};
+use spin_http::config::{ContentEncoding, HttpCompressionConfig};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// Compresses the response body if the route's compression config allows it and the
+/// client accepts one of its encodings.
+pub(crate) fn compress_response(
+    config: &HttpCompressionConfig,
+    method: &Method,
+    accept_encoding: Option<&HeaderValue>,
+    mut response: Response<Body>,
+) -> Response<Body> {
+    if !is_compressible(config, method, &response) {
+        return response;
+    }
+    // The response depends on the request's Accept-Encoding whether or not it is compressed.
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    let Some(encoding) = accept_encoding
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| negotiate(&config.encodings, value))
+    else {
+        return response;
+    };
+
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    // The compressed representation is not byte-for-byte the one the guest tagged.
+    if let Some(etag) = parts.headers.get_mut(header::ETAG) {
+        if !etag.as_bytes().starts_with(b"W/") {
+            let mut weak = b"W/".to_vec();
+            weak.extend_from_slice(etag.as_bytes());
+            if let Ok(weak) = HeaderValue::from_bytes(&weak) {
+                *etag = weak;
+            }
+        }
+    }
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    Response::from_parts(parts, compress_body(body, encoding))
+}
+
+/// Returns whether the response is eligible for compression.
+fn is_compressible(
+    config: &HttpCompressionConfig,
+    method: &Method,
+    response: &Response<Body>,
+) -> bool {
+    let headers = response.headers();
+    if method == Method::HEAD
+        || matches!(
+            response.status(),
+            StatusCode::NO_CONTENT | StatusCode::PARTIAL_CONTENT | StatusCode::NOT_MODIFIED
+        )
+        || response.status().is_informational()
+        || headers.contains_key(header::CONTENT_ENCODING)
+        || headers.contains_key(header::CONTENT_RANGE)
+        || has_no_transform(headers)
+    {
+        return false;
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    if !content_type.is_some_and(|content_type| config.allows_content_type(content_type)) {
+        return false;
+    }
+    // Event streams are long-lived and consumed as they arrive, which compression gets in the
+    // way of (and proxies commonly mishandle), so they are never compressed.
+    if content_type.is_some_and(is_event_stream) {
+        return false;
+    }
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    !content_length.is_some_and(|length| length < config.min_size)
+}
+
+fn is_event_stream(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|essence| essence.trim().eq_ignore_ascii_case("text/event-stream"))
+}
+
+fn has_no_transform(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-transform"))
+}
+
+/// Picks the most preferred of `encodings` that the `Accept-Encoding` header value allows.
+fn negotiate(encodings: &[ContentEncoding], accept_encoding: &str) -> Option<ContentEncoding> {
+    let accepted = accept_encoding
+        .split(',')
+        .filter_map(|coding| {
+            let mut params = coding.split(';');
+            let name = params.next()?.trim().to_ascii_lowercase();
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((name, quality))
+        })
+        .collect::<Vec<_>>();
+    let quality = |name: &str| {
+        accepted
+            .iter()
+            .find(|(coding, _)| coding == name)
+            .or_else(|| accepted.iter().find(|(coding, _)| coding == "*"))
+            .map(|(_, quality)| *quality)
+    };
+    encodings
+        .iter()
+        .copied()
+        .filter(|encoding| quality(encoding.as_str()).is_some_and(|q| q > 0.0))
+        .max_by(|a, b| {
+            // Prefer the client's quality, then the configured order.
+            let (qa, qb) = (quality(a.as_str()), quality(b.as_str()));
+            qa.partial_cmp(&qb)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| {
+                    let position = |e| encodings.iter().position(|x| *x == e);
+                    position(*b).cmp(&position(*a))
+                })
+        })
+}
+
+/// Compresses the body as it streams.
+///
+/// The encoder is flushed after each frame of the original body, so that data the guest has
+/// written reaches the client without waiting for more to fill the encoder's buffer. Trailers of
+/// the original body are dropped.
+fn compress_body(body: Body, encoding: ContentEncoding) -> Body {
+    match encoding {
+        ContentEncoding::Gzip => encode_body(body, GzipEncoder::new(vec![]), GzipEncoder::get_mut),
+        ContentEncoding::Br => {
+            encode_body(body, BrotliEncoder::new(vec![]), BrotliEncoder::get_mut)
+        }
+        ContentEncoding::Zstd => encode_body(body, ZstdEncoder::new(vec![]), ZstdEncoder::get_mut),
+    }
+}
+
+/// Streams the body through `encoder`, which writes to the buffer returned by `output`.
+fn encode_body<E>(body: Body, encoder: E, output: fn(&mut E) -> &mut Vec<u8>) -> Body
+where
+    E: AsyncWrite + Send + Sync + Unpin + 'static,
+{
+    let compression_failed =
+        |err: std::io::Error| ErrorCode::InternalError(Some(format!("compression failed: {err}")));
+    let data = body.into_data_stream();
+    let stream = stream::try_unfold(Some((data, encoder)), move |state| async move {
+        let Some((mut data, mut encoder)) = state else {
+            return Ok(None);
+        };
+        match data.try_next().await? {
+            Some(chunk) => {
+                encoder
+                    .write_all(&chunk)
+                    .await
+                    .map_err(compression_failed)?;
+                encoder.flush().await.map_err(compression_failed)?;
+                let compressed = std::mem::take(output(&mut encoder));
+                Ok(Some((compressed, Some((data, encoder)))))
+            }
+            None => {
+                encoder.shutdown().await.map_err(compression_failed)?;
+                let compressed = std::mem::take(output(&mut encoder));
+                Ok(Some((compressed, None)))
+            }
+        }
+    })
+    .try_filter(|compressed| future::ready(!compressed.is_empty()))
+    .map_ok(|compressed| Frame::data(Bytes::from(compressed)));
+    BodyExt::boxed(StreamBody::new(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use spin_http::body;
+
+    use super::*;
+
+    fn response(content_type: &str, body: &'static [u8]) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(body::full(body.into()))
+            .unwrap()
+    }
+
+    #[test]
+    fn negotiates_preferred_encoding() {
+        use ContentEncoding::*;
+        let all = [Zstd, Br, Gzip];
+        assert_eq!(negotiate(&all, "gzip, br"), Some(Br));
+        assert_eq!(negotiate(&all, "gzip;q=1.0, br;q=0.5"), Some(Gzip));
+        assert_eq!(negotiate(&all, "*"), Some(Zstd));
+        assert_eq!(negotiate(&all, "*, zstd;q=0"), Some(Br));
+        assert_eq!(negotiate(&all, "identity"), None);
+        assert_eq!(negotiate(&[Gzip], "br"), None);
+    }
+
+    #[tokio::test]
+    async fn compresses_allowed_responses() {
+        let config = HttpCompressionConfig {
+            min_size: 0,
+            ..Default::default()
+        };
+        let accept = HeaderValue::from_static("gzip");
+        let text = b"hello hello hello hello hello";
+        let response = compress_response(
+            &config,
+            &Method::GET,
+            Some(&accept),
+            response("text/plain", text),
+        );
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+
+        let compressed = response.into_body().collect().await.unwrap().to_bytes();
+        let mut decoded = vec![];
+        let mut decoder = async_compression::tokio::bufread::GzipDecoder::new(&compressed[..]);
+        tokio::io::AsyncReadExt::read_to_end(&mut decoder, &mut decoded)
+            .await
+            .unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[tokio::test]
+    async fn flushes_each_frame() {
+        let config = HttpCompressionConfig {
+            min_size: 0,
+            ..Default::default()
+        };
+        let accept = HeaderValue::from_static("gzip");
+        // The guest has written one event, and is still working on the next.
+        let frames = futures::stream::iter([Ok(Frame::data(Bytes::from_static(b"data: one\n\n")))])
+            .chain(futures::stream::pending());
+        let response = Response::builder()
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(BodyExt::boxed(StreamBody::new(frames)))
+            .unwrap();
+        let response = compress_response(&config, &Method::GET, Some(&accept), response);
+
+        let frame = response.into_body().frame().await.unwrap().unwrap();
+        let mut decoder = async_compression::tokio::write::GzipDecoder::new(vec![]);
+        decoder.write_all(frame.data_ref().unwrap()).await.unwrap();
+        decoder.flush().await.unwrap();
+        assert_eq!(decoder.get_ref(), b"data: one\n\n");
+    }
+
+    #[test]
+    fn weakens_etag_of_compressed_responses() {
+        let config = HttpCompressionConfig::default();
+        let accept = HeaderValue::from_static("gzip");
+        let mut res = response("text/plain", &[b'a'; 2048]);
+        res.headers_mut()
+            .insert(header::ETAG, HeaderValue::from_static("\"abc\""));
+        let res = compress_response(&config, &Method::GET, Some(&accept), res);
+        assert_eq!(res.headers()[header::ETAG], "W/\"abc\"");
+    }
+
+    #[test]
+    fn skips_ineligible_responses() {
+        let config = HttpCompressionConfig::default();
+        let accept = HeaderValue::from_static("gzip");
+        let large = &[b'a'; 2048];
+
+        // Content type not allowed
+        let res = compress_response(
+            &config,
+            &Method::GET,
+            Some(&accept),
+            response("image/png", large),
+        );
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+
+        // Too small
+        let res = compress_response(
+            &config,
+            &Method::GET,
+            Some(&accept),
+            response("text/plain", b"small"),
+        );
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+
+        // Already encoded
+        let mut res = response("text/plain", large);
+        res.headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("br"));
+        let res = compress_response(&config, &Method::GET, Some(&accept), res);
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "br");
+        assert!(res.headers().get(header::VARY).is_none());
+
+        // Event stream
+        let res = compress_response(
+            &config,
+            &Method::GET,
+            Some(&accept),
+            response("text/event-stream; charset=utf-8", large),
+        );
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+}
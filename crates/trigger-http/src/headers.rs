@@ -98,13 +98,15 @@ pub fn prepare_request_headers(
         res.push((name, value));
     }
 
-    let default_host = http::HeaderValue::from_str("localhost")?;
-    let host = std::str::from_utf8(
-        req.headers()
-            .get("host")
-            .unwrap_or(&default_host)
-            .as_bytes(),
-    )?;
+    let host = match req.headers().get("host") {
+        Some(host) => std::str::from_utf8(host.as_bytes())?,
+        // HTTP/2 requests carry the authority in the URI rather than in a `Host` header
+        None => req
+            .uri()
+            .authority()
+            .map(|authority| authority.as_str())
+            .unwrap_or("localhost"),
+    };
 
     // Set the environment information (path info, base path, etc) as headers.
     // In the future, we might want to have this information in a context
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use http::Response;
 use tracing::Level;
@@ -23,6 +25,7 @@ macro_rules! http_span {
             "http.response.status_code" = ::tracing::field::Empty,
             "http.route" = ::tracing::field::Empty,
             "otel.name" = ::tracing::field::Empty,
+            "spin.request.timeout" = ::tracing::field::Empty,
         )
     };
 }
@@ -72,6 +75,21 @@ pub(crate) fn instrument_error(err: &anyhow::Error) {
     span.record("error.type", format!("{:?}", err));
 }
 
+/// Records the request timeout on the current span.
+pub(crate) fn record_request_timeout(timeout: Duration) {
+    let span = tracing::Span::current();
+    span.record(
+        "spin.request.timeout",
+        tracing::field::display(humantime::format_duration(timeout)),
+    );
+}
+
+/// Marks the current span as having timed out.
+pub(crate) fn instrument_timeout() {
+    let span = tracing::Span::current();
+    span.record("error.type", "timeout");
+}
+
 /// MatchedRoute is used as a response extension to track the route that was matched for OTel
 /// tracing purposes.
 #[derive(Clone)]
@@ -1,7 +1,9 @@
 //! Implementation for the Spin HTTP engine.
 
+mod compression;
 mod headers;
 mod instrument;
+mod metrics;
 mod outbound_http;
 mod server;
 mod spin;
@@ -14,6 +16,7 @@ use std::{
     net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::{bail, Context};
@@ -21,9 +24,10 @@ use clap::Args;
 use serde::Deserialize;
 use spin_app::App;
 use spin_factors::RuntimeFactors;
-use spin_trigger::Trigger;
+use spin_trigger::{ShutdownSignal, Trigger};
 use wasmtime_wasi_http::bindings::http::types::ErrorCode;
 
+pub use metrics::MetricsEndpoint;
 pub use server::HttpServer;
 
 pub use tls::TlsConfig;
@@ -50,9 +54,42 @@ pub struct CliArgs {
     /// The path to the certificate key to use for https, if this is not set, normal http will be used. The key should be in PKCS#8 format
     #[clap(long, env = "SPIN_TLS_KEY", requires = "tls-cert")]
     pub tls_key: Option<PathBuf>,
+
+    /// Enable HTTP/2 support. With TLS, HTTP/2 is negotiated via ALPN; without TLS, clients
+    /// may use HTTP/2 with prior knowledge (h2c). HTTP/1.1 remains available in both cases.
+    #[clap(
+        long = "enable-http2",
+        env = "SPIN_HTTP_ENABLE_HTTP2",
+        takes_value = false
+    )]
+    pub enable_http2: bool,
+
+    /// Default maximum time a component may take to handle a request, such as "30s".
+    /// Components can override this with the `timeout` option of their HTTP trigger.
+    /// Requests which exceed their timeout receive a 504 Gateway Timeout response.
+    #[clap(long = "request-timeout", env = "SPIN_HTTP_REQUEST_TIMEOUT", value_parser = humantime::parse_duration)]
+    pub request_timeout: Option<Duration>,
+
+    /// Serve Prometheus metrics about the requests handled by each component at
+    /// `/.well-known/spin/metrics`.
+    #[clap(long = "metrics", env = "SPIN_HTTP_METRICS", takes_value = false)]
+    pub metrics: bool,
+
+    /// Serve Prometheus metrics at `/metrics` on this address, rather than on the
+    /// app's listen address.
+    #[clap(long = "metrics-listen", env = "SPIN_HTTP_METRICS_LISTEN_ADDR", value_parser = parse_listen_addr)]
+    pub metrics_listen: Option<SocketAddr>,
 }
 
 impl CliArgs {
+    fn metrics_endpoint(&self) -> MetricsEndpoint {
+        match (self.metrics_listen, self.metrics) {
+            (Some(addr), _) => MetricsEndpoint::Listen(addr),
+            (None, true) => MetricsEndpoint::WellKnown,
+            (None, false) => MetricsEndpoint::Disabled,
+        }
+    }
+
     fn into_tls_config(self) -> Option<TlsConfig> {
         match (self.tls_cert, self.tls_key) {
             (Some(cert_path), Some(key_path)) => Some(TlsConfig {
@@ -73,6 +110,12 @@ pub struct HttpTrigger {
     /// If the port is set to 0, the actual address will be determined by the OS.
     listen_addr: SocketAddr,
     tls_config: Option<TlsConfig>,
+    /// Whether HTTP/2 connections should be accepted.
+    enable_http2: bool,
+    /// The default request timeout for components which do not set their own.
+    request_timeout: Option<Duration>,
+    /// Where Prometheus metrics are served, if anywhere.
+    metrics: MetricsEndpoint,
 }
 
 impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
@@ -82,13 +125,35 @@ impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
     type InstanceState = ();
 
     fn new(cli_args: Self::CliArgs, app: &spin_app::App) -> anyhow::Result<Self> {
-        Self::new(app, cli_args.address, cli_args.into_tls_config())
+        let enable_http2 = cli_args.enable_http2;
+        let request_timeout = cli_args.request_timeout;
+        let metrics = cli_args.metrics_endpoint();
+        Self::new(
+            app,
+            cli_args.address,
+            cli_args.into_tls_config(),
+            enable_http2,
+            request_timeout,
+            metrics,
+        )
     }
 
     async fn run(self, trigger_app: TriggerApp<F>) -> anyhow::Result<()> {
         let server = self.into_server(trigger_app)?;
 
-        server.serve().await?;
+        server.serve(ShutdownSignal::new()).await?;
+
+        Ok(())
+    }
+
+    async fn run_with_shutdown(
+        self,
+        trigger_app: TriggerApp<F>,
+        shutdown: ShutdownSignal,
+    ) -> anyhow::Result<()> {
+        let server = self.into_server(trigger_app)?;
+
+        server.serve(shutdown).await?;
 
         Ok(())
     }
@@ -104,12 +169,18 @@ impl HttpTrigger {
         app: &spin_app::App,
         listen_addr: SocketAddr,
         tls_config: Option<TlsConfig>,
+        enable_http2: bool,
+        request_timeout: Option<Duration>,
+        metrics: MetricsEndpoint,
     ) -> anyhow::Result<Self> {
         Self::validate_app(app)?;
 
         Ok(Self {
             listen_addr,
             tls_config,
+            enable_http2,
+            request_timeout,
+            metrics,
         })
     }
 
@@ -121,8 +192,18 @@ impl HttpTrigger {
         let Self {
             listen_addr,
             tls_config,
+            enable_http2,
+            request_timeout,
+            metrics,
         } = self;
-        let server = Arc::new(HttpServer::new(listen_addr, tls_config, trigger_app)?);
+        let server = Arc::new(HttpServer::new(
+            listen_addr,
+            tls_config,
+            enable_http2,
+            request_timeout,
+            metrics,
+            trigger_app,
+        )?);
         Ok(server)
     }
 
@@ -0,0 +1,302 @@
+//! Request metrics in the Prometheus text exposition format.
+//!
+//! Metrics are only recorded by servers with an endpoint serving them, so that apps which
+//! don't expose them pay nothing for them.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Where the HTTP trigger exposes its Prometheus metrics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetricsEndpoint {
+    /// Metrics are not exposed.
+    #[default]
+    Disabled,
+    /// Metrics are served at `/.well-known/spin/metrics` on the app's listen address.
+    WellKnown,
+    /// Metrics are served at `/metrics` on a separate listen address.
+    Listen(SocketAddr),
+}
+
+/// The content type of the Prometheus text exposition format.
+pub(crate) const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Histogram bucket upper bounds, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Awaits the instantiation of a component, recording how long it took if metrics are
+/// being recorded.
+pub(crate) async fn time_instantiation<T>(
+    metrics: Option<&HttpMetrics>,
+    component_id: &str,
+    instantiate: impl Future<Output = T>,
+) -> T {
+    let start = Instant::now();
+    let instance = instantiate.await;
+    if let Some(metrics) = metrics {
+        metrics.record_instantiation(component_id, start.elapsed());
+    }
+    instance
+}
+
+/// The kind of a failed request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ErrorKind {
+    /// The component failed, or the request could not be dispatched to it.
+    Error,
+    /// The component didn't respond before its request timeout.
+    Timeout,
+}
+
+impl ErrorKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Timeout => "timeout",
+        }
+    }
+}
+
+/// Metrics about the requests handled by each component.
+#[derive(Default)]
+pub(crate) struct HttpMetrics {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// (Component ID, route, response status) -> count
+    requests: BTreeMap<(String, String, u16), u64>,
+    /// (Component ID, kind) -> count
+    errors: BTreeMap<(String, ErrorKind), u64>,
+    /// (Component ID, route) -> time to respond
+    request_durations: BTreeMap<(String, String), Histogram>,
+    /// Component ID -> time to instantiate
+    instantiation_durations: BTreeMap<String, Histogram>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// The number of observations in each bucket (not cumulative).
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(bucket) = BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+impl HttpMetrics {
+    /// Records a request handled by a component through the given route, with the
+    /// status of its response and the time taken to produce the response head.
+    ///
+    /// The route is the pattern from the manifest rather than the request path, so that
+    /// the number of series stays bounded.
+    pub fn record_request(&self, component_id: &str, route: &str, status: u16, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner
+            .requests
+            .entry((component_id.to_owned(), route.to_owned(), status))
+            .or_default() += 1;
+        inner
+            .request_durations
+            .entry((component_id.to_owned(), route.to_owned()))
+            .or_default()
+            .observe(duration);
+    }
+
+    /// Records a request which a component failed to handle.
+    pub fn record_error(&self, component_id: &str, kind: ErrorKind) {
+        *self
+            .inner
+            .lock()
+            .unwrap()
+            .errors
+            .entry((component_id.to_owned(), kind))
+            .or_default() += 1;
+    }
+
+    /// Records the time taken to instantiate a component.
+    pub fn record_instantiation(&self, component_id: &str, duration: Duration) {
+        self.inner
+            .lock()
+            .unwrap()
+            .instantiation_durations
+            .entry(component_id.to_owned())
+            .or_default()
+            .observe(duration);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        write_header(
+            &mut out,
+            "spin_http_requests_total",
+            "counter",
+            "Requests handled by each component, by route and response status.",
+        );
+        for ((component, route, status), count) in &inner.requests {
+            let _ = writeln!(
+                out,
+                "spin_http_requests_total{{component=\"{}\",route=\"{}\",status=\"{status}\"}} {count}",
+                escape(component),
+                escape(route)
+            );
+        }
+
+        write_header(
+            &mut out,
+            "spin_http_request_errors_total",
+            "counter",
+            "Requests which each component failed to handle, by kind of failure.",
+        );
+        for ((component, kind), count) in &inner.errors {
+            let _ = writeln!(
+                out,
+                "spin_http_request_errors_total{{component=\"{}\",kind=\"{}\"}} {count}",
+                escape(component),
+                kind.as_str()
+            );
+        }
+
+        write_histograms(
+            &mut out,
+            "spin_http_request_duration_seconds",
+            "Time taken by each component to respond to a request, by route.",
+            inner
+                .request_durations
+                .iter()
+                .map(|((component, route), histogram)| {
+                    (
+                        format!(
+                            "component=\"{}\",route=\"{}\"",
+                            escape(component),
+                            escape(route)
+                        ),
+                        histogram,
+                    )
+                }),
+        );
+        write_histograms(
+            &mut out,
+            "spin_http_instantiation_duration_seconds",
+            "Time taken to instantiate each component for a request.",
+            inner
+                .instantiation_durations
+                .iter()
+                .map(|(component, histogram)| {
+                    (format!("component=\"{}\"", escape(component)), histogram)
+                }),
+        );
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Writes histograms, each given with its rendered labels.
+fn write_histograms<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    histograms: impl IntoIterator<Item = (String, &'a Histogram)>,
+) {
+    write_header(out, name, "histogram", help);
+    for (labels, histogram) in histograms {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels},le=\"+Inf\"}} {}",
+            histogram.count
+        );
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", histogram.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", histogram.count);
+    }
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_text() {
+        let metrics = HttpMetrics::default();
+        metrics.record_request("hello", "/hello/...", 200, Duration::from_millis(20));
+        metrics.record_request("hello", "/hello/...", 200, Duration::from_millis(200));
+        metrics.record_request("hello", "/hello/...", 504, Duration::from_secs(30));
+        metrics.record_request("hello", "/hi", 200, Duration::from_millis(1));
+        metrics.record_error("hello", ErrorKind::Timeout);
+        metrics.record_instantiation("hello", Duration::from_millis(1));
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE spin_http_requests_total counter\n"));
+        assert!(text.contains(
+            "spin_http_requests_total{component=\"hello\",route=\"/hello/...\",status=\"200\"} 2\n"
+        ));
+        assert!(text.contains(
+            "spin_http_requests_total{component=\"hello\",route=\"/hello/...\",status=\"504\"} 1\n"
+        ));
+        assert!(text.contains(
+            "spin_http_requests_total{component=\"hello\",route=\"/hi\",status=\"200\"} 1\n"
+        ));
+        assert!(text
+            .contains("spin_http_request_errors_total{component=\"hello\",kind=\"timeout\"} 1\n"));
+        assert!(text.contains(
+            "spin_http_request_duration_seconds_bucket{component=\"hello\",route=\"/hello/...\",le=\"0.025\"} 1\n"
+        ));
+        assert!(text.contains(
+            "spin_http_request_duration_seconds_bucket{component=\"hello\",route=\"/hello/...\",le=\"0.25\"} 2\n"
+        ));
+        assert!(text.contains(
+            "spin_http_request_duration_seconds_bucket{component=\"hello\",route=\"/hello/...\",le=\"+Inf\"} 3\n"
+        ));
+        assert!(text.contains(
+            "spin_http_request_duration_seconds_count{component=\"hello\",route=\"/hello/...\"} 3\n"
+        ));
+        assert!(text.contains(
+            "spin_http_request_duration_seconds_count{component=\"hello\",route=\"/hi\"} 1\n"
+        ));
+        assert!(text.contains(
+            "spin_http_instantiation_duration_seconds_bucket{component=\"hello\",le=\"0.005\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape("a\"b\\c\nd"), r#"a\"b\\c\nd"#);
+    }
+}
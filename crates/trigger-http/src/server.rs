@@ -1,4 +1,12 @@
-use std::{collections::HashMap, future::Future, io::IsTerminal, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::IsTerminal,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{bail, Context};
 use http::{
@@ -11,28 +19,38 @@ use hyper::{
     server::conn::http1,
     service::service_fn,
 };
-use hyper_util::rt::TokioIo;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
 use spin_app::{APP_DESCRIPTION_KEY, APP_NAME_KEY};
+use spin_core::Trap;
 use spin_factor_outbound_http::{OutboundHttpFactor, SelfRequestOrigin};
 use spin_factors::RuntimeFactors;
 use spin_http::{
     app_info::AppInfo,
     body,
     config::{HttpExecutorType, HttpTriggerConfig},
-    routes::{RouteMatch, Router},
+    routes::{RouteConditions, RouteMatch, Router, RoutingError},
     trigger::HandlerType,
 };
+use spin_trigger::ShutdownSignal;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpListener,
-    task,
 };
+use tokio_util::task::TaskTracker;
 use tracing::Instrument;
 use wasmtime_wasi_http::body::HyperOutgoingBody;
 
 use crate::{
+    compression::compress_response,
     headers::strip_forbidden_headers,
-    instrument::{finalize_http_span, http_span, instrument_error, MatchedRoute},
+    instrument::{
+        finalize_http_span, http_span, instrument_error, instrument_timeout,
+        record_request_timeout, MatchedRoute,
+    },
+    metrics::{ErrorKind, HttpMetrics, MetricsEndpoint},
     outbound_http::OutboundHttpInterceptor,
     spin::SpinHttpExecutor,
     wagi::WagiHttpExecutor,
@@ -46,6 +64,8 @@ pub struct HttpServer<F: RuntimeFactors> {
     listen_addr: SocketAddr,
     /// The TLS configuration for the server.
     tls_config: Option<TlsConfig>,
+    /// Whether to accept HTTP/2 connections in addition to HTTP/1.1.
+    enable_http2: bool,
     /// Request router.
     router: Router,
     /// The app being triggered.
@@ -54,6 +74,12 @@ pub struct HttpServer<F: RuntimeFactors> {
     component_trigger_configs: HashMap<String, HttpTriggerConfig>,
     // Component ID -> handler type
     component_handler_types: HashMap<String, HandlerType>,
+    // Component ID -> request timeout
+    component_timeouts: HashMap<String, Duration>,
+    /// Where Prometheus metrics are served, if anywhere.
+    metrics_endpoint: MetricsEndpoint,
+    /// The recorded metrics, if they are served.
+    metrics: Option<Arc<HttpMetrics>>,
 }
 
 impl<F: RuntimeFactors> HttpServer<F> {
@@ -61,6 +87,9 @@ impl<F: RuntimeFactors> HttpServer<F> {
     pub fn new(
         listen_addr: SocketAddr,
         tls_config: Option<TlsConfig>,
+        enable_http2: bool,
+        request_timeout: Option<Duration>,
+        metrics_endpoint: MetricsEndpoint,
         trigger_app: TriggerApp<F>,
     ) -> anyhow::Result<Self> {
         // This needs to be a vec before building the router to handle duplicate routes
@@ -75,8 +104,15 @@ impl<F: RuntimeFactors> HttpServer<F> {
         // Build router
         let component_routes = component_trigger_configs
             .iter()
-            .map(|(component_id, config)| (component_id.as_str(), &config.route));
-        let (router, duplicate_routes) = Router::build("/", component_routes)?;
+            .map(|(component_id, config)| {
+                let conditions = RouteConditions::parse(&config.methods, &config.hosts)
+                    .with_context(|| {
+                        format!("Invalid route conditions for component '{component_id}'")
+                    })?;
+                Ok((component_id.as_str(), &config.route, conditions))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (router, duplicate_routes) = Router::build_with_conditions("/", component_routes)?;
         if !duplicate_routes.is_empty() {
             tracing::error!(
                 "The following component routes are duplicates and will never be used:"
@@ -117,38 +153,105 @@ impl<F: RuntimeFactors> HttpServer<F> {
                 Ok((component_id.clone(), handler_type))
             })
             .collect::<anyhow::Result<_>>()?;
+
+        let component_timeouts = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, trigger_config)| {
+                let timeout = match &trigger_config.timeout {
+                    Some(timeout) => match humantime::parse_duration(timeout) {
+                        Ok(timeout) => timeout,
+                        Err(err) => {
+                            return Some(Err(anyhow::anyhow!(
+                                "Invalid timeout {timeout:?} for component '{component_id}': {err}"
+                            )))
+                        }
+                    },
+                    None => request_timeout?,
+                };
+                Some(Ok((component_id.clone(), timeout)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let metrics = (metrics_endpoint != MetricsEndpoint::Disabled).then(Default::default);
+
         Ok(Self {
             listen_addr,
             tls_config,
+            enable_http2,
             router,
             trigger_app,
             component_trigger_configs,
             component_handler_types,
+            component_timeouts,
+            metrics_endpoint,
+            metrics,
         })
     }
 
-    /// Serve incoming requests over the provided [`TcpListener`].
-    pub async fn serve(self: Arc<Self>) -> anyhow::Result<()> {
+    /// Serve incoming requests on the listen address until `shutdown` is requested.
+    ///
+    /// Once shutdown is requested, no new connections are accepted, and this returns
+    /// when all in-flight requests have completed.
+    pub async fn serve(self: Arc<Self>, shutdown: ShutdownSignal) -> anyhow::Result<()> {
         let listener = TcpListener::bind(self.listen_addr).await.with_context(|| {
             format!(
                 "Unable to listen on {listen_addr}",
                 listen_addr = self.listen_addr
             )
         })?;
-        if let Some(tls_config) = self.tls_config.clone() {
-            self.serve_https(listener, tls_config).await?;
+        let connections = TaskTracker::new();
+        if let (MetricsEndpoint::Listen(addr), Some(metrics)) =
+            (self.metrics_endpoint, &self.metrics)
+        {
+            let metrics_listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Unable to listen for metrics on {addr}"))?;
+            // Metrics are served alongside the app until shutdown, and a failure to serve
+            // them doesn't stop the app from being served.
+            connections.spawn(serve_metrics(
+                metrics_listener,
+                metrics.clone(),
+                connections.clone(),
+                shutdown.clone(),
+            ));
+        }
+        let res = if let Some(tls_config) = self.tls_config.clone() {
+            self.serve_https(listener, tls_config, &connections, &shutdown)
+                .await
         } else {
-            self.serve_http(listener).await?;
+            self.serve_http(listener, &connections, &shutdown).await
+        };
+
+        // Drain any connections still in flight
+        connections.close();
+        if !connections.is_empty() {
+            tracing::info!(
+                "Waiting for {} open connection(s) to finish",
+                connections.len()
+            );
         }
-        Ok(())
+        connections.wait().await;
+        res
     }
 
-    async fn serve_http(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
+    async fn serve_http(
+        self: Arc<Self>,
+        listener: TcpListener,
+        connections: &TaskTracker,
+        shutdown: &ShutdownSignal,
+    ) -> anyhow::Result<()> {
         self.print_startup_msgs("http", &listener)?;
         loop {
-            let (stream, client_addr) = listener.accept().await?;
-            self.clone()
-                .serve_connection(stream, Scheme::HTTP, client_addr);
+            let (stream, client_addr) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = shutdown.requested() => return Ok(()),
+            };
+            connections.spawn(self.clone().serve_connection(
+                stream,
+                Scheme::HTTP,
+                client_addr,
+                shutdown.clone(),
+            ));
         }
     }
 
@@ -156,17 +259,29 @@ impl<F: RuntimeFactors> HttpServer<F> {
         self: Arc<Self>,
         listener: TcpListener,
         tls_config: TlsConfig,
+        connections: &TaskTracker,
+        shutdown: &ShutdownSignal,
     ) -> anyhow::Result<()> {
         self.print_startup_msgs("https", &listener)?;
-        let acceptor = tls_config.server_config()?;
+        let acceptor = tls_config.server_config(self.enable_http2)?;
         loop {
-            let (stream, client_addr) = listener.accept().await?;
-            match acceptor.accept(stream).await {
-                Ok(stream) => self
-                    .clone()
-                    .serve_connection(stream, Scheme::HTTPS, client_addr),
-                Err(err) => tracing::error!(?err, "Failed to start TLS session"),
-            }
+            let (stream, client_addr) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = shutdown.requested() => return Ok(()),
+            };
+            let acceptor = acceptor.clone();
+            let server = self.clone();
+            let shutdown = shutdown.clone();
+            connections.spawn(async move {
+                match acceptor.accept(stream).await {
+                    Ok(stream) => {
+                        server
+                            .serve_connection(stream, Scheme::HTTPS, client_addr, shutdown)
+                            .await
+                    }
+                    Err(err) => tracing::error!(?err, "Failed to start TLS session"),
+                }
+            });
         }
     }
 
@@ -196,21 +311,66 @@ impl<F: RuntimeFactors> HttpServer<F> {
                     path,
                 )),
                 "info" => self.app_info(path),
+                "metrics" => match (&self.metrics, self.metrics_endpoint) {
+                    (Some(metrics), MetricsEndpoint::WellKnown) => metrics_response(metrics, path),
+                    _ => Self::not_found(NotFoundRouteKind::WellKnown),
+                },
                 _ => Self::not_found(NotFoundRouteKind::WellKnown),
             };
         }
 
-        match self.router.route(&path) {
+        let host = req
+            .headers()
+            .get(http::header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| req.uri().authority().map(|authority| authority.as_str()));
+        match self.router.route_request(req.method(), host, &path) {
             Ok(route_match) => {
                 self.handle_trigger_route(req, route_match, server_scheme, client_addr)
                     .await
             }
-            Err(_) => Self::not_found(NotFoundRouteKind::Normal(path.to_string())),
+            Err(RoutingError::NotFound) => {
+                Self::not_found(NotFoundRouteKind::Normal(path.to_string()))
+            }
+            Err(RoutingError::MethodNotAllowed { allowed }) => Self::method_not_allowed(&allowed),
         }
     }
 
     /// Handles a successful route match.
     pub async fn handle_trigger_route(
+        self: &Arc<Self>,
+        req: Request<Body>,
+        route_match: RouteMatch,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+    ) -> anyhow::Result<Response<Body>> {
+        let Some(metrics) = &self.metrics else {
+            return self
+                .execute_trigger_route(req, route_match, server_scheme, client_addr)
+                .await;
+        };
+        let component_id = route_match.component_id().to_owned();
+        let route = route_match.based_route().to_owned();
+        let start = Instant::now();
+        let res = self
+            .execute_trigger_route(req, route_match, server_scheme, client_addr)
+            .await;
+        match &res {
+            Ok(res) => metrics.record_request(
+                &component_id,
+                &route,
+                res.status().as_u16(),
+                start.elapsed(),
+            ),
+            Err(_) => {
+                metrics.record_request(&component_id, &route, 500, start.elapsed());
+                metrics.record_error(&component_id, ErrorKind::Error);
+            }
+        }
+        res
+    }
+
+    async fn execute_trigger_route(
         self: &Arc<Self>,
         mut req: Request<Body>,
         route_match: RouteMatch,
@@ -235,6 +395,16 @@ impl<F: RuntimeFactors> HttpServer<F> {
 
         let mut instance_builder = self.trigger_app.prepare(component_id)?;
 
+        // If the request has a timeout, the guest is interrupted via its epoch
+        // deadline should it still be running when the timeout expires.
+        let deadline = self.component_timeouts.get(component_id).map(|timeout| {
+            record_request_timeout(*timeout);
+            Instant::now() + *timeout
+        });
+        if let Some(deadline) = deadline {
+            instance_builder.store_builder().deadline(deadline);
+        }
+
         // Set up outbound HTTP request origin and service chaining
         // The outbound HTTP factor is required since both inbound and outbound wasi HTTP
         // implementations assume they use the same underlying wasmtime resource storage.
@@ -256,46 +426,109 @@ impl<F: RuntimeFactors> HttpServer<F> {
             .as_ref()
             .unwrap_or(&HttpExecutorType::Http);
 
-        let res = match executor {
-            HttpExecutorType::Http => match handler_type {
-                HandlerType::Spin => {
-                    SpinHttpExecutor
-                        .execute(instance_builder, &route_match, req, client_addr)
+        // The request is consumed by the executor, so keep what compression negotiates on.
+        let method = req.method().clone();
+        let accept_encoding = req.headers().get(http::header::ACCEPT_ENCODING).cloned();
+
+        let execute = async {
+            match executor {
+                HttpExecutorType::Http => match handler_type {
+                    HandlerType::Spin => {
+                        SpinHttpExecutor
+                            .execute(
+                                instance_builder,
+                                &route_match,
+                                req,
+                                client_addr,
+                                self.metrics.as_deref(),
+                            )
+                            .await
+                    }
+                    HandlerType::Wasi0_2
+                    | HandlerType::Wasi2023_11_10
+                    | HandlerType::Wasi2023_10_18 => {
+                        WasiHttpExecutor {
+                            handler_type: *handler_type,
+                        }
+                        .execute(
+                            instance_builder,
+                            &route_match,
+                            req,
+                            client_addr,
+                            self.metrics.as_deref(),
+                        )
                         .await
-                }
-                HandlerType::Wasi0_2
-                | HandlerType::Wasi2023_11_10
-                | HandlerType::Wasi2023_10_18 => {
-                    WasiHttpExecutor {
-                        handler_type: *handler_type,
                     }
-                    .execute(instance_builder, &route_match, req, client_addr)
-                    .await
+                    HandlerType::Wagi => unreachable!(),
+                },
+                HttpExecutorType::Wagi(wagi_config) => {
+                    let executor = WagiHttpExecutor {
+                        wagi_config: wagi_config.clone(),
+                    };
+                    executor
+                        .execute(
+                            instance_builder,
+                            &route_match,
+                            req,
+                            client_addr,
+                            self.metrics.as_deref(),
+                        )
+                        .await
                 }
-                HandlerType::Wagi => unreachable!(),
-            },
-            HttpExecutorType::Wagi(wagi_config) => {
-                let executor = WagiHttpExecutor {
-                    wagi_config: wagi_config.clone(),
-                };
-                executor
-                    .execute(instance_builder, &route_match, req, client_addr)
-                    .await
             }
         };
+
+        // Guests blocked on host calls aren't interrupted by the epoch deadline, so the
+        // timeout is also enforced on the execution as a whole.
+        let res = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), execute).await {
+                Ok(res) => res,
+                Err(_elapsed) => {
+                    tracing::error!("Component '{component_id}' timed out handling request");
+                    instrument_timeout();
+                    self.record_error(component_id, ErrorKind::Timeout);
+                    return Self::gateway_timeout(route_match.raw_route());
+                }
+            },
+            None => execute.await,
+        };
         match res {
-            Ok(res) => Ok(MatchedRoute::with_response_extension(
-                res,
-                route_match.raw_route(),
-            )),
+            Ok(res) => {
+                let res = match &trigger_config.compression {
+                    Some(compression) => {
+                        compress_response(compression, &method, accept_encoding.as_ref(), res)
+                    }
+                    None => res,
+                };
+                Ok(MatchedRoute::with_response_extension(
+                    res,
+                    route_match.raw_route(),
+                ))
+            }
+            Err(err) if matches!(err.downcast_ref::<Trap>(), Some(Trap::Interrupt)) => {
+                tracing::error!(
+                    "Component '{component_id}' was interrupted at its request deadline"
+                );
+                instrument_timeout();
+                self.record_error(component_id, ErrorKind::Timeout);
+                Self::gateway_timeout(route_match.raw_route())
+            }
             Err(err) => {
                 tracing::error!("Error processing request: {err:?}");
                 instrument_error(&err);
+                self.record_error(component_id, ErrorKind::Error);
                 Self::internal_error(None, route_match.raw_route())
             }
         }
     }
 
+    /// Records a request which a component failed to handle, if metrics are recorded.
+    fn record_error(&self, component_id: &str, kind: ErrorKind) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_error(component_id, kind);
+        }
+    }
+
     /// Returns spin status information.
     fn app_info(&self, route: String) -> anyhow::Result<Response<Body>> {
         let info = AppInfo::new(self.trigger_app.app());
@@ -326,6 +559,16 @@ impl<F: RuntimeFactors> HttpServer<F> {
         ))
     }
 
+    /// Creates an HTTP 504 response.
+    fn gateway_timeout(route: impl Into<String>) -> anyhow::Result<Response<Body>> {
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder()
+                .status(StatusCode::GATEWAY_TIMEOUT)
+                .body(body::empty())?,
+            route,
+        ))
+    }
+
     /// Creates an HTTP 404 response.
     fn not_found(kind: NotFoundRouteKind) -> anyhow::Result<Response<Body>> {
         use std::sync::atomic::{AtomicBool, Ordering};
@@ -342,30 +585,49 @@ impl<F: RuntimeFactors> HttpServer<F> {
             .body(body::empty())?)
     }
 
-    fn serve_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+    /// Creates an HTTP 405 response.
+    fn method_not_allowed(allowed: &[http::Method]) -> anyhow::Result<Response<Body>> {
+        let allow = allowed
+            .iter()
+            .map(http::Method::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(http::header::ALLOW, allow)
+            .body(body::empty())?)
+    }
+
+    async fn serve_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
         self: Arc<Self>,
         stream: S,
         server_scheme: Scheme,
         client_addr: SocketAddr,
+        shutdown: ShutdownSignal,
     ) {
-        task::spawn(async move {
-            if let Err(err) = http1::Builder::new()
+        let enable_http2 = self.enable_http2;
+        let service = service_fn(move |request| {
+            self.clone()
+                .instrumented_service_fn(server_scheme.clone(), client_addr, request)
+        });
+        let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = if enable_http2 {
+            // The auto builder detects the protocol from the connection preface, which
+            // covers both ALPN-negotiated h2 over TLS and prior-knowledge h2c.
+            let mut builder = auto::Builder::new(TokioExecutor::new());
+            builder.http1().keep_alive(true);
+            let conn = builder.serve_connection(TokioIo::new(stream), service);
+            serve_until_shutdown(conn, &shutdown, |conn| conn.graceful_shutdown()).await
+        } else {
+            let conn = http1::Builder::new()
                 .keep_alive(true)
-                .serve_connection(
-                    TokioIo::new(stream),
-                    service_fn(move |request| {
-                        self.clone().instrumented_service_fn(
-                            server_scheme.clone(),
-                            client_addr,
-                            request,
-                        )
-                    }),
-                )
+                .serve_connection(TokioIo::new(stream), service);
+            serve_until_shutdown(conn, &shutdown, |conn| conn.graceful_shutdown())
                 .await
-            {
-                tracing::warn!("Error serving HTTP connection: {err:?}");
-            }
-        });
+                .map_err(Into::into)
+        };
+        if let Err(err) = result {
+            tracing::warn!("Error serving HTTP connection: {err:?}");
+        }
     }
 
     async fn instrumented_service_fn(
@@ -412,6 +674,85 @@ impl<F: RuntimeFactors> HttpServer<F> {
     }
 }
 
+/// Serves Prometheus metrics at `/metrics` on the given listener until shutdown is requested.
+///
+/// Errors are logged rather than returned, so that they don't affect serving the app.
+async fn serve_metrics(
+    listener: TcpListener,
+    metrics: Arc<HttpMetrics>,
+    connections: TaskTracker,
+    shutdown: ShutdownSignal,
+) {
+    if let Ok(local_addr) = listener.local_addr() {
+        terminal::step!("Serving metrics", "http://{local_addr:?}/metrics");
+        tracing::info!("Serving metrics on http://{local_addr:?}/metrics");
+    }
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::error!("Error accepting metrics connection: {err:?}");
+                    // Errors such as running out of file descriptors persist for a while.
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = shutdown.requested() => return,
+        };
+        let metrics = metrics.clone();
+        let shutdown = shutdown.clone();
+        connections.spawn(async move {
+            let service = service_fn(|request: Request<Incoming>| {
+                let metrics = metrics.clone();
+                async move {
+                    match request.uri().path() {
+                        "/metrics" => metrics_response(&metrics, "/metrics".into()),
+                        _ => Ok(Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(body::empty())?),
+                    }
+                }
+            });
+            let conn = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            if let Err(err) =
+                serve_until_shutdown(conn, &shutdown, |conn| conn.graceful_shutdown()).await
+            {
+                tracing::warn!("Error serving metrics connection: {err:?}");
+            }
+        });
+    }
+}
+
+/// Returns the recorded metrics in the Prometheus text format.
+fn metrics_response(metrics: &HttpMetrics, route: String) -> anyhow::Result<Response<Body>> {
+    Ok(MatchedRoute::with_response_extension(
+        Response::builder()
+            .header("content-type", crate::metrics::CONTENT_TYPE)
+            .body(body::full(metrics.render().into()))?,
+        route,
+    ))
+}
+
+/// Drives a connection to completion, shutting it down gracefully once shutdown is
+/// requested so that in-flight requests can finish.
+async fn serve_until_shutdown<C, E>(
+    conn: C,
+    shutdown: &ShutdownSignal,
+    graceful_shutdown: impl FnOnce(Pin<&mut C>),
+) -> Result<(), E>
+where
+    C: Future<Output = Result<(), E>>,
+{
+    tokio::pin!(conn);
+    tokio::select! {
+        res = conn.as_mut() => return res,
+        _ = shutdown.requested() => {}
+    }
+    graceful_shutdown(conn.as_mut());
+    conn.await
+}
+
 /// The incoming request's scheme and authority
 ///
 /// The incoming request's URI is relative to the server, so we need to set the scheme and authority.
@@ -456,11 +797,14 @@ fn set_req_uri(req: &mut Request<Body>, scheme: Scheme) -> anyhow::Result<()> {
 
 /// An HTTP executor.
 pub(crate) trait HttpExecutor: Clone + Send + Sync + 'static {
+    /// Handles a request with a new instance of the matched component, recording the time
+    /// taken to instantiate it in `metrics`, if given.
     fn execute<F: RuntimeFactors>(
         &self,
         instance_builder: TriggerInstanceBuilder<F>,
         route_match: &RouteMatch,
         req: Request<Body>,
         client_addr: SocketAddr,
+        metrics: Option<&HttpMetrics>,
     ) -> impl Future<Output = anyhow::Result<Response<Body>>>;
 }
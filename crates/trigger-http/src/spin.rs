@@ -11,6 +11,7 @@ use tracing::{instrument, Level};
 
 use crate::{
     headers::{append_headers, prepare_request_headers},
+    metrics::{self, HttpMetrics},
     server::HttpExecutor,
     Body, TriggerInstanceBuilder,
 };
@@ -27,12 +28,15 @@ impl HttpExecutor for SpinHttpExecutor {
         route_match: &RouteMatch,
         req: Request<Body>,
         client_addr: SocketAddr,
+        metrics: Option<&HttpMetrics>,
     ) -> Result<Response<Body>> {
         let component_id = route_match.component_id();
 
         tracing::trace!("Executing request using the Spin executor for component {component_id}");
 
-        let (instance, mut store) = instance_builder.instantiate(()).await?;
+        let (instance, mut store) =
+            metrics::time_instantiation(metrics, component_id, instance_builder.instantiate(()))
+                .await?;
 
         let headers = prepare_request_headers(&req, route_match, client_addr)?;
         // Expects here are safe since we have already checked that this
@@ -19,16 +19,27 @@ pub struct TlsConfig {
 
 impl TlsConfig {
     // Creates a TLS acceptor from server config.
-    pub(super) fn server_config(&self) -> anyhow::Result<TlsAcceptor> {
+    //
+    // If `enable_http2` is set, `h2` is offered via ALPN ahead of `http/1.1`.
+    pub(super) fn server_config(&self, enable_http2: bool) -> anyhow::Result<TlsAcceptor> {
+        let cfg = self.rustls_server_config(enable_http2)?;
+        Ok(Arc::new(cfg).into())
+    }
+
+    fn rustls_server_config(&self, enable_http2: bool) -> anyhow::Result<rustls::ServerConfig> {
         let certs = load_certs(&self.cert_path)?;
         let private_key = load_key(&self.key_path)?;
 
-        let cfg = rustls::ServerConfig::builder()
+        let mut cfg = rustls::ServerConfig::builder()
             .with_no_client_auth()
             .with_single_cert(certs, private_key)
             .map_err(|e| anyhow::anyhow!("{}", e))?;
 
-        Ok(Arc::new(cfg).into())
+        if enable_http2 {
+            cfg.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        }
+
+        Ok(cfg)
     }
 }
 
@@ -128,4 +139,21 @@ mod tests {
         let keys = load_key(path);
         assert!(keys.is_ok());
     }
+
+    #[test]
+    fn test_alpn_protocols() {
+        let tls_config = TlsConfig {
+            cert_path: Path::new(TESTDATA_DIR).join("valid-cert.pem"),
+            key_path: Path::new(TESTDATA_DIR).join("valid-private-key.pem"),
+        };
+
+        let cfg = tls_config.rustls_server_config(false).unwrap();
+        assert!(cfg.alpn_protocols.is_empty());
+
+        let cfg = tls_config.rustls_server_config(true).unwrap();
+        assert_eq!(
+            cfg.alpn_protocols,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+    }
 }
@@ -10,7 +10,12 @@ use tracing::{instrument, Level};
 use wasmtime_wasi::pipe::MemoryOutputPipe;
 use wasmtime_wasi_http::body::HyperIncomingBody as Body;
 
-use crate::{headers::compute_default_headers, server::HttpExecutor, TriggerInstanceBuilder};
+use crate::{
+    headers::compute_default_headers,
+    metrics::{self, HttpMetrics},
+    server::HttpExecutor,
+    TriggerInstanceBuilder,
+};
 
 #[derive(Clone)]
 pub struct WagiHttpExecutor {
@@ -25,6 +30,7 @@ impl HttpExecutor for WagiHttpExecutor {
         route_match: &RouteMatch,
         req: Request<Body>,
         client_addr: SocketAddr,
+        metrics: Option<&HttpMetrics>,
     ) -> Result<Response<Body>> {
         let component = route_match.component_id();
 
@@ -85,7 +91,9 @@ impl HttpExecutor for WagiHttpExecutor {
         wasi_builder.stdin_pipe(Cursor::new(body));
         wasi_builder.stdout(stdout.clone());
 
-        let (instance, mut store) = instance_builder.instantiate(()).await?;
+        let (instance, mut store) =
+            metrics::time_instantiation(metrics, component, instance_builder.instantiate(()))
+                .await?;
 
         let command = wasmtime_wasi::bindings::Command::new(&mut store, &instance)?;
 
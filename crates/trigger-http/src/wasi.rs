@@ -14,7 +14,12 @@ use tracing::{instrument, Instrument, Level};
 use wasmtime_wasi_http::bindings::http::types::Scheme;
 use wasmtime_wasi_http::{bindings::Proxy, body::HyperIncomingBody as Body, WasiHttpView};
 
-use crate::{headers::prepare_request_headers, server::HttpExecutor, TriggerInstanceBuilder};
+use crate::{
+    headers::prepare_request_headers,
+    metrics::{self, HttpMetrics},
+    server::HttpExecutor,
+    TriggerInstanceBuilder,
+};
 
 /// An [`HttpExecutor`] that uses the `wasi:http/incoming-handler` interface.
 #[derive(Clone)]
@@ -30,12 +35,15 @@ impl HttpExecutor for WasiHttpExecutor {
         route_match: &RouteMatch,
         mut req: Request<Body>,
         client_addr: SocketAddr,
+        metrics: Option<&HttpMetrics>,
     ) -> Result<Response<Body>> {
         let component_id = route_match.component_id();
 
         tracing::trace!("Executing request using the Wasi executor for component {component_id}");
 
-        let (instance, mut store) = instance_builder.instantiate(()).await?;
+        let (instance, mut store) =
+            metrics::time_instantiation(metrics, component_id, instance_builder.instantiate(()))
+                .await?;
 
         let headers = prepare_request_headers(&req, route_match, client_addr)?;
         req.headers_mut().clear();
@@ -0,0 +1,639 @@
+//! Connections to MQTT brokers which survive broker restarts and network failures.
+
+use std::collections::VecDeque;
+
+use anyhow::Context;
+use rumqttc::{
+    AsyncClient, ConnectReturnCode, ConnectionError, Event, EventLoop, MqttOptions, Outgoing,
+    Packet, Publish, SubscribeFilter, SubscribeReasonCode,
+};
+use spin_trigger::ReconnectPolicy;
+use tokio::sync::mpsc;
+
+/// The number of received messages which may wait for room in the message channel,
+/// while the connection keeps exchanging keep-alive pings with the broker.
+const MAX_PENDING_MESSAGES: usize = 1000;
+
+/// A broker to connect to, with credentials resolved.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Broker {
+    pub address: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// A connection to an MQTT broker, subscribed to a set of topic filters.
+pub(crate) struct Connection {
+    server_addr: String,
+    client: AsyncClient,
+    event_loop: EventLoop,
+    filters: Vec<SubscribeFilter>,
+    policy: ReconnectPolicy,
+}
+
+impl Connection {
+    /// Creates a connection which sends messages to a channel, where at most
+    /// `max_unacked` of them may be waiting or being handled before being acknowledged.
+    pub fn new(
+        broker: &Broker,
+        filters: Vec<SubscribeFilter>,
+        policy: ReconnectPolicy,
+        max_unacked: usize,
+    ) -> anyhow::Result<Self> {
+        let mut options = MqttOptions::parse_url(with_client_id(&broker.address))
+            .with_context(|| format!("invalid MQTT broker address {:?}", broker.address))?;
+        if let Some(username) = &broker.username {
+            options.set_credentials(username, broker.password.as_deref().unwrap_or_default());
+        }
+        // Messages are acknowledged once handled, so that the broker doesn't consider
+        // them delivered if the trigger stops first
+        options.set_manual_acks(true);
+        let server_addr = format!(
+            "{}:{}",
+            options.broker_address().0,
+            options.broker_address().1
+        );
+        // Leave room in the request queue to acknowledge every message at once, and to
+        // (re)subscribe to and unsubscribe from every filter, so that requests never wait
+        // for the event loop while it waits for room in the message channel
+        let capacity = MAX_PENDING_MESSAGES + max_unacked + 2 * filters.len() + 10;
+        let (client, event_loop) = AsyncClient::new(options, capacity);
+        Ok(Self {
+            server_addr,
+            client,
+            event_loop,
+            filters,
+            policy,
+        })
+    }
+
+    /// The address of the broker, for messages.
+    pub fn server_addr(&self) -> &str {
+        &self.server_addr
+    }
+
+    /// Returns a handle to acknowledge messages and close the connection with.
+    pub fn handle(&self) -> ConnectionHandle {
+        ConnectionHandle {
+            client: self.client.clone(),
+            filters: self.filters.iter().map(|f| f.path.clone()).collect(),
+        }
+    }
+
+    /// Sends each message published to a subscribed topic to `messages`, reconnecting
+    /// and resubscribing whenever the connection is lost. Fails if the first connection
+    /// fails, the broker refuses the connection or a subscription, or the reconnection
+    /// policy is exhausted.
+    ///
+    /// Once `messages` is closed, messages which were not sent to it are dropped
+    /// without being acknowledged, and the connection only sends the acknowledgements
+    /// of messages still being handled. It returns once [`ConnectionHandle::close`]
+    /// has disconnected it, or the connection is lost.
+    pub async fn run(self, messages: mpsc::Sender<Publish>) -> anyhow::Result<()> {
+        let Self {
+            server_addr,
+            client,
+            mut event_loop,
+            filters,
+            policy,
+        } = self;
+        // Dropped once `messages` is closed, as nothing is subscribed to any more
+        let mut client = Some(client);
+        let mut connected = false;
+        let mut failures = 0;
+        // Messages waiting for room in `messages`. The event loop is polled meanwhile,
+        // so that keep-alive pings are still sent while components catch up.
+        let mut pending = VecDeque::new();
+        tracing::info!("Connecting to MQTT broker at {server_addr}");
+        loop {
+            let event = tokio::select! {
+                biased;
+                _ = messages.closed(), if client.is_some() => {
+                    pending.clear();
+                    client = None;
+                    continue;
+                }
+                permit = messages.reserve(), if !pending.is_empty() => {
+                    if let (Ok(permit), Some(publish)) = (permit, pending.pop_front()) {
+                        permit.send(publish);
+                    }
+                    continue;
+                }
+                event = event_loop.poll(), if pending.len() < MAX_PENDING_MESSAGES => event,
+            };
+            let Some(client) = &client else {
+                // Closing: wait for acknowledgements and the disconnection to be sent
+                match event {
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) | Err(_) => return Ok(()),
+                    Ok(_) => continue,
+                }
+            };
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    if failures > 0 {
+                        tracing::info!(
+                            "Reconnected to MQTT broker at {server_addr} after {failures} attempts"
+                        );
+                    } else {
+                        tracing::info!("Connected to MQTT broker at {server_addr}");
+                    }
+                    connected = true;
+                    failures = 0;
+                    // Subscriptions don't outlive a clean session, so are renewed
+                    // on every connection.
+                    client
+                        .subscribe_many(filters.clone())
+                        .await
+                        .with_context(|| {
+                            format!("failed to subscribe on MQTT broker at {server_addr}")
+                        })?;
+                }
+                Ok(Event::Incoming(Packet::SubAck(suback))) => {
+                    let rejected = filters
+                        .iter()
+                        .zip(&suback.return_codes)
+                        .filter(|(_, code)| matches!(code, SubscribeReasonCode::Failure))
+                        .map(|(filter, _)| format!("{:?}", filter.path))
+                        .collect::<Vec<_>>();
+                    anyhow::ensure!(
+                        rejected.is_empty(),
+                        "MQTT broker at {server_addr} rejected subscriptions to {}",
+                        rejected.join(", ")
+                    );
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => pending.push_back(publish),
+                Ok(_) => {}
+                Err(err) if !connected => {
+                    return Err(err).with_context(|| {
+                        format!("failed to connect to MQTT broker at {server_addr}")
+                    });
+                }
+                Err(err) if is_fatal(&err) => {
+                    return Err(err).with_context(|| {
+                        format!("not reconnecting to MQTT broker at {server_addr}")
+                    });
+                }
+                Err(err) => {
+                    failures += 1;
+                    let Some(delay) = policy.delay(failures) else {
+                        return Err(err).with_context(|| {
+                            format!(
+                                "giving up on MQTT broker at {server_addr} after {} attempts to reconnect",
+                                failures - 1
+                            )
+                        });
+                    };
+                    tracing::warn!(
+                        "Lost connection to MQTT broker at {server_addr}: {err}; reconnecting in {delay:?} (attempt {failures})"
+                    );
+                    spin_telemetry::metrics::monotonic_counter!(
+                        spin.mqtt_reconnect_attempts = 1,
+                        server_address = server_addr.as_str()
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = messages.closed() => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Acknowledges the messages received by a [`Connection`], and closes it.
+#[derive(Clone)]
+pub(crate) struct ConnectionHandle {
+    client: AsyncClient,
+    filters: Vec<String>,
+}
+
+impl ConnectionHandle {
+    /// Acknowledges a message once it has been handled. Until then, the broker doesn't
+    /// consider a QoS 1 or 2 message delivered.
+    pub async fn ack(&self, publish: &Publish) -> anyhow::Result<()> {
+        self.client
+            .ack(publish)
+            .await
+            .context("failed to acknowledge MQTT message")
+    }
+
+    /// Unsubscribes from every topic filter and disconnects from the broker, after
+    /// sending any acknowledgements requested before.
+    pub async fn close(self) -> anyhow::Result<()> {
+        for filter in self.filters {
+            self.client
+                .unsubscribe(filter)
+                .await
+                .context("failed to unsubscribe from MQTT topic")?;
+        }
+        self.client
+            .disconnect()
+            .await
+            .context("failed to disconnect from MQTT broker")
+    }
+}
+
+/// Returns whether a connection error can't be fixed by reconnecting, such as the
+/// broker rejecting the client's credentials.
+fn is_fatal(err: &ConnectionError) -> bool {
+    matches!(
+        err,
+        ConnectionError::ConnectionRefused(
+            ConnectReturnCode::BadUserNamePassword
+                | ConnectReturnCode::NotAuthorized
+                | ConnectReturnCode::BadClientId
+                | ConnectReturnCode::RefusedProtocolVersion
+        )
+    )
+}
+
+/// Adds a unique client ID to a broker address which doesn't specify one.
+fn with_client_id(address: &str) -> String {
+    if address.contains("client_id=") {
+        return address.to_owned();
+    }
+    let separator = if address.contains('?') { '&' } else { '?' };
+    format!(
+        "{address}{separator}client_id=spin-{}",
+        uuid::Uuid::new_v4()
+    )
+}
+
+/// Returns whether a topic matches a topic filter, which may contain the `+` (single
+/// level) and `#` (multi-level) wildcards.
+pub(crate) fn topic_matches(filter: &str, topic: &str) -> bool {
+    // Wildcards at the first level don't match topics reserved by the broker
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut topic_levels = topic.split('/');
+    for filter_level in filter.split('/') {
+        match (filter_level, topic_levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (filter_level, Some(topic_level)) if filter_level == topic_level => {}
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::BytesMut;
+    use rumqttc::{
+        mqttbytes::{self, v4},
+        ConnAck, QoS, SubAck,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        task::JoinHandle,
+    };
+
+    use super::*;
+
+    #[test]
+    fn topics_match_filters() {
+        for (filter, topic) in [
+            ("sensors/1/temp", "sensors/1/temp"),
+            ("sensors/+/temp", "sensors/1/temp"),
+            ("sensors/#", "sensors/1/temp"),
+            ("sensors/#", "sensors"),
+            ("#", "sensors/1/temp"),
+            ("+/+", "/sensors"),
+        ] {
+            assert!(
+                topic_matches(filter, topic),
+                "{filter} should match {topic}"
+            );
+        }
+        for (filter, topic) in [
+            ("sensors/1/temp", "sensors/2/temp"),
+            ("sensors/+/temp", "sensors/1/2/temp"),
+            ("sensors/+", "sensors/1/temp"),
+            ("sensors/1", "sensors/1/temp"),
+            ("sensors/1/temp", "sensors/1"),
+            ("#", "$SYS/uptime"),
+            ("+/uptime", "$SYS/uptime"),
+        ] {
+            assert!(
+                !topic_matches(filter, topic),
+                "{filter} should not match {topic}"
+            );
+        }
+    }
+
+    #[test]
+    fn client_ids_are_added() {
+        let address = with_client_id("mqtt://localhost:1883");
+        assert!(address.starts_with("mqtt://localhost:1883?client_id=spin-"));
+        let address = with_client_id("mqtt://localhost:1883?keep_alive_secs=5");
+        assert!(address.starts_with("mqtt://localhost:1883?keep_alive_secs=5&client_id=spin-"));
+        let address = "mqtt://localhost:1883?client_id=mine";
+        assert_eq!(with_client_id(address), address);
+    }
+
+    const POLICY: ReconnectPolicy = ReconnectPolicy {
+        max_delay: Duration::from_secs(30),
+        max_attempts: 10,
+    };
+
+    #[test]
+    fn refused_credentials_are_fatal() {
+        let refused = ConnectionError::ConnectionRefused;
+        assert!(is_fatal(&refused(ConnectReturnCode::BadUserNamePassword)));
+        assert!(is_fatal(&refused(ConnectReturnCode::NotAuthorized)));
+        assert!(!is_fatal(&refused(ConnectReturnCode::ServiceUnavailable)));
+        assert!(!is_fatal(&ConnectionError::NetworkTimeout));
+    }
+
+    fn connect(
+        listener: &TcpListener,
+        capacity: usize,
+    ) -> (
+        JoinHandle<anyhow::Result<()>>,
+        mpsc::Receiver<Publish>,
+        ConnectionHandle,
+    ) {
+        let broker = Broker {
+            address: format!("mqtt://{}", listener.local_addr().unwrap()),
+            username: None,
+            password: None,
+        };
+        let filters = vec![SubscribeFilter::new(
+            "sensors/+/temp".into(),
+            QoS::AtLeastOnce,
+        )];
+        let connection = Connection::new(&broker, filters, POLICY, capacity).unwrap();
+        let handle = connection.handle();
+        let (tx, rx) = mpsc::channel(capacity);
+        (tokio::spawn(connection.run(tx)), rx, handle)
+    }
+
+    /// A stand-in for a broker, which serves one client connection at a time.
+    struct StandInBroker {
+        stream: TcpStream,
+        buf: BytesMut,
+    }
+
+    impl StandInBroker {
+        async fn accept(listener: &TcpListener) -> Self {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut broker = Self {
+                stream,
+                buf: BytesMut::new(),
+            };
+            let Packet::Connect(_) = broker.read().await else {
+                panic!("expected CONNECT");
+            };
+            broker
+                .write(|out| ConnAck::new(ConnectReturnCode::Success, false).write(out))
+                .await;
+            broker
+        }
+
+        /// Acknowledges the client's subscriptions, returning their filters.
+        async fn accept_subscribe(&mut self) -> Vec<String> {
+            self.subscribe(SubscribeReasonCode::Success).await
+        }
+
+        /// Replies to the client's subscriptions, returning their filters.
+        async fn subscribe(&mut self, code: impl Fn(QoS) -> SubscribeReasonCode) -> Vec<String> {
+            let subscribe = loop {
+                match self.read().await {
+                    Packet::Subscribe(subscribe) => break subscribe,
+                    Packet::PingReq => continue,
+                    packet => panic!("expected SUBSCRIBE, got {packet:?}"),
+                }
+            };
+            let codes = subscribe
+                .filters
+                .iter()
+                .map(|filter| code(filter.qos))
+                .collect();
+            self.write(|out| SubAck::new(subscribe.pkid, codes).write(out))
+                .await;
+            subscribe.filters.into_iter().map(|f| f.path).collect()
+        }
+
+        async fn publish(&mut self, topic: &str, payload: &str) {
+            let publish = Publish::new(topic, QoS::AtMostOnce, payload);
+            self.write(|out| publish.write(out)).await;
+        }
+
+        /// Returns the next packet from the client other than a ping, or `None` once
+        /// the client closes the connection.
+        async fn next(&mut self) -> Option<Packet> {
+            loop {
+                match v4::read(&mut self.buf, 1024 * 1024) {
+                    Ok(Packet::PingReq) => continue,
+                    Ok(packet) => return Some(packet),
+                    Err(mqttbytes::Error::InsufficientBytes(_)) => {
+                        if self.stream.read_buf(&mut self.buf).await.unwrap() == 0 {
+                            return None;
+                        }
+                    }
+                    Err(err) => panic!("invalid packet from client: {err:?}"),
+                }
+            }
+        }
+
+        async fn read(&mut self) -> Packet {
+            loop {
+                match v4::read(&mut self.buf, 1024 * 1024) {
+                    Ok(packet) => return packet,
+                    Err(mqttbytes::Error::InsufficientBytes(_)) => {
+                        let read = self.stream.read_buf(&mut self.buf).await.unwrap();
+                        assert!(read > 0, "client closed the connection");
+                    }
+                    Err(err) => panic!("invalid packet from client: {err:?}"),
+                }
+            }
+        }
+
+        async fn write(
+            &mut self,
+            packet: impl FnOnce(&mut BytesMut) -> Result<usize, mqttbytes::Error>,
+        ) {
+            let mut out = BytesMut::new();
+            packet(&mut out).unwrap();
+            self.stream.write_all(&out).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_are_received_across_reconnections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (connection, mut rx, _handle) = connect(&listener, 10);
+
+        let mut stand_in = StandInBroker::accept(&listener).await;
+        assert_eq!(stand_in.accept_subscribe().await, ["sensors/+/temp"]);
+        stand_in.publish("sensors/1/temp", "21").await;
+        let publish = rx.recv().await.unwrap();
+        assert_eq!(publish.topic, "sensors/1/temp");
+        assert_eq!(publish.payload.as_ref(), b"21");
+
+        // The client reconnects and resubscribes after the broker drops it
+        drop(stand_in);
+        let mut stand_in = StandInBroker::accept(&listener).await;
+        assert_eq!(stand_in.accept_subscribe().await, ["sensors/+/temp"]);
+        stand_in.publish("sensors/2/temp", "22").await;
+        let publish = rx.recv().await.unwrap();
+        assert_eq!(publish.topic, "sensors/2/temp");
+        assert_eq!(publish.payload.as_ref(), b"22");
+
+        // Once its messages are no longer wanted, the connection stops when it's lost
+        drop(rx);
+        drop(stand_in);
+        connection.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn closed_connections_unsubscribe_and_disconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (connection, rx, handle) = connect(&listener, 10);
+        let mut stand_in = StandInBroker::accept(&listener).await;
+        stand_in.accept_subscribe().await;
+
+        // The broker stays connected, but the client closes the connection
+        drop(rx);
+        handle.close().await.unwrap();
+        let Some(Packet::Unsubscribe(unsubscribe)) = stand_in.next().await else {
+            panic!("expected UNSUBSCRIBE");
+        };
+        assert_eq!(unsubscribe.topics, ["sensors/+/temp"]);
+        let Some(Packet::Disconnect) = stand_in.next().await else {
+            panic!("expected DISCONNECT");
+        };
+        connection.await.unwrap().unwrap();
+        assert!(stand_in.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn messages_are_acknowledged_once_handled() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (connection, mut rx, handle) = connect(&listener, 1);
+        let mut stand_in = StandInBroker::accept(&listener).await;
+        stand_in.accept_subscribe().await;
+
+        // Messages keep being read from the broker while the channel is full
+        for pkid in 1..=3 {
+            let mut publish = Publish::new("sensors/1/temp", QoS::AtLeastOnce, pkid.to_string());
+            publish.pkid = pkid;
+            stand_in.write(|out| publish.write(out)).await;
+        }
+        for pkid in 1..=3 {
+            let publish = rx.recv().await.unwrap();
+            assert_eq!(publish.payload.as_ref(), pkid.to_string().as_bytes());
+
+            // Nothing is acknowledged while the message is being handled
+            let next = tokio::time::timeout(Duration::from_millis(200), stand_in.next()).await;
+            assert!(next.is_err(), "unexpected {next:?} before handling");
+
+            handle.ack(&publish).await.unwrap();
+            let Some(Packet::PubAck(puback)) = stand_in.next().await else {
+                panic!("expected PUBACK");
+            };
+            assert_eq!(puback.pkid, pkid);
+        }
+
+        drop(rx);
+        handle.close().await.unwrap();
+        connection.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn messages_being_handled_are_acknowledged_after_closing() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (connection, mut rx, handle) = connect(&listener, 10);
+        let mut stand_in = StandInBroker::accept(&listener).await;
+        stand_in.accept_subscribe().await;
+
+        for pkid in 1..=2 {
+            let mut publish = Publish::new("sensors/1/temp", QoS::AtLeastOnce, pkid.to_string());
+            publish.pkid = pkid;
+            stand_in.write(|out| publish.write(out)).await;
+        }
+        let handled = rx.recv().await.unwrap();
+        assert_eq!(handled.pkid, 1);
+
+        // Shutdown stops receiving messages, but the message being handled is still
+        // acknowledged, while the one which never was handled is not
+        drop(rx);
+        handle.ack(&handled).await.unwrap();
+        handle.close().await.unwrap();
+        let Some(Packet::PubAck(puback)) = stand_in.next().await else {
+            panic!("expected PUBACK");
+        };
+        assert_eq!(puback.pkid, 1);
+        let Some(Packet::Unsubscribe(_)) = stand_in.next().await else {
+            panic!("expected UNSUBSCRIBE");
+        };
+        let Some(Packet::Disconnect) = stand_in.next().await else {
+            panic!("expected DISCONNECT");
+        };
+        connection.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejected_subscriptions_fail() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (connection, _rx, _handle) = connect(&listener, 10);
+        let mut stand_in = StandInBroker::accept(&listener).await;
+        stand_in.subscribe(|_| SubscribeReasonCode::Failure).await;
+
+        let err = connection.await.unwrap().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("rejected subscriptions to \"sensors/+/temp\""),
+            "{err:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn first_connection_failures_are_not_retried() {
+        // Nothing is listening once the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (connection, _rx, _handle) = connect(&listener, 10);
+        drop(listener);
+
+        let err = connection.await.unwrap().unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("failed to connect to MQTT broker"),
+            "{err:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (connection, _rx, _handle) = connect(&listener, 10);
+        let mut stand_in = StandInBroker::accept(&listener).await;
+        stand_in.accept_subscribe().await;
+        drop(stand_in);
+
+        // The broker comes back, but refuses the client's credentials
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stand_in = StandInBroker {
+            stream,
+            buf: BytesMut::new(),
+        };
+        let Packet::Connect(_) = stand_in.read().await else {
+            panic!("expected CONNECT");
+        };
+        stand_in
+            .write(|out| ConnAck::new(ConnectReturnCode::NotAuthorized, false).write(out))
+            .await;
+
+        let err = connection.await.unwrap().unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("not reconnecting to MQTT broker"),
+            "{err:#}"
+        );
+    }
+}
@@ -0,0 +1,452 @@
+mod connection;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use clap::Args;
+use connection::{topic_matches, Broker, Connection};
+use futures::{stream::FuturesUnordered, StreamExt};
+use rumqttc::{Publish, QoS, SubscribeFilter};
+use serde::Deserialize;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{App, ReconnectPolicy, ShutdownSignal, Trigger, TriggerApp};
+use spin_world::exports::spin::mqtt::inbound_mqtt;
+use tokio::sync::mpsc;
+use tracing::{instrument, Level};
+
+/// The number of received messages which may wait to be handled.
+const MESSAGE_CHANNEL_CAP: usize = 100;
+/// The number of messages from a single broker which may be handled at once.
+const MAX_IN_FLIGHT: usize = 100;
+
+#[derive(Args)]
+pub struct CliArgs {
+    /// The number of consecutive attempts to reconnect to an MQTT broker after losing
+    /// the connection, before the trigger exits. The trigger exits immediately if it
+    /// can't connect at startup, or if the broker rejects its credentials.
+    #[clap(
+        long = "max-reconnect-attempts",
+        env = "SPIN_MQTT_MAX_RECONNECT_ATTEMPTS",
+        default_value = "10"
+    )]
+    pub max_reconnect_attempts: u32,
+
+    /// The longest delay between attempts to reconnect to an MQTT broker, such as "30s".
+    /// Delays start short and double after each failed attempt, up to this limit.
+    #[clap(
+        long = "max-reconnect-delay",
+        env = "SPIN_MQTT_MAX_RECONNECT_DELAY",
+        default_value = "30s",
+        value_parser = humantime::parse_duration
+    )]
+    pub max_reconnect_delay: Duration,
+}
+
+/// The Spin MQTT trigger.
+pub struct MqttTrigger {
+    /// How connections reconnect after losing their connection.
+    reconnect: ReconnectPolicy,
+}
+
+/// MQTT trigger metadata.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerMetadata {
+    address: String,
+}
+
+/// MQTT trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Topic filter to subscribe to, which may contain `+` and `#` wildcards
+    topic: String,
+    /// QoS to subscribe with: 0 (at most once), 1 (at least once) or 2 (exactly once)
+    #[serde(default)]
+    qos: u8,
+    /// Optionally override address for trigger
+    address: Option<String>,
+    /// Username to connect to the broker with
+    username: Option<String>,
+    /// Password to connect to the broker with
+    password: Option<String>,
+}
+
+impl TriggerConfig {
+    /// The QoS to subscribe with.
+    fn qos(&self) -> anyhow::Result<QoS> {
+        Ok(match self.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            qos => anyhow::bail!(
+                "invalid mqtt trigger qos {qos} for component {}; must be 0, 1 or 2",
+                self.component
+            ),
+        })
+    }
+}
+
+impl<F: RuntimeFactors> Trigger<F> for MqttTrigger {
+    const TYPE: &'static str = "mqtt";
+
+    type CliArgs = CliArgs;
+
+    type InstanceState = ();
+
+    fn new(cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self {
+            reconnect: ReconnectPolicy {
+                max_delay: cli_args.max_reconnect_delay,
+                max_attempts: cli_args.max_reconnect_attempts,
+            },
+        })
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        self.run_with_shutdown(trigger_app, ShutdownSignal::new())
+            .await
+    }
+
+    async fn run_with_shutdown(
+        self,
+        trigger_app: TriggerApp<Self, F>,
+        shutdown: ShutdownSignal,
+    ) -> anyhow::Result<()> {
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("MqttTrigger depends on VariablesFactor")?;
+
+        let app = trigger_app.app();
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+        let metadata = app
+            .get_trigger_metadata::<TriggerMetadata>(trigger_type)?
+            .unwrap_or_default();
+        let default_address_expr = &metadata.address;
+        let default_address = app_variables
+            .resolve_expression(default_address_expr.clone())
+            .await
+            .with_context(|| {
+                format!("failed to resolve mqtt trigger default address {default_address_expr:?}")
+            })?;
+
+        // Maps <broker> -> <subscriptions>
+        let mut broker_subscriptions: HashMap<Broker, Vec<Subscription>> = HashMap::new();
+
+        // Resolve trigger configs before connecting to any brokers
+        for (_, config) in app
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .collect::<Vec<_>>()
+        {
+            let qos = config.qos()?;
+            let component_id = config.component;
+
+            let resolve = |name: &'static str, expr: String| {
+                let component_id = &component_id;
+                async move {
+                    app_variables
+                        .resolve_expression(expr.clone())
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "failed to resolve mqtt trigger {name} {expr:?} for component {component_id}"
+                            )
+                        })
+                }
+            };
+            let address = resolve(
+                "address",
+                config.address.unwrap_or_else(|| default_address.clone()),
+            )
+            .await?;
+            let topic = resolve("topic", config.topic).await?;
+            let username = match config.username {
+                Some(username) => Some(resolve("username", username).await?),
+                None => None,
+            };
+            let password = match config.password {
+                Some(password) => Some(resolve("password", password).await?),
+                None => None,
+            };
+
+            broker_subscriptions
+                .entry(Broker {
+                    address,
+                    username,
+                    password,
+                })
+                .or_default()
+                .push(Subscription {
+                    topic,
+                    qos,
+                    component_id,
+                });
+        }
+
+        // Start subscriber(s)
+        let trigger_app = Arc::new(trigger_app);
+        let mut subscriber_tasks = Vec::new();
+        for (broker, subscriptions) in broker_subscriptions {
+            let subscriber =
+                Subscriber::new(&broker, trigger_app.clone(), subscriptions, self.reconnect)?;
+            let task = tokio::spawn(subscriber.run_listener(shutdown.clone()));
+            subscriber_tasks.push(task);
+        }
+
+        // Wait for any task to complete
+        let (res, _, remaining_tasks) = futures::future::select_all(subscriber_tasks).await;
+        if shutdown.is_requested() {
+            // Let the remaining subscribers finish handling any in-flight messages
+            for remaining in futures::future::join_all(remaining_tasks).await {
+                remaining??;
+            }
+        }
+        res?
+    }
+}
+
+/// A component's subscription to a topic filter.
+struct Subscription {
+    topic: String,
+    qos: QoS,
+    component_id: String,
+}
+
+/// Subscribes to topics from a single MQTT broker.
+struct Subscriber<F: RuntimeFactors> {
+    connection: Connection,
+    trigger_app: Arc<TriggerApp<MqttTrigger, F>>,
+    subscriptions: Vec<Subscription>,
+}
+
+impl<F: RuntimeFactors> Subscriber<F> {
+    fn new(
+        broker: &Broker,
+        trigger_app: Arc<TriggerApp<MqttTrigger, F>>,
+        subscriptions: Vec<Subscription>,
+        reconnect: ReconnectPolicy,
+    ) -> anyhow::Result<Self> {
+        let filters = subscribe_filters(&subscriptions);
+        let connection = Connection::new(
+            broker,
+            filters,
+            reconnect,
+            MESSAGE_CHANNEL_CAP + MAX_IN_FLIGHT,
+        )?;
+        Ok(Self {
+            connection,
+            trigger_app,
+            subscriptions,
+        })
+    }
+
+    async fn run_listener(self, shutdown: ShutdownSignal) -> anyhow::Result<()> {
+        let Self {
+            connection,
+            trigger_app,
+            subscriptions,
+        } = self;
+        let server_addr = connection.server_addr().to_owned();
+        let handle = connection.handle();
+
+        println!("Active Topics on {server_addr}:");
+        for subscription in &subscriptions {
+            println!(
+                "\t{server_addr}/{} (qos {}): [{}]",
+                subscription.topic, subscription.qos as u8, subscription.component_id
+            );
+        }
+
+        let (tx, mut rx) = mpsc::channel(MESSAGE_CHANNEL_CAP);
+        let connection = tokio::spawn(connection.run(tx));
+
+        let mut in_flight = FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                publish = rx.recv(), if in_flight.len() < MAX_IN_FLIGHT => {
+                    let Some(publish) = publish else {
+                        break;
+                    };
+                    let (trigger_app, subscriptions) = (&trigger_app, &subscriptions);
+                    let handle = &handle;
+                    in_flight.push(async move {
+                        handle_message(trigger_app, subscriptions, &publish).await;
+                        // Messages are acknowledged even if a component failed, as
+                        // they would otherwise be redelivered to it
+                        if let Err(err) = handle.ack(&publish).await {
+                            tracing::warn!("{err:#}");
+                        }
+                    });
+                }
+                Some(()) = in_flight.next(), if !in_flight.is_empty() => {}
+                _ = shutdown.requested() => break,
+            }
+        }
+
+        // Messages are handled to completion even if shutdown is requested meanwhile,
+        // and acknowledged before disconnecting. Messages which were received but not
+        // handled are not acknowledged.
+        drop(rx);
+        while in_flight.next().await.is_some() {}
+        if shutdown.is_requested() {
+            tracing::info!("Unsubscribing from topics on {server_addr}");
+            if let Err(err) = handle.close().await {
+                tracing::warn!("{err:#}");
+            }
+        }
+        connection.await??;
+        if shutdown.is_requested() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("disconnected from {server_addr}"))
+        }
+    }
+}
+
+/// Returns the filters to subscribe to: each topic filter once, at the highest QoS
+/// any component asked for.
+fn subscribe_filters(subscriptions: &[Subscription]) -> Vec<SubscribeFilter> {
+    let mut filters: HashMap<&str, QoS> = HashMap::new();
+    for subscription in subscriptions {
+        let qos = filters
+            .entry(&subscription.topic)
+            .or_insert(subscription.qos);
+        if subscription.qos as u8 > *qos as u8 {
+            *qos = subscription.qos;
+        }
+    }
+    filters
+        .into_iter()
+        .map(|(topic, qos)| SubscribeFilter::new(topic.to_owned(), qos))
+        .collect()
+}
+
+#[instrument(name = "spin_trigger_mqtt.handle_message", skip_all, fields(
+    otel.name = format!("{} receive", publish.topic),
+    otel.kind = "consumer",
+    messaging.operation = "receive",
+    messaging.system = "mqtt"
+))]
+async fn handle_message<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<MqttTrigger, F>,
+    subscriptions: &[Subscription],
+    publish: &Publish,
+) {
+    let topic = &publish.topic;
+    tracing::trace!(%topic, "Received message");
+
+    // A component whose filters overlap is invoked once per message
+    let mut component_ids = subscriptions
+        .iter()
+        .filter(|subscription| topic_matches(&subscription.topic, topic))
+        .map(|subscription| subscription.component_id.as_str())
+        .collect::<Vec<_>>();
+    component_ids.sort_unstable();
+    component_ids.dedup();
+
+    let dispatch_futures = component_ids.into_iter().map(|component_id| async move {
+        tracing::trace!("Executing MQTT component {component_id}");
+        if let Err(err) = dispatch_handler(trigger_app, component_id, publish).await {
+            tracing::info!("Component {component_id} handler failed: {err}");
+        }
+    });
+    futures::future::join_all(dispatch_futures).await;
+}
+
+#[instrument(name = "spin_trigger_mqtt.dispatch_handler", skip_all, err(level = Level::INFO), fields(
+    component_id = component_id,
+))]
+async fn dispatch_handler<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<MqttTrigger, F>,
+    component_id: &str,
+    publish: &Publish,
+) -> anyhow::Result<()> {
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.request_count = 1,
+        trigger_type = "mqtt",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+
+    let (instance, mut store) = trigger_app.prepare(component_id)?.instantiate(()).await?;
+
+    let guest_indices = inbound_mqtt::GuestIndices::new_instance(&mut store, &instance)?;
+    let guest = guest_indices.load(&mut store, &instance)?;
+
+    let message = inbound_mqtt::Message {
+        topic: publish.topic.clone(),
+        payload: publish.payload.to_vec(),
+        qos: match publish.qos {
+            QoS::AtMostOnce => inbound_mqtt::Qos::AtMostOnce,
+            QoS::AtLeastOnce => inbound_mqtt::Qos::AtLeastOnce,
+            QoS::ExactlyOnce => inbound_mqtt::Qos::ExactlyOnce,
+        },
+    };
+    match guest.call_handle_message(&mut store, &message).await? {
+        Ok(()) => Ok(()),
+        Err(inbound_mqtt::Error::Other(err)) => {
+            anyhow::bail!("MQTT handler returned an error: {err}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qos_defaults_to_at_most_once() {
+        let config: TriggerConfig =
+            toml::from_str("component = \"sensor\"\ntopic = \"sensors/+/temp\"").unwrap();
+        assert_eq!(config.qos().unwrap(), QoS::AtMostOnce);
+    }
+
+    #[test]
+    fn invalid_qos_is_rejected() {
+        let config = |qos| TriggerConfig {
+            component: "sensor".into(),
+            topic: "sensors/+/temp".into(),
+            qos,
+            ..Default::default()
+        };
+        assert_eq!(config(1).qos().unwrap(), QoS::AtLeastOnce);
+        assert_eq!(config(2).qos().unwrap(), QoS::ExactlyOnce);
+        let err = config(3).qos().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid mqtt trigger qos 3 for component sensor; must be 0, 1 or 2"
+        );
+    }
+
+    #[test]
+    fn filters_are_subscribed_once_at_highest_qos() {
+        let subscription = |topic: &str, qos, component_id: &str| Subscription {
+            topic: topic.into(),
+            qos,
+            component_id: component_id.into(),
+        };
+        let subscriptions = [
+            subscription("sensors/+/temp", QoS::AtMostOnce, "logger"),
+            subscription("sensors/+/temp", QoS::AtLeastOnce, "alerts"),
+            subscription("sensors/+/temp", QoS::AtMostOnce, "dashboard"),
+            subscription("sensors/#", QoS::ExactlyOnce, "archive"),
+        ];
+        let mut filters = subscribe_filters(&subscriptions)
+            .into_iter()
+            .map(|filter| (filter.path, filter.qos))
+            .collect::<Vec<_>>();
+        filters.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            filters,
+            [
+                ("sensors/#".to_owned(), QoS::ExactlyOnce),
+                ("sensors/+/temp".to_owned(), QoS::AtLeastOnce),
+            ]
+        );
+    }
+}
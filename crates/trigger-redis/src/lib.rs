@@ -1,16 +1,54 @@
-use std::{collections::HashMap, sync::Arc};
+mod reconnect;
+mod stream;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use anyhow::Context;
+use clap::Args;
 use futures::{StreamExt, TryFutureExt};
+use reconnect::Connection;
 use redis::{Client, Msg};
 use serde::Deserialize;
 use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
-use spin_trigger::{cli::NoCliArgs, App, Trigger, TriggerApp};
-use spin_world::exports::fermyon::spin::inbound_redis;
+use spin_trigger::{App, ReconnectPolicy, ShutdownSignal, Trigger, TriggerApp};
+use spin_world::exports::{
+    fermyon::spin::inbound_redis, spin::redis::inbound_redis as inbound_redis3,
+};
+use stream::{StreamConfig, StreamConsumer, StreamOptions};
 use tracing::{instrument, Level};
 
-pub struct RedisTrigger;
+#[derive(Args)]
+pub struct CliArgs {
+    /// The number of consecutive attempts to reconnect to a Redis server after losing
+    /// the connection, before the trigger exits. The trigger exits immediately if it
+    /// can't connect at startup, or if the server rejects its credentials.
+    #[clap(
+        long = "max-reconnect-attempts",
+        env = "SPIN_REDIS_MAX_RECONNECT_ATTEMPTS",
+        default_value = "10"
+    )]
+    pub max_reconnect_attempts: u32,
+
+    /// The longest delay between attempts to reconnect to a Redis server, such as "30s".
+    /// Delays start short and double after each failed attempt, up to this limit.
+    #[clap(
+        long = "max-reconnect-delay",
+        env = "SPIN_REDIS_MAX_RECONNECT_DELAY",
+        default_value = "30s",
+        value_parser = humantime::parse_duration
+    )]
+    pub max_reconnect_delay: Duration,
+}
+
+/// The name of the `spin:redis/inbound-redis@3.0.0` export.
+const INBOUND_REDIS_V3_EXPORT: &str = "spin:redis/inbound-redis@3.0.0";
+
+/// The Spin Redis trigger.
+pub struct RedisTrigger {
+    /// How subscribers reconnect after losing their connection.
+    reconnect: ReconnectPolicy,
+}
 
 /// Redis trigger metadata.
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -26,7 +64,11 @@ struct TriggerConfig {
     /// Component ID to invoke
     component: String,
     /// Channel to subscribe to
-    channel: String,
+    channel: Option<String>,
+    /// Channel pattern to subscribe to, such as `orders.*`, instead of a channel
+    pattern: Option<String>,
+    /// Stream to consume as a member of a consumer group, instead of a channel
+    stream: Option<StreamConfig>,
     /// Optionally override address for trigger
     address: Option<String>,
 }
@@ -34,15 +76,29 @@ struct TriggerConfig {
 impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
     const TYPE: &'static str = "redis";
 
-    type CliArgs = NoCliArgs;
+    type CliArgs = CliArgs;
 
     type InstanceState = ();
 
-    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
-        Ok(Self)
+    fn new(cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self {
+            reconnect: ReconnectPolicy {
+                max_delay: cli_args.max_reconnect_delay,
+                max_attempts: cli_args.max_reconnect_attempts,
+            },
+        })
     }
 
     async fn run(self, trigger_app: spin_trigger::TriggerApp<Self, F>) -> anyhow::Result<()> {
+        self.run_with_shutdown(trigger_app, ShutdownSignal::new())
+            .await
+    }
+
+    async fn run_with_shutdown(
+        self,
+        trigger_app: spin_trigger::TriggerApp<Self, F>,
+        shutdown: ShutdownSignal,
+    ) -> anyhow::Result<()> {
         let app_variables = trigger_app
             .configured_app()
             .app_state::<VariablesFactor>()
@@ -61,8 +117,11 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
                 format!("failed to resolve redis trigger default address {default_address_expr:?}")
             })?;
 
-        // Maps <server address> -> <channel> -> <component IDs>
-        let mut server_channel_components: HashMap<String, ChannelComponents> = HashMap::new();
+        // Maps <server address> -> <channels and patterns> -> <component IDs>
+        let mut server_subscriptions: HashMap<String, Subscriptions> = HashMap::new();
+        // Maps (<server address>, <stream key>, <group>) -> (<component ID>, <options>)
+        let mut stream_consumers: HashMap<(String, String, String), (String, StreamOptions)> =
+            HashMap::new();
 
         // Resolve trigger configs before starting any subscribers
         for (_, config) in app
@@ -82,19 +141,62 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
                     )
                 })?;
 
-            let channel_expr = &config.channel;
+            let sources = [&config.channel, &config.pattern]
+                .into_iter()
+                .flatten()
+                .count()
+                + usize::from(config.stream.is_some());
+            anyhow::ensure!(
+                sources == 1,
+                "Redis trigger for component {component_id} must have exactly one of `channel`, `pattern` or `stream`"
+            );
+
+            if let Some(stream_config) = &config.stream {
+                let key_expr = &stream_config.key;
+                let key = app_variables
+                    .resolve_expression(key_expr.clone())
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "failed to resolve redis trigger stream {key_expr:?} for component {component_id}"
+                        )
+                    })?;
+                let options = StreamOptions::new(stream_config, key).with_context(|| {
+                    format!("invalid redis trigger stream for component {component_id}")
+                })?;
+                let consumer_key = (address, options.key.clone(), options.group.clone());
+                if let Some((other_id, _)) = stream_consumers.get(&consumer_key) {
+                    anyhow::bail!(
+                        "components {other_id} and {component_id} both consume redis stream {:?} as group {:?}; use a separate group for each",
+                        consumer_key.1,
+                        consumer_key.2,
+                    );
+                }
+                stream_consumers.insert(consumer_key, (component_id, options));
+                continue;
+            }
+
+            let (kind, channel_expr) = match (&config.channel, &config.pattern) {
+                (Some(channel), _) => ("channel", channel),
+                (_, Some(pattern)) => ("pattern", pattern),
+                _ => unreachable!("checked above"),
+            };
             let channel = app_variables
                 .resolve_expression(channel_expr.clone())
                 .await
                 .with_context(|| {
                     format!(
-                        "failed to resolve redis trigger channel {channel_expr:?} for component {component_id}"
+                        "failed to resolve redis trigger {kind} {channel_expr:?} for component {component_id}"
                     )
                 })?;
 
-            server_channel_components
-                .entry(address)
-                .or_default()
+            let subscriptions = server_subscriptions.entry(address).or_default();
+            let channel_components = if config.pattern.is_some() {
+                &mut subscriptions.patterns
+            } else {
+                &mut subscriptions.channels
+            };
+            channel_components
                 .entry(channel)
                 .or_default()
                 .push(component_id);
@@ -103,14 +205,32 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
         // Start subscriber(s)
         let trigger_app = Arc::new(trigger_app);
         let mut subscriber_tasks = Vec::new();
-        for (address, channel_components) in server_channel_components {
-            let subscriber = Subscriber::new(address, trigger_app.clone(), channel_components)?;
-            let task = tokio::spawn(subscriber.run_listener());
+        for (address, subscriptions) in server_subscriptions {
+            let subscriber =
+                Subscriber::new(address, trigger_app.clone(), subscriptions, self.reconnect)?;
+            let task = tokio::spawn(subscriber.run_listener(shutdown.clone()));
+            subscriber_tasks.push(task);
+        }
+        for ((address, _, _), (component_id, options)) in stream_consumers {
+            let consumer = StreamConsumer::new(
+                address,
+                trigger_app.clone(),
+                component_id,
+                options,
+                self.reconnect,
+            )?;
+            let task = tokio::spawn(consumer.run(shutdown.clone()));
             subscriber_tasks.push(task);
         }
 
         // Wait for any task to complete
-        let (res, _, _) = futures::future::select_all(subscriber_tasks).await;
+        let (res, _, remaining_tasks) = futures::future::select_all(subscriber_tasks).await;
+        if shutdown.is_requested() {
+            // Let the remaining subscribers finish handling any in-flight messages
+            for remaining in futures::future::join_all(remaining_tasks).await {
+                remaining??;
+            }
+        }
         res?
     }
 }
@@ -118,28 +238,66 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
 /// Maps <channel> -> <component IDs>
 type ChannelComponents = HashMap<String, Vec<String>>;
 
+/// The channels and channel patterns to subscribe to on a single Redis server.
+#[derive(Default)]
+struct Subscriptions {
+    /// Maps <channel> -> <component IDs>
+    channels: ChannelComponents,
+    /// Maps <channel pattern> -> <component IDs>
+    patterns: ChannelComponents,
+}
+
 /// Subscribes to channels from a single Redis server.
 struct Subscriber<F: RuntimeFactors> {
     client: Client,
     trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
-    channel_components: ChannelComponents,
+    subscriptions: Subscriptions,
+    reconnect: ReconnectPolicy,
 }
 
 impl<F: RuntimeFactors> Subscriber<F> {
     fn new(
         address: String,
         trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
-        channel_components: ChannelComponents,
+        subscriptions: Subscriptions,
+        reconnect: ReconnectPolicy,
     ) -> anyhow::Result<Self> {
         let client = Client::open(address)?;
         Ok(Self {
             client,
             trigger_app,
-            channel_components,
+            subscriptions,
+            reconnect,
         })
     }
 
-    async fn run_listener(self) -> anyhow::Result<()> {
+    async fn run_listener(self, shutdown: ShutdownSignal) -> anyhow::Result<()> {
+        let server_addr = self.client.get_connection_info().addr.to_string();
+        let mut connection = Connection::new(server_addr, self.reconnect);
+        let mut first_connection = true;
+        loop {
+            let err = match self
+                .listen(&mut connection, &shutdown, first_connection)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            first_connection = false;
+            if !connection.disconnected(err, &shutdown).await? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Subscribes to all channels and handles messages until shutdown is requested,
+    /// or the connection fails.
+    async fn listen(
+        &self,
+        connection: &mut Connection,
+        shutdown: &ShutdownSignal,
+        first_connection: bool,
+    ) -> anyhow::Result<()> {
         let server_addr = &self.client.get_connection_info().addr;
 
         tracing::info!("Connecting to Redis server at {server_addr}");
@@ -149,19 +307,47 @@ impl<F: RuntimeFactors> Subscriber<F> {
             .await
             .with_context(|| format!("Redis trigger failed to connect to {server_addr}"))?;
 
-        println!("Active Channels on {server_addr}:");
+        if first_connection {
+            println!("Active Channels on {server_addr}:");
+        }
 
         // Subscribe to channels
-        for (channel, components) in &self.channel_components {
+        for (channel, components) in &self.subscriptions.channels {
             tracing::info!("Subscribing to {channel:?} on {server_addr}");
             pubsub.subscribe(channel).await.with_context(|| {
                 format!("Redis trigger failed to subscribe to channel {channel:?} on {server_addr}")
             })?;
-            println!("\t{server_addr}/{channel}: [{}]", components.join(","));
+            if first_connection {
+                println!("\t{server_addr}/{channel}: [{}]", components.join(","));
+            }
+        }
+        for (pattern, components) in &self.subscriptions.patterns {
+            tracing::info!("Subscribing to pattern {pattern:?} on {server_addr}");
+            pubsub.psubscribe(pattern).await.with_context(|| {
+                format!("Redis trigger failed to subscribe to pattern {pattern:?} on {server_addr}")
+            })?;
+            if first_connection {
+                println!(
+                    "\t{server_addr}/{pattern} (pattern): [{}]",
+                    components.join(",")
+                );
+            }
         }
+        connection.connected();
 
         let mut message_stream = pubsub.on_message();
-        while let Some(msg) = message_stream.next().await {
+        loop {
+            let msg = tokio::select! {
+                msg = message_stream.next() => msg,
+                _ = shutdown.requested() => {
+                    tracing::info!("Unsubscribing from channels on {server_addr}");
+                    return Ok(());
+                }
+            };
+            let Some(msg) = msg else {
+                break;
+            };
+            // Messages are handled to completion even if shutdown is requested meanwhile
             if let Err(err) = self.handle_message(msg).await {
                 tracing::error!("Error handling message from {server_addr}: {err}");
             }
@@ -180,44 +366,86 @@ impl<F: RuntimeFactors> Subscriber<F> {
         let channel = msg.get_channel_name();
         tracing::trace!(%server_addr, %channel, "Received message");
 
-        let Some(component_ids) = self.channel_components.get(channel) else {
-            anyhow::bail!("message from unexpected channel {channel:?}");
+        let (pattern, component_ids) = if msg.from_pattern() {
+            let pattern: String = msg.get_pattern()?;
+            let Some(component_ids) = self.subscriptions.patterns.get(&pattern) else {
+                anyhow::bail!("message from unexpected pattern {pattern:?}");
+            };
+            (Some(pattern), component_ids)
+        } else {
+            let Some(component_ids) = self.subscriptions.channels.get(channel) else {
+                anyhow::bail!("message from unexpected channel {channel:?}");
+            };
+            (None, component_ids)
+        };
+        let message = Message {
+            channel,
+            pattern: pattern.as_deref(),
+            payload: msg.get_payload_bytes(),
         };
 
         let dispatch_futures = component_ids.iter().map(|component_id| {
             tracing::trace!("Executing Redis component {component_id}");
-            self.dispatch_handler(&msg, component_id)
-                .inspect_err(move |err| {
-                    tracing::info!("Component {component_id} handler failed: {err}");
-                })
+            dispatch_handler(&self.trigger_app, component_id, &message).inspect_err(move |err| {
+                tracing::info!("Component {component_id} handler failed: {err}");
+            })
         });
         futures::future::join_all(dispatch_futures).await;
 
         Ok(())
     }
+}
 
-    async fn dispatch_handler(&self, msg: &Msg, component_id: &str) -> anyhow::Result<()> {
-        spin_telemetry::metrics::monotonic_counter!(
-            spin.request_count = 1,
-            trigger_type = "redis",
-            app_id = self.trigger_app.app().id(),
-            component_id = component_id
-        );
-
-        let (instance, mut store) = self
-            .trigger_app
-            .prepare(component_id)?
-            .instantiate(())
-            .await?;
-
-        let guest_indices = inbound_redis::GuestIndices::new_instance(&mut store, &instance)?;
+/// A message to be handled by a component.
+struct Message<'a> {
+    /// The channel the message was published to, or the key of the stream it was
+    /// added to.
+    channel: &'a str,
+    /// The pattern which the channel matched, for messages from pattern subscriptions.
+    pattern: Option<&'a str>,
+    payload: &'a [u8],
+}
+
+/// Invokes a component's `handle-message` export with a message. Components which export
+/// `spin:redis/inbound-redis@3.0.0` receive the message's channel along with its payload.
+async fn dispatch_handler<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<RedisTrigger, F>,
+    component_id: &str,
+    message: &Message<'_>,
+) -> anyhow::Result<()> {
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.request_count = 1,
+        trigger_type = "redis",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+
+    let (instance, mut store) = trigger_app.prepare(component_id)?.instantiate(()).await?;
+
+    if instance
+        .get_export(&mut store, None, INBOUND_REDIS_V3_EXPORT)
+        .is_some()
+    {
+        let guest_indices = inbound_redis3::GuestIndices::new_instance(&mut store, &instance)?;
         let guest = guest_indices.load(&mut store, &instance)?;
+        let message = inbound_redis3::Message {
+            channel: message.channel.to_owned(),
+            pattern: message.pattern.map(ToOwned::to_owned),
+            payload: message.payload.to_vec(),
+        };
+        return match guest.call_handle_message(&mut store, &message).await? {
+            Ok(()) => Ok(()),
+            Err(inbound_redis3::Error::Other(err)) => {
+                anyhow::bail!("Redis handler returned an error: {err}")
+            }
+        };
+    }
 
-        let payload = msg.get_payload_bytes().to_vec();
+    let guest_indices = inbound_redis::GuestIndices::new_instance(&mut store, &instance)?;
+    let guest = guest_indices.load(&mut store, &instance)?;
 
-        guest
-            .call_handle_message(&mut store, &payload)
-            .await?
-            .context("Redis handler returned an error")
-    }
+    guest
+        .call_handle_message(&mut store, &message.payload.to_vec())
+        .await?
+        .context("Redis handler returned an error")
 }
@@ -0,0 +1,164 @@
+//! Reconnecting to Redis servers after losing the connection.
+
+use spin_trigger::{ReconnectPolicy, ShutdownSignal};
+
+/// Tracks the state of a subscriber's connection to a Redis server, reporting it in
+/// logs and metrics.
+pub(crate) struct Connection {
+    server_addr: String,
+    policy: ReconnectPolicy,
+    connected: bool,
+    /// Whether the subscriber has ever connected. Until it has, errors are not retried,
+    /// so that a misconfigured server address fails at startup.
+    ever_connected: bool,
+    /// The number of consecutive failed attempts to reconnect.
+    failures: u32,
+}
+
+impl Connection {
+    pub fn new(server_addr: String, policy: ReconnectPolicy) -> Self {
+        Self {
+            server_addr,
+            policy,
+            connected: false,
+            ever_connected: false,
+            failures: 0,
+        }
+    }
+
+    /// Records that the subscriber has (re)connected and is receiving messages.
+    pub fn connected(&mut self) {
+        if self.failures > 0 {
+            tracing::info!(
+                "Reconnected to Redis server at {} after {} attempts",
+                self.server_addr,
+                self.failures
+            );
+        }
+        self.connected = true;
+        self.ever_connected = true;
+        self.failures = 0;
+        spin_telemetry::metrics::counter!(
+            spin.redis_connections = 1,
+            server_address = self.server_addr.as_str()
+        );
+    }
+
+    /// Records that the connection was lost, or could not be made, and waits before
+    /// the next attempt. Returns `Ok(false)` if shutdown was requested meanwhile, and
+    /// the error if it should not be retried: because the subscriber never connected,
+    /// the error is fatal, or the reconnection policy is exhausted.
+    pub async fn disconnected(
+        &mut self,
+        err: anyhow::Error,
+        shutdown: &ShutdownSignal,
+    ) -> anyhow::Result<bool> {
+        let server_addr = &self.server_addr;
+        if !self.ever_connected {
+            return Err(err);
+        }
+        if is_fatal(&err) {
+            return Err(err.context(format!("not reconnecting to Redis server at {server_addr}")));
+        }
+        if self.connected {
+            self.connected = false;
+            tracing::warn!("Lost connection to Redis server at {server_addr}: {err:#}");
+            spin_telemetry::metrics::counter!(
+                spin.redis_connections = -1,
+                server_address = server_addr.as_str()
+            );
+        } else {
+            tracing::warn!("Failed to connect to Redis server at {server_addr}: {err:#}");
+        }
+
+        self.failures += 1;
+        let Some(delay) = self.policy.delay(self.failures) else {
+            return Err(err.context(format!(
+                "giving up on Redis server at {server_addr} after {} attempts to reconnect",
+                self.failures - 1
+            )));
+        };
+        tracing::info!(
+            "Reconnecting to Redis server at {server_addr} in {} (attempt {})",
+            humantime::format_duration(delay),
+            self.failures
+        );
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.redis_reconnect_attempts = 1,
+            server_address = server_addr.as_str()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => Ok(true),
+            _ = shutdown.requested() => Ok(false),
+        }
+    }
+}
+
+/// Returns whether an error can't be fixed by reconnecting, such as the server
+/// rejecting the subscriber's credentials or permissions.
+fn is_fatal(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<redis::RedisError>())
+        .any(|err| {
+            matches!(
+                err.kind(),
+                redis::ErrorKind::AuthenticationFailed | redis::ErrorKind::InvalidClientConfig
+            ) || matches!(err.code(), Some("NOAUTH" | "WRONGPASS" | "NOPERM"))
+        })
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if self.connected {
+            spin_telemetry::metrics::counter!(
+                spin.redis_connections = -1,
+                server_address = self.server_addr.as_str()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn auth_errors_are_fatal() {
+        let auth_failed = redis::RedisError::from((
+            redis::ErrorKind::AuthenticationFailed,
+            "Password authentication failed",
+        ));
+        assert!(is_fatal(
+            &anyhow::Error::from(auth_failed).context("failed to connect")
+        ));
+        let wrong_pass = redis::make_extension_error("WRONGPASS".into(), None);
+        assert!(is_fatal(&wrong_pass.into()));
+        let no_perm = redis::make_extension_error("NOPERM".into(), None);
+        assert!(is_fatal(&no_perm.into()));
+    }
+
+    #[test]
+    fn connection_errors_are_not_fatal() {
+        let io_error =
+            redis::RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(!is_fatal(&io_error.into()));
+        assert!(!is_fatal(&anyhow::anyhow!("disconnected")));
+    }
+
+    #[tokio::test]
+    async fn errors_before_connecting_are_not_retried() {
+        let policy = ReconnectPolicy {
+            max_delay: Duration::from_secs(30),
+            max_attempts: 10,
+        };
+        let mut connection = Connection::new("redis://localhost".into(), policy);
+        let shutdown = ShutdownSignal::new();
+        let err = connection
+            .disconnected(anyhow::anyhow!("connection refused"), &shutdown)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "connection refused");
+    }
+}
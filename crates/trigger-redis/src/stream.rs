@@ -0,0 +1,507 @@
+//! Consuming Redis Streams with consumer groups.
+//!
+//! Unlike pub/sub, entries added to a stream while no instance is consuming it are
+//! delivered once one starts, and an entry is only acknowledged (`XACK`) once its
+//! handler succeeds. Entries which stay unacknowledged for longer than the visibility
+//! timeout, because their handler failed or their consumer went away, are claimed
+//! and delivered again; after too many deliveries they are moved to a dead-letter
+//! stream instead.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use redis::{
+    aio::MultiplexedConnection,
+    streams::{
+        StreamClaimReply, StreamId, StreamPendingCountReply, StreamReadOptions, StreamReadReply,
+    },
+    AsyncCommands, Client,
+};
+use serde::Deserialize;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{ShutdownSignal, TriggerApp};
+use tracing::{instrument, Level};
+
+use crate::{
+    dispatch_handler,
+    reconnect::{Connection, ReconnectPolicy},
+    Message, RedisTrigger,
+};
+
+/// The default time an entry may go unacknowledged before it is delivered again.
+const DEFAULT_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(30);
+/// The longest a read waits for new entries, so that pending entries are reclaimed
+/// promptly even when no new entries arrive.
+const MAX_BLOCK: Duration = Duration::from_secs(5);
+/// The number of entries read or reclaimed at a time.
+const BATCH_SIZE: usize = 100;
+
+/// Redis Streams consumer configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StreamConfig {
+    /// Key of the stream to consume
+    pub key: String,
+    /// Consumer group to read as. The group is created, if it does not exist, to
+    /// read the entries added from then on.
+    pub group: String,
+    /// Name of this consumer within the group. Defaults to `spin-<hostname>`, which
+    /// stays the same across restarts so that consumers don't pile up in the group.
+    /// Instances on the same host share the default name, and so their pending entries.
+    pub consumer: Option<String>,
+    /// Field of each entry holding the message payload. Defaults to `payload`.
+    pub field: Option<String>,
+    /// How long a delivered entry may go unacknowledged before it is delivered again,
+    /// such as `"30s"`. Defaults to 30 seconds.
+    pub visibility_timeout: Option<String>,
+    /// The number of deliveries after which an unacknowledged entry is moved to the
+    /// dead-letter stream. If omitted, entries are delivered until they are handled.
+    pub max_deliveries: Option<usize>,
+    /// Key of the stream to which dead-lettered entries are added. Defaults to the
+    /// key of the consumed stream with a `:dead-letter` suffix.
+    pub dead_letter_stream: Option<String>,
+}
+
+/// A stream consumer's options, with defaults applied.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct StreamOptions {
+    pub key: String,
+    pub group: String,
+    pub consumer: String,
+    pub field: String,
+    pub visibility_timeout: Duration,
+    pub max_deliveries: Option<usize>,
+    pub dead_letter_stream: String,
+}
+
+impl StreamOptions {
+    /// Applies defaults to a config whose stream key has been resolved to `key`.
+    pub fn new(config: &StreamConfig, key: String) -> anyhow::Result<Self> {
+        let visibility_timeout = match &config.visibility_timeout {
+            Some(timeout) => humantime::parse_duration(timeout)
+                .with_context(|| format!("invalid stream visibility_timeout {timeout:?}"))?,
+            None => DEFAULT_VISIBILITY_TIMEOUT,
+        };
+        anyhow::ensure!(
+            !visibility_timeout.is_zero(),
+            "stream visibility_timeout must be greater than zero"
+        );
+        anyhow::ensure!(
+            config.max_deliveries != Some(0),
+            "stream max_deliveries must be at least 1"
+        );
+        let dead_letter_stream = config
+            .dead_letter_stream
+            .clone()
+            .unwrap_or_else(|| format!("{key}:dead-letter"));
+        anyhow::ensure!(
+            dead_letter_stream != key,
+            "stream dead_letter_stream must differ from the consumed stream {key:?}"
+        );
+        Ok(Self {
+            key,
+            group: config.group.clone(),
+            consumer: config
+                .consumer
+                .clone()
+                .unwrap_or_else(default_consumer_name),
+            field: config.field.clone().unwrap_or_else(|| "payload".into()),
+            visibility_timeout,
+            max_deliveries: config.max_deliveries,
+            dead_letter_stream,
+        })
+    }
+
+    /// The time a read waits for new entries.
+    fn block_time(&self) -> Duration {
+        self.visibility_timeout.min(MAX_BLOCK)
+    }
+
+    /// Whether an entry delivered this many times should be dead-lettered rather than
+    /// delivered again.
+    fn is_exhausted(&self, times_delivered: usize) -> bool {
+        self.max_deliveries
+            .is_some_and(|max_deliveries| times_delivered >= max_deliveries)
+    }
+}
+
+/// The name of a consumer which doesn't configure one.
+fn default_consumer_name() -> String {
+    match whoami::fallible::hostname() {
+        Ok(hostname) => format!("spin-{hostname}"),
+        Err(err) => {
+            tracing::warn!("Failed to get hostname for the default stream consumer name: {err}");
+            "spin".into()
+        }
+    }
+}
+
+/// Consumes a single stream as a member of a consumer group, invoking one component.
+pub(crate) struct StreamConsumer<F: RuntimeFactors> {
+    client: Client,
+    trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
+    component_id: String,
+    options: StreamOptions,
+    reconnect: ReconnectPolicy,
+}
+
+impl<F: RuntimeFactors> StreamConsumer<F> {
+    pub fn new(
+        address: String,
+        trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
+        component_id: String,
+        options: StreamOptions,
+        reconnect: ReconnectPolicy,
+    ) -> anyhow::Result<Self> {
+        let client = Client::open(address)?;
+        Ok(Self {
+            client,
+            trigger_app,
+            component_id,
+            options,
+            reconnect,
+        })
+    }
+
+    pub async fn run(self, shutdown: ShutdownSignal) -> anyhow::Result<()> {
+        let server_addr = self.client.get_connection_info().addr.to_string();
+        let mut connection = Connection::new(server_addr, self.reconnect);
+        let mut first_connection = true;
+        loop {
+            let err = match self
+                .consume(&mut connection, &shutdown, first_connection)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            first_connection = false;
+            if !connection.disconnected(err, &shutdown).await? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handles entries until shutdown is requested, or the connection fails.
+    async fn consume(
+        &self,
+        connection: &mut Connection,
+        shutdown: &ShutdownSignal,
+        first_connection: bool,
+    ) -> anyhow::Result<()> {
+        let server_addr = &self.client.get_connection_info().addr;
+        let StreamOptions {
+            key,
+            group,
+            consumer,
+            ..
+        } = &self.options;
+
+        tracing::info!("Connecting to Redis server at {server_addr}");
+        let mut conn = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .with_context(|| format!("Redis trigger failed to connect to {server_addr}"))?;
+
+        self.create_group(&mut conn).await.with_context(|| {
+            format!("Redis trigger failed to create consumer group {group:?} for stream {key:?} on {server_addr}")
+        })?;
+        if first_connection {
+            println!(
+                "Consuming stream {server_addr}/{key} as {group}/{consumer}: [{}]",
+                self.component_id
+            );
+        }
+        connection.connected();
+
+        let mut next_reclaim = Instant::now();
+        loop {
+            if Instant::now() >= next_reclaim {
+                self.reclaim(&mut conn).await.with_context(|| {
+                    format!("Redis trigger failed to reclaim pending entries of stream {key:?} on {server_addr}")
+                })?;
+                next_reclaim = Instant::now() + self.options.visibility_timeout;
+            }
+
+            // Entries read by a cancelled read stay pending, so they are reclaimed and
+            // delivered again once their visibility timeout has passed.
+            let entries = tokio::select! {
+                entries = self.read_new(&mut conn) => entries.with_context(|| {
+                    format!("Redis trigger failed to read stream {key:?} on {server_addr}")
+                })?,
+                _ = shutdown.requested() => {
+                    tracing::info!("Stopping consumer of stream {key:?} on {server_addr}");
+                    return Ok(());
+                }
+            };
+            // Entries are handled to completion even if shutdown is requested meanwhile
+            for entry in entries {
+                self.process_entry(&mut conn, entry, 1).await?;
+            }
+        }
+    }
+
+    async fn create_group(&self, conn: &mut MultiplexedConnection) -> redis::RedisResult<()> {
+        let res: redis::RedisResult<()> = conn
+            .xgroup_create_mkstream(&self.options.key, &self.options.group, "$")
+            .await;
+        match res {
+            Err(err) if err.code() == Some("BUSYGROUP") => Ok(()),
+            res => res,
+        }
+    }
+
+    /// Reads entries which have not yet been delivered to any consumer in the group.
+    async fn read_new(
+        &self,
+        conn: &mut MultiplexedConnection,
+    ) -> redis::RedisResult<Vec<StreamId>> {
+        let options = StreamReadOptions::default()
+            .group(&self.options.group, &self.options.consumer)
+            .count(BATCH_SIZE)
+            .block(self.options.block_time().as_millis() as usize);
+        let reply: Option<StreamReadReply> = conn
+            .xread_options(&[&self.options.key], &[">"], &options)
+            .await?;
+        Ok(reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .collect())
+    }
+
+    /// Claims entries which have gone unacknowledged for longer than the visibility
+    /// timeout, and either delivers them again or dead-letters them.
+    async fn reclaim(&self, conn: &mut MultiplexedConnection) -> anyhow::Result<()> {
+        let min_idle = self.options.visibility_timeout.as_millis() as usize;
+        let mut start = "-".to_owned();
+        loop {
+            let pending: StreamPendingCountReply = conn
+                .xpending_count(
+                    &self.options.key,
+                    &self.options.group,
+                    &start,
+                    "+",
+                    BATCH_SIZE,
+                )
+                .await?;
+            let Some(last) = pending.ids.last() else {
+                return Ok(());
+            };
+            start = format!("({}", last.id);
+            let more = pending.ids.len() == BATCH_SIZE;
+
+            let idle = pending
+                .ids
+                .into_iter()
+                .filter(|pending| pending.last_delivered_ms >= min_idle)
+                .map(|pending| (pending.id, pending.times_delivered))
+                .collect::<Vec<_>>();
+            if !idle.is_empty() {
+                // Claiming only entries which are still idle ensures that an entry
+                // claimed by another consumer meanwhile is not delivered twice.
+                let ids = idle.iter().map(|(id, _)| id).collect::<Vec<_>>();
+                let claimed: StreamClaimReply = conn
+                    .xclaim(
+                        &self.options.key,
+                        &self.options.group,
+                        &self.options.consumer,
+                        min_idle,
+                        &ids,
+                    )
+                    .await?;
+                for entry in claimed.ids {
+                    let times_delivered = idle
+                        .iter()
+                        .find(|(id, _)| *id == entry.id)
+                        .map_or(0, |(_, times_delivered)| *times_delivered);
+                    if self.options.is_exhausted(times_delivered) {
+                        self.dead_letter(conn, &entry, times_delivered).await?;
+                    } else {
+                        self.process_entry(conn, entry, times_delivered + 1).await?;
+                    }
+                }
+            }
+
+            if !more {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Invokes the component for an entry, acknowledging the entry if it succeeds.
+    /// An entry whose handler fails is left pending, to be delivered again. Errors
+    /// are only returned if the acknowledgement fails.
+    async fn process_entry(
+        &self,
+        conn: &mut MultiplexedConnection,
+        entry: StreamId,
+        delivery: usize,
+    ) -> anyhow::Result<()> {
+        let Some(payload) = entry.get::<Vec<u8>>(&self.options.field) else {
+            tracing::error!(
+                "Entry {} of stream {:?} has no {:?} field",
+                entry.id,
+                self.options.key,
+                self.options.field
+            );
+            // An entry without a payload will never be handled, so don't redeliver it
+            return self.dead_letter(conn, &entry, delivery).await;
+        };
+        if self
+            .handle_entry(&entry.id, &payload, delivery)
+            .await
+            .is_ok()
+        {
+            let _: usize = conn
+                .xack(&self.options.key, &self.options.group, &[&entry.id])
+                .await
+                .with_context(|| format!("failed to acknowledge stream entry {}", entry.id))?;
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "spin_trigger_redis.handle_stream_entry", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{} receive", self.options.key),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.system = "redis",
+        messaging.message.id = id,
+        messaging.redis.delivery_count = delivery,
+    ))]
+    async fn handle_entry(&self, id: &str, payload: &[u8], delivery: usize) -> anyhow::Result<()> {
+        let component_id = &self.component_id;
+        tracing::trace!("Executing Redis component {component_id}");
+        let message = Message {
+            channel: &self.options.key,
+            pattern: None,
+            payload,
+        };
+        dispatch_handler(&self.trigger_app, component_id, &message)
+            .await
+            .inspect_err(|err| {
+                tracing::info!(
+                    "Component {component_id} handler failed on delivery {delivery} of stream entry {id}: {err}"
+                );
+            })
+    }
+
+    /// Adds an entry to the dead-letter stream and acknowledges it, so that it is not
+    /// delivered again.
+    async fn dead_letter(
+        &self,
+        conn: &mut MultiplexedConnection,
+        entry: &StreamId,
+        times_delivered: usize,
+    ) -> anyhow::Result<()> {
+        let StreamOptions {
+            key,
+            group,
+            dead_letter_stream,
+            ..
+        } = &self.options;
+        tracing::warn!(
+            "Moving entry {} of stream {key:?} to dead-letter stream {dead_letter_stream:?} after {times_delivered} deliveries",
+            entry.id
+        );
+
+        let mut fields = entry
+            .map
+            .iter()
+            .map(|(field, value)| Ok((field.clone(), redis::from_redis_value(value)?)))
+            .collect::<redis::RedisResult<Vec<(String, Vec<u8>)>>>()?;
+        fields.push(("spin-source-stream".into(), key.clone().into_bytes()));
+        fields.push(("spin-source-id".into(), entry.id.clone().into_bytes()));
+        fields.push((
+            "spin-delivery-count".into(),
+            times_delivered.to_string().into_bytes(),
+        ));
+
+        let () = redis::pipe()
+            .atomic()
+            .xadd(dead_letter_stream, "*", &fields)
+            .ignore()
+            .xack(key, group, &[&entry.id])
+            .ignore()
+            .query_async(conn)
+            .await
+            .with_context(|| format!("failed to dead-letter stream entry {}", entry.id))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StreamConfig {
+        StreamConfig {
+            key: "orders".into(),
+            group: "spin".into(),
+            consumer: None,
+            field: None,
+            visibility_timeout: None,
+            max_deliveries: None,
+            dead_letter_stream: None,
+        }
+    }
+
+    #[test]
+    fn options_apply_defaults() {
+        let options = StreamOptions::new(&config(), "orders".into()).unwrap();
+        assert_eq!(options.field, "payload");
+        assert_eq!(options.visibility_timeout, DEFAULT_VISIBILITY_TIMEOUT);
+        assert_eq!(options.dead_letter_stream, "orders:dead-letter");
+        assert!(options.consumer.starts_with("spin"));
+        assert_eq!(
+            StreamOptions::new(&config(), "orders".into())
+                .unwrap()
+                .consumer,
+            options.consumer
+        );
+        assert_eq!(options.block_time(), MAX_BLOCK);
+        assert!(!options.is_exhausted(1000));
+    }
+
+    #[test]
+    fn options_are_validated() {
+        let options = StreamOptions::new(
+            &StreamConfig {
+                visibility_timeout: Some("1500ms".into()),
+                max_deliveries: Some(3),
+                ..config()
+            },
+            "orders".into(),
+        )
+        .unwrap();
+        assert_eq!(options.visibility_timeout, Duration::from_millis(1500));
+        assert_eq!(options.block_time(), Duration::from_millis(1500));
+        assert!(!options.is_exhausted(2));
+        assert!(options.is_exhausted(3));
+
+        for invalid in [
+            StreamConfig {
+                visibility_timeout: Some("soon".into()),
+                ..config()
+            },
+            StreamConfig {
+                visibility_timeout: Some("0s".into()),
+                ..config()
+            },
+            StreamConfig {
+                max_deliveries: Some(0),
+                ..config()
+            },
+            StreamConfig {
+                dead_letter_stream: Some("orders".into()),
+                ..config()
+            },
+        ] {
+            assert!(StreamOptions::new(&invalid, "orders".into()).is_err());
+        }
+    }
+}
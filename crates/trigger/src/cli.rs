@@ -1,10 +1,12 @@
 mod initial_kv_setter;
 mod launch_metadata;
+mod sqlite_migrations;
 mod sqlite_statements;
 mod stdio;
 mod summary;
 
 use std::path::PathBuf;
+use std::time::Duration;
 use std::{future::Future, sync::Arc};
 
 use anyhow::{Context, Result};
@@ -16,12 +18,13 @@ use spin_common::url::parse_file_url;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::{ComponentLoader, FactorsExecutor};
 
-use crate::{loader::ComponentLoader as ComponentLoaderImpl, Trigger, TriggerApp};
+use crate::{loader::ComponentLoader as ComponentLoaderImpl, ShutdownSignal, Trigger, TriggerApp};
 pub use initial_kv_setter::InitialKvSetterHook;
 pub use launch_metadata::LaunchMetadata;
+pub use sqlite_migrations::SqliteMigrationsExecutorHook;
 pub use sqlite_statements::SqlStatementExecutorHook;
 use stdio::FollowComponents;
-pub use stdio::StdioLoggingExecutorHooks;
+pub use stdio::{LogFormat, StdioLoggingExecutorHooks};
 pub use summary::{KeyValueDefaultStoreSummaryHook, SqliteDefaultStoreSummaryHook};
 
 pub const APP_LOG_DIR: &str = "APP_LOG_DIR";
@@ -92,6 +95,11 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
         )]
     pub silence_component_logs: bool,
 
+    /// Format of component stdout/stderr logs: `text`, or `json` to log each line as a
+    /// JSON object tagged with the component, trigger, route and trace that produced it.
+    #[clap(long = "log-format", env = "SPIN_LOG_FORMAT", default_value = "text")]
+    pub log_format: LogFormat,
+
     /// Configuration file for config providers and wasmtime config.
     #[clap(
         name = RUNTIME_CONFIG_FILE,
@@ -100,6 +108,16 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     )]
     pub runtime_config_file: Option<PathBuf>,
 
+    /// Maximum time, in seconds, to wait for in-flight work to finish after a
+    /// shutdown is requested (e.g. by SIGTERM or Ctrl+C). Any work still in
+    /// flight after this time is abandoned.
+    #[clap(
+        long = "shutdown-timeout",
+        env = "SPIN_SHUTDOWN_TIMEOUT",
+        default_value = "30"
+    )]
+    pub shutdown_timeout_secs: u64,
+
     /// Set the application state directory path. This is used in the default
     /// locations for logs, key value stores, etc.
     ///
@@ -137,6 +155,10 @@ pub struct FactorsConfig {
     pub follow_components: FollowComponents,
     /// Log directory for component stdout/stderr.
     pub log_dir: UserProvidedPath,
+    /// Format of component stdout/stderr logs.
+    pub log_format: LogFormat,
+    /// The type of the trigger running the app.
+    pub trigger_type: String,
 }
 
 /// An empty implementation of clap::Args to be used as TriggerExecutor::RunConfig
@@ -218,30 +240,51 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
             local_app_dir: local_app_dir.clone(),
             follow_components,
             log_dir,
+            log_format: self.log_format,
+            trigger_type: T::TYPE.to_owned(),
         };
 
+        let shutdown = ShutdownSignal::new();
         let run_fut = builder
             .run(
                 app,
                 common_options,
                 self.builder_args,
                 &ComponentLoaderImpl::new(),
+                shutdown.clone(),
             )
             .await?;
 
-        let (abortable, abort_handle) = futures::future::abortable(run_fut);
-        ctrlc::set_handler(move || abort_handle.abort())?;
-        match abortable.await {
-            Ok(Ok(())) => {
-                tracing::info!("Trigger executor shut down: exiting");
-                Ok(())
-            }
-            Ok(Err(err)) => {
-                tracing::error!("Trigger executor failed");
-                Err(err)
-            }
-            Err(_aborted) => {
-                tracing::info!("User requested shutdown: exiting");
+        ctrlc::set_handler({
+            let shutdown = shutdown.clone();
+            move || shutdown.request()
+        })?;
+
+        // Once shutdown is requested, in-flight work is given until the timeout to finish.
+        let shutdown_timeout = Duration::from_secs(self.shutdown_timeout_secs);
+        let shutdown_deadline = async {
+            shutdown.requested().await;
+            tracing::info!("User requested shutdown: waiting up to {shutdown_timeout:?} for in-flight work to finish");
+            tokio::time::sleep(shutdown_timeout).await;
+        };
+
+        tokio::select! {
+            res = run_fut => match res {
+                Ok(()) if shutdown.is_requested() => {
+                    tracing::info!("User requested shutdown: exiting");
+                    Ok(())
+                }
+                Ok(()) => {
+                    tracing::info!("Trigger executor shut down: exiting");
+                    Ok(())
+                }
+                Err(err) => {
+                    tracing::error!("Trigger executor failed");
+                    Err(err)
+                }
+            },
+            _ = shutdown_deadline => {
+                tracing::warn!("Shutdown timeout expired with work still in flight: exiting");
                 Ok(())
             }
         }
@@ -334,16 +377,18 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> TriggerAppBuilder<T, B> {
         Ok(configured_app)
     }
 
-    /// Run the [`TriggerApp`] with the given [`App`] and options.
+    /// Run the [`TriggerApp`] with the given [`App`] and options, until the
+    /// given [`ShutdownSignal`] is requested.
     pub async fn run(
         mut self,
         app: App,
         common_options: FactorsConfig,
         options: B::CliArgs,
         loader: &impl ComponentLoader,
+        shutdown: ShutdownSignal,
     ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
         let configured_app = self.build(app, common_options, options, loader).await?;
-        Ok(self.trigger.run(configured_app))
+        Ok(self.trigger.run_with_shutdown(configured_app, shutdown))
     }
 }
 
@@ -0,0 +1,35 @@
+use spin_core::async_trait;
+use spin_factor_sqlite::SqliteFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::ExecutorHooks;
+
+/// ExecutorHook for applying the migrations components declare for their sqlite databases.
+///
+/// Pending migrations are applied before any sqlite statements, so the statements can rely on
+/// the schema. It will silently ignore the hook if the app does not have access to `SqliteFactor`.
+pub struct SqliteMigrationsExecutorHook;
+
+#[async_trait]
+impl<F, U> ExecutorHooks<F, U> for SqliteMigrationsExecutorHook
+where
+    F: RuntimeFactors,
+{
+    async fn configure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        let Ok(sqlite) = configured_app.app_state::<SqliteFactor>() else {
+            return Ok(());
+        };
+        let reports =
+            spin_factor_sqlite::migrations::migrate(sqlite, configured_app.app(), false).await?;
+        for report in reports.iter().filter(|r| !r.applied.is_empty()) {
+            println!(
+                "Applied {} migration(s) to SQLite database '{}'.",
+                report.applied.len(),
+                report.label
+            );
+        }
+        Ok(())
+    }
+}
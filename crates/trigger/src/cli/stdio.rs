@@ -1,10 +1,12 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::OnceLock,
     task::Poll,
 };
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use spin_common::ui::quoted_path;
 use spin_core::async_trait;
 use spin_factor_wasi::WasiFactor;
@@ -35,10 +37,37 @@ impl FollowComponents {
     }
 }
 
+/// The format in which component stdout/stderr is logged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Output is logged as written.
+    #[default]
+    Text,
+    /// Each line of output is logged as a JSON object, tagged with the component,
+    /// trigger, route and trace that produced it.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => anyhow::bail!("unknown log format {s:?}: expected 'text' or 'json'"),
+        }
+    }
+}
+
 /// Implements TriggerHooks, writing logs to a log file and (optionally) stderr
 pub struct StdioLoggingExecutorHooks {
     follow_components: FollowComponents,
     log_dir: Option<PathBuf>,
+    /// If set, logs are structured, tagged with this trigger type.
+    structured_trigger_type: Option<String>,
+    /// Component ID -> the route of the component, for components serving a single route.
+    routes: OnceLock<HashMap<String, String>>,
 }
 
 impl StdioLoggingExecutorHooks {
@@ -46,9 +75,18 @@ impl StdioLoggingExecutorHooks {
         Self {
             follow_components,
             log_dir,
+            structured_trigger_type: None,
+            routes: OnceLock::new(),
         }
     }
 
+    /// Log each line of component output as a JSON object, tagged with the given trigger
+    /// type. See [`LogFormat::Json`].
+    pub fn with_json_logs(mut self, trigger_type: impl Into<String>) -> Self {
+        self.structured_trigger_type = Some(trigger_type.into());
+        self
+    }
+
     fn component_stdio_writer(
         &self,
         component_id: &str,
@@ -61,11 +99,55 @@ impl StdioLoggingExecutorHooks {
         let log_path = log_path.as_deref();
 
         let follow = self.follow_components.should_follow(component_id);
-        match log_path {
+        let writer = match log_path {
             Some(log_path) => ComponentStdioWriter::new_forward(log_path, follow)
-                .with_context(|| format!("Failed to open log file {}", quoted_path(log_path))),
-            None => ComponentStdioWriter::new_inherit(),
+                .with_context(|| format!("Failed to open log file {}", quoted_path(log_path)))?,
+            None => ComponentStdioWriter::new_inherit()?,
+        };
+        Ok(match &self.structured_trigger_type {
+            Some(trigger_type) => writer.with_json_lines(JsonLines::new(
+                LogSource {
+                    component_id: component_id.to_owned(),
+                    trigger_type: trigger_type.clone(),
+                    route: self
+                        .routes
+                        .get()
+                        .and_then(|routes| routes.get(component_id))
+                        .cloned(),
+                },
+                log_suffix,
+            )),
+            None => writer,
+        })
+    }
+
+    /// Finds the route of each component which serves a single route.
+    fn single_routes(app: &spin_app::App, trigger_type: &str) -> HashMap<String, String> {
+        #[derive(Deserialize)]
+        struct RouteConfig {
+            component: Option<String>,
+            route: Option<String>,
+        }
+        let mut routes: HashMap<String, HashSet<String>> = HashMap::new();
+        for trigger in app.triggers_with_type(trigger_type) {
+            if let Ok(RouteConfig {
+                component: Some(component),
+                route: Some(route),
+            }) = trigger.typed_config()
+            {
+                routes.entry(component).or_default().insert(route);
+            }
         }
+        routes
+            .into_iter()
+            .filter_map(|(component, routes)| {
+                let mut routes = routes.into_iter();
+                match (routes.next(), routes.next()) {
+                    (Some(route), None) => Some((component, route)),
+                    _ => None,
+                }
+            })
+            .collect()
     }
 
     fn validate_follows(&self, app: &spin_app::App) -> anyhow::Result<()> {
@@ -95,6 +177,10 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         configured_app: &spin_factors::ConfiguredApp<F>,
     ) -> anyhow::Result<()> {
         self.validate_follows(configured_app.app())?;
+        if let Some(trigger_type) = &self.structured_trigger_type {
+            let routes = Self::single_routes(configured_app.app(), trigger_type);
+            let _ = self.routes.set(routes);
+        }
         if let Some(dir) = &self.log_dir {
             // Ensure log dir exists if set
             std::fs::create_dir_all(dir)
@@ -131,6 +217,18 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
 /// tracing compatibility layer.
 pub struct ComponentStdioWriter {
     inner: ComponentStdioWriterInner,
+    /// If set, output is logged as JSON lines.
+    json: Option<JsonLines>,
+    /// JSON lines waiting to be written.
+    pending: PendingLines,
+}
+
+/// Formatted JSON lines, and how much of them has been written to each destination.
+#[derive(Default)]
+struct PendingLines {
+    lines: Vec<u8>,
+    written_to_file: usize,
+    written_to_stderr: usize,
 }
 
 enum ComponentStdioWriterInner {
@@ -170,14 +268,96 @@ impl ComponentStdioWriter {
                 state: ComponentStdioWriterState::File,
                 follow,
             },
+            json: None,
+            pending: Default::default(),
         })
     }
 
     fn new_inherit() -> anyhow::Result<Self> {
         Ok(Self {
             inner: ComponentStdioWriterInner::Inherit,
+            json: None,
+            pending: Default::default(),
         })
     }
+
+    fn with_json_lines(mut self, json: JsonLines) -> Self {
+        self.json = Some(json);
+        self
+    }
+
+    /// Writes the pending JSON lines to the log file and/or stderr.
+    fn poll_write_pending(&mut self, cx: &mut std::task::Context<'_>) -> Poll<std::io::Result<()>> {
+        let pending = &mut self.pending;
+        let (file, follow) = match &mut self.inner {
+            ComponentStdioWriterInner::Inherit => (None, true),
+            ComponentStdioWriterInner::Forward {
+                async_file, follow, ..
+            } => (Some(async_file), *follow),
+        };
+        if let Some(file) = file {
+            while pending.written_to_file < pending.lines.len() {
+                let unwritten = &pending.lines[pending.written_to_file..];
+                let written =
+                    futures::ready!(std::pin::Pin::new(&mut *file).poll_write(cx, unwritten))?;
+                if written == 0 {
+                    return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+                }
+                pending.written_to_file += written;
+            }
+        }
+        if follow {
+            while pending.written_to_stderr < pending.lines.len() {
+                let unwritten = &pending.lines[pending.written_to_stderr..];
+                let written = futures::ready!(
+                    std::pin::Pin::new(&mut tokio::io::stderr()).poll_write(cx, unwritten)
+                )?;
+                if written == 0 {
+                    return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+                }
+                pending.written_to_stderr += written;
+            }
+        }
+        *pending = Default::default();
+        Poll::Ready(Ok(()))
+    }
+
+    /// Writes the rest of the pending JSON lines to the synchronous handles.
+    fn write_pending(&mut self) -> std::io::Result<()> {
+        use std::io::Write;
+        let pending = std::mem::take(&mut self.pending);
+        match &mut self.inner {
+            ComponentStdioWriterInner::Inherit => {
+                std::io::stderr().write_all(&pending.lines[pending.written_to_stderr..])
+            }
+            ComponentStdioWriterInner::Forward {
+                sync_file, follow, ..
+            } => {
+                sync_file.write_all(&pending.lines[pending.written_to_file..])?;
+                if *follow {
+                    std::io::stderr().write_all(&pending.lines[pending.written_to_stderr..])?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes already formatted output to the log file and/or stderr.
+    fn write_formatted(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        use std::io::Write;
+        match &mut self.inner {
+            ComponentStdioWriterInner::Inherit => std::io::stderr().write_all(buf),
+            ComponentStdioWriterInner::Forward {
+                sync_file, follow, ..
+            } => {
+                sync_file.write_all(buf)?;
+                if *follow {
+                    std::io::stderr().write_all(buf)?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl AsyncWrite for ComponentStdioWriter {
@@ -188,6 +368,18 @@ impl AsyncWrite for ComponentStdioWriter {
     ) -> Poll<std::result::Result<usize, std::io::Error>> {
         let this = self.get_mut();
 
+        if this.json.is_some() {
+            // Complete lines are formatted and written through the async handles; output is
+            // only accepted once the lines before it have been written.
+            futures::ready!(this.poll_write_pending(cx))?;
+            this.pending.lines = this.json.as_mut().unwrap().push(buf);
+            // The lines are written by the next write or flush if they can't be written now.
+            if let Poll::Ready(Err(e)) = this.poll_write_pending(cx) {
+                return Poll::Ready(Err(e));
+            }
+            return Poll::Ready(Ok(buf.len()));
+        }
+
         loop {
             match &mut this.inner {
                 ComponentStdioWriterInner::Inherit => {
@@ -246,6 +438,7 @@ impl AsyncWrite for ComponentStdioWriter {
         cx: &mut std::task::Context<'_>,
     ) -> Poll<std::result::Result<(), std::io::Error>> {
         let this = self.get_mut();
+        futures::ready!(this.poll_write_pending(cx))?;
 
         match &mut this.inner {
             ComponentStdioWriterInner::Inherit => {
@@ -267,6 +460,7 @@ impl AsyncWrite for ComponentStdioWriter {
         cx: &mut std::task::Context<'_>,
     ) -> Poll<std::result::Result<(), std::io::Error>> {
         let this = self.get_mut();
+        futures::ready!(this.poll_write_pending(cx))?;
 
         match &mut this.inner {
             ComponentStdioWriterInner::Inherit => {
@@ -286,6 +480,15 @@ impl AsyncWrite for ComponentStdioWriter {
 
 impl std::io::Write for ComponentStdioWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(json) = &mut self.json {
+            let lines = json.push(buf);
+            self.write_pending()?;
+            if !lines.is_empty() {
+                self.write_formatted(&lines)?;
+            }
+            return Ok(buf.len());
+        }
+
         spin_telemetry::logs::handle_app_log(buf);
 
         match &mut self.inner {
@@ -321,6 +524,107 @@ impl std::io::Write for ComponentStdioWriter {
     }
 }
 
+impl Drop for ComponentStdioWriter {
+    fn drop(&mut self) {
+        // Log any lines which weren't written before the component finished, and any final
+        // line which it didn't terminate.
+        let _ = self.write_pending();
+        if let Some(lines) = self.json.as_mut().and_then(JsonLines::finish) {
+            let _ = self.write_formatted(&lines);
+        }
+    }
+}
+
+/// Where component output comes from, for tagging structured logs.
+struct LogSource {
+    component_id: String,
+    trigger_type: String,
+    route: Option<String>,
+}
+
+/// Splits component output into lines and formats them as JSON objects.
+struct JsonLines {
+    source: LogSource,
+    stream: String,
+    /// Output following the last complete line.
+    partial: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct JsonLogLine<'a> {
+    timestamp: String,
+    component_id: &'a str,
+    trigger_type: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    route: Option<&'a str>,
+    stream: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span_id: Option<String>,
+    message: &'a str,
+}
+
+impl JsonLines {
+    fn new(source: LogSource, stream: &str) -> Self {
+        Self {
+            source,
+            stream: stream.to_owned(),
+            partial: Vec::new(),
+        }
+    }
+
+    /// Adds output, returning the formatted JSON lines for any lines it completes.
+    fn push(&mut self, buf: &[u8]) -> Vec<u8> {
+        self.partial.extend_from_slice(buf);
+        let Some(end) = self.partial.iter().rposition(|b| *b == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        complete[..end]
+            .split(|b| *b == b'\n')
+            .flat_map(|line| self.format(line))
+            .collect()
+    }
+
+    /// Formats any remaining unterminated line.
+    fn finish(&mut self) -> Option<Vec<u8>> {
+        if self.partial.is_empty() {
+            return None;
+        }
+        let line = std::mem::take(&mut self.partial);
+        Some(self.format(&line))
+    }
+
+    fn format(&self, line: &[u8]) -> Vec<u8> {
+        let message = String::from_utf8_lossy(line);
+        let message = message.strip_suffix('\r').unwrap_or(&message);
+        let context = spin_telemetry::logs::AppLogContext {
+            component_id: &self.source.component_id,
+            trigger_type: &self.source.trigger_type,
+            route: self.source.route.as_deref(),
+            stream: &self.stream,
+        };
+        spin_telemetry::logs::handle_structured_app_log(message, &context);
+
+        let (trace_id, span_id) = spin_telemetry::logs::current_trace_ids().unzip();
+        let line = JsonLogLine {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            component_id: context.component_id,
+            trigger_type: context.trigger_type,
+            route: context.route,
+            stream: context.stream,
+            trace_id,
+            span_id,
+            message,
+        };
+        let mut json = serde_json::to_vec(&line).expect("log line should serialize");
+        json.push(b'\n');
+        json
+    }
+}
+
 fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> String {
     items
         .into_iter()
@@ -328,3 +632,71 @@ fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> Stri
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_lines() -> JsonLines {
+        JsonLines::new(
+            LogSource {
+                component_id: "hello".into(),
+                trigger_type: "http".into(),
+                route: Some("/hello".into()),
+            },
+            "stdout",
+        )
+    }
+
+    fn parse(lines: &[u8]) -> Vec<serde_json::Value> {
+        serde_json::Deserializer::from_slice(lines)
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn json_lines_are_split_and_tagged() {
+        let mut json = json_lines();
+        assert!(json.push(b"partial").is_empty());
+
+        let lines = parse(&json.push(b" line\r\nsecond\nthird"));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["message"], "partial line");
+        assert_eq!(lines[0]["component_id"], "hello");
+        assert_eq!(lines[0]["trigger_type"], "http");
+        assert_eq!(lines[0]["route"], "/hello");
+        assert_eq!(lines[0]["stream"], "stdout");
+        assert_eq!(lines[1]["message"], "second");
+
+        let rest = parse(&json.finish().unwrap());
+        assert_eq!(rest[0]["message"], "third");
+        assert!(json.finish().is_none());
+    }
+
+    #[tokio::test]
+    async fn json_lines_are_written_asynchronously() -> anyhow::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("hello_stdout.txt");
+        let mut writer =
+            ComponentStdioWriter::new_forward(&log_path, false)?.with_json_lines(json_lines());
+        writer.write_all(b"first\nsec").await?;
+        writer.write_all(b"ond\n").await?;
+        writer.flush().await?;
+
+        let lines = parse(&std::fs::read(&log_path)?);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["message"], "first");
+        assert_eq!(lines[1]["message"], "second");
+        Ok(())
+    }
+
+    #[test]
+    fn log_format_parses() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("text".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
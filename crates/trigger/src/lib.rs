@@ -1,5 +1,7 @@
 pub mod cli;
 pub mod loader;
+mod reconnect;
+mod shutdown;
 
 use std::future::Future;
 
@@ -8,6 +10,8 @@ use spin_core::Linker;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::{FactorsExecutorApp, FactorsInstanceBuilder};
 
+pub use reconnect::ReconnectPolicy;
+pub use shutdown::ShutdownSignal;
 pub use spin_app::App;
 
 /// Type alias for a [`spin_factors_executor::FactorsExecutorApp`] specialized to a [`Trigger`].
@@ -65,6 +69,25 @@ pub trait Trigger<F: RuntimeFactors>: Sized + Send {
         trigger_app: TriggerApp<Self, F>,
     ) -> impl Future<Output = anyhow::Result<()>> + Send;
 
+    /// Run this trigger until `shutdown` is requested.
+    ///
+    /// Implementations should stop accepting new work once shutdown is requested
+    /// and return when any in-flight work has completed. The default implementation
+    /// stops the trigger immediately, abandoning any in-flight work.
+    fn run_with_shutdown(
+        self,
+        trigger_app: TriggerApp<Self, F>,
+        shutdown: ShutdownSignal,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        let run = self.run(trigger_app);
+        async move {
+            tokio::select! {
+                res = run => res,
+                _ = shutdown.requested() => Ok(()),
+            }
+        }
+    }
+
     /// Returns a list of host requirements supported by this trigger specifically.
     ///
     /// See [`App::ensure_needs_only`].
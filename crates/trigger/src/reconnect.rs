@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+/// The delay before the first attempt to reconnect.
+const INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// How a trigger reconnects after losing its connection to a server, such as a Redis
+/// server or an MQTT broker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// The longest delay between attempts. Delays double after each failed attempt,
+    /// up to this limit.
+    pub max_delay: Duration,
+    /// The number of consecutive failed attempts after which the trigger gives up.
+    pub max_attempts: u32,
+}
+
+impl ReconnectPolicy {
+    /// Returns the delay before the given reconnection attempt (counting from 1), or
+    /// `None` if no more attempts should be made.
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt > self.max_attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        Some(INITIAL_DELAY.saturating_mul(factor).min(self.max_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_back_off_exponentially() {
+        let policy = ReconnectPolicy {
+            max_delay: Duration::from_secs(3),
+            max_attempts: u32::MAX,
+        };
+        let delays = (1..=5).map(|attempt| policy.delay(attempt).unwrap());
+        assert_eq!(
+            delays.collect::<Vec<_>>(),
+            [500, 1000, 2000, 3000, 3000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.delay(u32::MAX), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn attempts_are_limited() {
+        let policy = ReconnectPolicy {
+            max_delay: Duration::from_secs(30),
+            max_attempts: 2,
+        };
+        assert!(policy.delay(1).is_some());
+        assert!(policy.delay(2).is_some());
+        assert_eq!(policy.delay(3), None);
+    }
+}
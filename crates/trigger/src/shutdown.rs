@@ -0,0 +1,48 @@
+use tokio_util::sync::CancellationToken;
+
+/// A signal that a trigger should shut down gracefully.
+///
+/// Clones share state: requesting shutdown through any clone is observed by all of them.
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownSignal {
+    token: CancellationToken,
+}
+
+impl ShutdownSignal {
+    /// Creates a new signal which has not been requested.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests shutdown.
+    pub fn request(&self) {
+        self.token.cancel();
+    }
+
+    /// Returns whether shutdown has been requested.
+    pub fn is_requested(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Waits until shutdown is requested.
+    pub async fn requested(&self) {
+        self.token.cancelled().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clones_observe_request() {
+        let shutdown = ShutdownSignal::new();
+        let clone = shutdown.clone();
+        assert!(!clone.is_requested());
+
+        let waiter = tokio::spawn(async move { clone.requested().await });
+        shutdown.request();
+        waiter.await.unwrap();
+        assert!(shutdown.is_requested());
+    }
+}
@@ -12,8 +12,8 @@ mod rdbms_types {
         }
     }
 
-    impl From<spin::postgres::postgres::Column> for v1::rdbms_types::Column {
-        fn from(value: spin::postgres::postgres::Column) -> Self {
+    impl From<spin::postgres4_0_0::postgres::Column> for v1::rdbms_types::Column {
+        fn from(value: spin::postgres4_0_0::postgres::Column) -> Self {
             v1::rdbms_types::Column {
                 name: value.name,
                 data_type: value.data_type.into(),
@@ -21,8 +21,8 @@ mod rdbms_types {
         }
     }
 
-    impl From<spin::postgres::postgres::Column> for v2::rdbms_types::Column {
-        fn from(value: spin::postgres::postgres::Column) -> Self {
+    impl From<spin::postgres4_0_0::postgres::Column> for v2::rdbms_types::Column {
+        fn from(value: spin::postgres4_0_0::postgres::Column) -> Self {
             v2::rdbms_types::Column {
                 name: value.name,
                 data_type: value.data_type.into(),
@@ -52,26 +52,36 @@ mod rdbms_types {
         }
     }
 
-    impl From<spin::postgres::postgres::DbValue> for v1::rdbms_types::DbValue {
-        fn from(value: spin::postgres::postgres::DbValue) -> v1::rdbms_types::DbValue {
+    impl From<spin::postgres4_0_0::postgres::DbValue> for v1::rdbms_types::DbValue {
+        fn from(value: spin::postgres4_0_0::postgres::DbValue) -> v1::rdbms_types::DbValue {
             match value {
-                spin::postgres::postgres::DbValue::Boolean(b) => {
+                spin::postgres4_0_0::postgres::DbValue::Boolean(b) => {
                     v1::rdbms_types::DbValue::Boolean(b)
                 }
-                spin::postgres::postgres::DbValue::Int8(i) => v1::rdbms_types::DbValue::Int8(i),
-                spin::postgres::postgres::DbValue::Int16(i) => v1::rdbms_types::DbValue::Int16(i),
-                spin::postgres::postgres::DbValue::Int32(i) => v1::rdbms_types::DbValue::Int32(i),
-                spin::postgres::postgres::DbValue::Int64(i) => v1::rdbms_types::DbValue::Int64(i),
-                spin::postgres::postgres::DbValue::Floating32(r) => {
+                spin::postgres4_0_0::postgres::DbValue::Int8(i) => {
+                    v1::rdbms_types::DbValue::Int8(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Int16(i) => {
+                    v1::rdbms_types::DbValue::Int16(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Int32(i) => {
+                    v1::rdbms_types::DbValue::Int32(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Int64(i) => {
+                    v1::rdbms_types::DbValue::Int64(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Floating32(r) => {
                     v1::rdbms_types::DbValue::Floating32(r)
                 }
-                spin::postgres::postgres::DbValue::Floating64(r) => {
+                spin::postgres4_0_0::postgres::DbValue::Floating64(r) => {
                     v1::rdbms_types::DbValue::Floating64(r)
                 }
-                spin::postgres::postgres::DbValue::Str(s) => v1::rdbms_types::DbValue::Str(s),
-                spin::postgres::postgres::DbValue::Binary(b) => v1::rdbms_types::DbValue::Binary(b),
-                spin::postgres::postgres::DbValue::DbNull => v1::rdbms_types::DbValue::DbNull,
-                spin::postgres::postgres::DbValue::Unsupported => {
+                spin::postgres4_0_0::postgres::DbValue::Str(s) => v1::rdbms_types::DbValue::Str(s),
+                spin::postgres4_0_0::postgres::DbValue::Binary(b) => {
+                    v1::rdbms_types::DbValue::Binary(b)
+                }
+                spin::postgres4_0_0::postgres::DbValue::DbNull => v1::rdbms_types::DbValue::DbNull,
+                spin::postgres4_0_0::postgres::DbValue::Unsupported => {
                     v1::rdbms_types::DbValue::Unsupported
                 }
                 _ => v1::rdbms_types::DbValue::Unsupported,
@@ -79,26 +89,36 @@ mod rdbms_types {
         }
     }
 
-    impl From<spin::postgres::postgres::DbValue> for v2::rdbms_types::DbValue {
-        fn from(value: spin::postgres::postgres::DbValue) -> v2::rdbms_types::DbValue {
+    impl From<spin::postgres4_0_0::postgres::DbValue> for v2::rdbms_types::DbValue {
+        fn from(value: spin::postgres4_0_0::postgres::DbValue) -> v2::rdbms_types::DbValue {
             match value {
-                spin::postgres::postgres::DbValue::Boolean(b) => {
+                spin::postgres4_0_0::postgres::DbValue::Boolean(b) => {
                     v2::rdbms_types::DbValue::Boolean(b)
                 }
-                spin::postgres::postgres::DbValue::Int8(i) => v2::rdbms_types::DbValue::Int8(i),
-                spin::postgres::postgres::DbValue::Int16(i) => v2::rdbms_types::DbValue::Int16(i),
-                spin::postgres::postgres::DbValue::Int32(i) => v2::rdbms_types::DbValue::Int32(i),
-                spin::postgres::postgres::DbValue::Int64(i) => v2::rdbms_types::DbValue::Int64(i),
-                spin::postgres::postgres::DbValue::Floating32(r) => {
+                spin::postgres4_0_0::postgres::DbValue::Int8(i) => {
+                    v2::rdbms_types::DbValue::Int8(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Int16(i) => {
+                    v2::rdbms_types::DbValue::Int16(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Int32(i) => {
+                    v2::rdbms_types::DbValue::Int32(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Int64(i) => {
+                    v2::rdbms_types::DbValue::Int64(i)
+                }
+                spin::postgres4_0_0::postgres::DbValue::Floating32(r) => {
                     v2::rdbms_types::DbValue::Floating32(r)
                 }
-                spin::postgres::postgres::DbValue::Floating64(r) => {
+                spin::postgres4_0_0::postgres::DbValue::Floating64(r) => {
                     v2::rdbms_types::DbValue::Floating64(r)
                 }
-                spin::postgres::postgres::DbValue::Str(s) => v2::rdbms_types::DbValue::Str(s),
-                spin::postgres::postgres::DbValue::Binary(b) => v2::rdbms_types::DbValue::Binary(b),
-                spin::postgres::postgres::DbValue::DbNull => v2::rdbms_types::DbValue::DbNull,
-                spin::postgres::postgres::DbValue::Unsupported => {
+                spin::postgres4_0_0::postgres::DbValue::Str(s) => v2::rdbms_types::DbValue::Str(s),
+                spin::postgres4_0_0::postgres::DbValue::Binary(b) => {
+                    v2::rdbms_types::DbValue::Binary(b)
+                }
+                spin::postgres4_0_0::postgres::DbValue::DbNull => v2::rdbms_types::DbValue::DbNull,
+                spin::postgres4_0_0::postgres::DbValue::Unsupported => {
                     v2::rdbms_types::DbValue::Unsupported
                 }
                 _ => v2::rdbms_types::DbValue::Unsupported,
@@ -106,49 +126,73 @@ mod rdbms_types {
         }
     }
 
-    impl From<spin::postgres::postgres::DbDataType> for v1::rdbms_types::DbDataType {
-        fn from(value: spin::postgres::postgres::DbDataType) -> v1::rdbms_types::DbDataType {
+    impl From<spin::postgres4_0_0::postgres::DbDataType> for v1::rdbms_types::DbDataType {
+        fn from(value: spin::postgres4_0_0::postgres::DbDataType) -> v1::rdbms_types::DbDataType {
             match value {
-                spin::postgres::postgres::DbDataType::Boolean => {
+                spin::postgres4_0_0::postgres::DbDataType::Boolean => {
                     v1::rdbms_types::DbDataType::Boolean
                 }
-                spin::postgres::postgres::DbDataType::Int8 => v1::rdbms_types::DbDataType::Int8,
-                spin::postgres::postgres::DbDataType::Int16 => v1::rdbms_types::DbDataType::Int16,
-                spin::postgres::postgres::DbDataType::Int32 => v1::rdbms_types::DbDataType::Int32,
-                spin::postgres::postgres::DbDataType::Int64 => v1::rdbms_types::DbDataType::Int64,
-                spin::postgres::postgres::DbDataType::Floating32 => {
+                spin::postgres4_0_0::postgres::DbDataType::Int8 => {
+                    v1::rdbms_types::DbDataType::Int8
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Int16 => {
+                    v1::rdbms_types::DbDataType::Int16
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Int32 => {
+                    v1::rdbms_types::DbDataType::Int32
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Int64 => {
+                    v1::rdbms_types::DbDataType::Int64
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Floating32 => {
                     v1::rdbms_types::DbDataType::Floating32
                 }
-                spin::postgres::postgres::DbDataType::Floating64 => {
+                spin::postgres4_0_0::postgres::DbDataType::Floating64 => {
                     v1::rdbms_types::DbDataType::Floating64
                 }
-                spin::postgres::postgres::DbDataType::Str => v1::rdbms_types::DbDataType::Str,
-                spin::postgres::postgres::DbDataType::Binary => v1::rdbms_types::DbDataType::Binary,
-                spin::postgres::postgres::DbDataType::Other => v1::rdbms_types::DbDataType::Other,
+                spin::postgres4_0_0::postgres::DbDataType::Str => v1::rdbms_types::DbDataType::Str,
+                spin::postgres4_0_0::postgres::DbDataType::Binary => {
+                    v1::rdbms_types::DbDataType::Binary
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Other => {
+                    v1::rdbms_types::DbDataType::Other
+                }
                 _ => v1::rdbms_types::DbDataType::Other,
             }
         }
     }
 
-    impl From<spin::postgres::postgres::DbDataType> for v2::rdbms_types::DbDataType {
-        fn from(value: spin::postgres::postgres::DbDataType) -> v2::rdbms_types::DbDataType {
+    impl From<spin::postgres4_0_0::postgres::DbDataType> for v2::rdbms_types::DbDataType {
+        fn from(value: spin::postgres4_0_0::postgres::DbDataType) -> v2::rdbms_types::DbDataType {
             match value {
-                spin::postgres::postgres::DbDataType::Boolean => {
+                spin::postgres4_0_0::postgres::DbDataType::Boolean => {
                     v2::rdbms_types::DbDataType::Boolean
                 }
-                spin::postgres::postgres::DbDataType::Int8 => v2::rdbms_types::DbDataType::Int8,
-                spin::postgres::postgres::DbDataType::Int16 => v2::rdbms_types::DbDataType::Int16,
-                spin::postgres::postgres::DbDataType::Int32 => v2::rdbms_types::DbDataType::Int32,
-                spin::postgres::postgres::DbDataType::Int64 => v2::rdbms_types::DbDataType::Int64,
-                spin::postgres::postgres::DbDataType::Floating32 => {
+                spin::postgres4_0_0::postgres::DbDataType::Int8 => {
+                    v2::rdbms_types::DbDataType::Int8
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Int16 => {
+                    v2::rdbms_types::DbDataType::Int16
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Int32 => {
+                    v2::rdbms_types::DbDataType::Int32
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Int64 => {
+                    v2::rdbms_types::DbDataType::Int64
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Floating32 => {
                     v2::rdbms_types::DbDataType::Floating32
                 }
-                spin::postgres::postgres::DbDataType::Floating64 => {
+                spin::postgres4_0_0::postgres::DbDataType::Floating64 => {
                     v2::rdbms_types::DbDataType::Floating64
                 }
-                spin::postgres::postgres::DbDataType::Str => v2::rdbms_types::DbDataType::Str,
-                spin::postgres::postgres::DbDataType::Binary => v2::rdbms_types::DbDataType::Binary,
-                spin::postgres::postgres::DbDataType::Other => v2::rdbms_types::DbDataType::Other,
+                spin::postgres4_0_0::postgres::DbDataType::Str => v2::rdbms_types::DbDataType::Str,
+                spin::postgres4_0_0::postgres::DbDataType::Binary => {
+                    v2::rdbms_types::DbDataType::Binary
+                }
+                spin::postgres4_0_0::postgres::DbDataType::Other => {
+                    v2::rdbms_types::DbDataType::Other
+                }
                 _ => v2::rdbms_types::DbDataType::Other,
             }
         }
@@ -220,27 +264,27 @@ mod rdbms_types {
         }
     }
 
-    impl TryFrom<v1::rdbms_types::ParameterValue> for spin::postgres::postgres::ParameterValue {
+    impl TryFrom<v1::rdbms_types::ParameterValue> for spin::postgres4_0_0::postgres::ParameterValue {
         type Error = v1::postgres::PgError;
 
         fn try_from(
             value: v1::rdbms_types::ParameterValue,
-        ) -> Result<spin::postgres::postgres::ParameterValue, Self::Error> {
+        ) -> Result<spin::postgres4_0_0::postgres::ParameterValue, Self::Error> {
             let converted = match value {
                 v1::rdbms_types::ParameterValue::Boolean(b) => {
-                    spin::postgres::postgres::ParameterValue::Boolean(b)
+                    spin::postgres4_0_0::postgres::ParameterValue::Boolean(b)
                 }
                 v1::rdbms_types::ParameterValue::Int8(i) => {
-                    spin::postgres::postgres::ParameterValue::Int8(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int8(i)
                 }
                 v1::rdbms_types::ParameterValue::Int16(i) => {
-                    spin::postgres::postgres::ParameterValue::Int16(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int16(i)
                 }
                 v1::rdbms_types::ParameterValue::Int32(i) => {
-                    spin::postgres::postgres::ParameterValue::Int32(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int32(i)
                 }
                 v1::rdbms_types::ParameterValue::Int64(i) => {
-                    spin::postgres::postgres::ParameterValue::Int64(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int64(i)
                 }
                 v1::rdbms_types::ParameterValue::Uint8(_)
                 | v1::rdbms_types::ParameterValue::Uint16(_)
@@ -251,46 +295,46 @@ mod rdbms_types {
                     ));
                 }
                 v1::rdbms_types::ParameterValue::Floating32(r) => {
-                    spin::postgres::postgres::ParameterValue::Floating32(r)
+                    spin::postgres4_0_0::postgres::ParameterValue::Floating32(r)
                 }
                 v1::rdbms_types::ParameterValue::Floating64(r) => {
-                    spin::postgres::postgres::ParameterValue::Floating64(r)
+                    spin::postgres4_0_0::postgres::ParameterValue::Floating64(r)
                 }
                 v1::rdbms_types::ParameterValue::Str(s) => {
-                    spin::postgres::postgres::ParameterValue::Str(s)
+                    spin::postgres4_0_0::postgres::ParameterValue::Str(s)
                 }
                 v1::rdbms_types::ParameterValue::Binary(b) => {
-                    spin::postgres::postgres::ParameterValue::Binary(b)
+                    spin::postgres4_0_0::postgres::ParameterValue::Binary(b)
                 }
                 v1::rdbms_types::ParameterValue::DbNull => {
-                    spin::postgres::postgres::ParameterValue::DbNull
+                    spin::postgres4_0_0::postgres::ParameterValue::DbNull
                 }
             };
             Ok(converted)
         }
     }
 
-    impl TryFrom<v2::rdbms_types::ParameterValue> for spin::postgres::postgres::ParameterValue {
+    impl TryFrom<v2::rdbms_types::ParameterValue> for spin::postgres4_0_0::postgres::ParameterValue {
         type Error = v2::rdbms_types::Error;
 
         fn try_from(
             value: v2::rdbms_types::ParameterValue,
-        ) -> Result<spin::postgres::postgres::ParameterValue, Self::Error> {
+        ) -> Result<spin::postgres4_0_0::postgres::ParameterValue, Self::Error> {
             let converted = match value {
                 v2::rdbms_types::ParameterValue::Boolean(b) => {
-                    spin::postgres::postgres::ParameterValue::Boolean(b)
+                    spin::postgres4_0_0::postgres::ParameterValue::Boolean(b)
                 }
                 v2::rdbms_types::ParameterValue::Int8(i) => {
-                    spin::postgres::postgres::ParameterValue::Int8(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int8(i)
                 }
                 v2::rdbms_types::ParameterValue::Int16(i) => {
-                    spin::postgres::postgres::ParameterValue::Int16(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int16(i)
                 }
                 v2::rdbms_types::ParameterValue::Int32(i) => {
-                    spin::postgres::postgres::ParameterValue::Int32(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int32(i)
                 }
                 v2::rdbms_types::ParameterValue::Int64(i) => {
-                    spin::postgres::postgres::ParameterValue::Int64(i)
+                    spin::postgres4_0_0::postgres::ParameterValue::Int64(i)
                 }
                 v2::rdbms_types::ParameterValue::Uint8(_)
                 | v2::rdbms_types::ParameterValue::Uint16(_)
@@ -301,19 +345,19 @@ mod rdbms_types {
                     ));
                 }
                 v2::rdbms_types::ParameterValue::Floating32(r) => {
-                    spin::postgres::postgres::ParameterValue::Floating32(r)
+                    spin::postgres4_0_0::postgres::ParameterValue::Floating32(r)
                 }
                 v2::rdbms_types::ParameterValue::Floating64(r) => {
-                    spin::postgres::postgres::ParameterValue::Floating64(r)
+                    spin::postgres4_0_0::postgres::ParameterValue::Floating64(r)
                 }
                 v2::rdbms_types::ParameterValue::Str(s) => {
-                    spin::postgres::postgres::ParameterValue::Str(s)
+                    spin::postgres4_0_0::postgres::ParameterValue::Str(s)
                 }
                 v2::rdbms_types::ParameterValue::Binary(b) => {
-                    spin::postgres::postgres::ParameterValue::Binary(b)
+                    spin::postgres4_0_0::postgres::ParameterValue::Binary(b)
                 }
                 v2::rdbms_types::ParameterValue::DbNull => {
-                    spin::postgres::postgres::ParameterValue::DbNull
+                    spin::postgres4_0_0::postgres::ParameterValue::DbNull
                 }
             };
             Ok(converted)
@@ -334,42 +378,44 @@ mod rdbms_types {
         }
     }
 
-    impl From<spin::postgres::postgres::Error> for v1::postgres::PgError {
-        fn from(error: spin::postgres::postgres::Error) -> v1::postgres::PgError {
+    impl From<spin::postgres4_0_0::postgres::Error> for v1::postgres::PgError {
+        fn from(error: spin::postgres4_0_0::postgres::Error) -> v1::postgres::PgError {
             match error {
-                spin::postgres::postgres::Error::ConnectionFailed(e) => {
+                spin::postgres4_0_0::postgres::Error::ConnectionFailed(e) => {
                     v1::postgres::PgError::ConnectionFailed(e)
                 }
-                spin::postgres::postgres::Error::BadParameter(e) => {
+                spin::postgres4_0_0::postgres::Error::BadParameter(e) => {
                     v1::postgres::PgError::BadParameter(e)
                 }
-                spin::postgres::postgres::Error::QueryFailed(e) => {
+                spin::postgres4_0_0::postgres::Error::QueryFailed(e) => {
                     v1::postgres::PgError::QueryFailed(e)
                 }
-                spin::postgres::postgres::Error::ValueConversionFailed(e) => {
+                spin::postgres4_0_0::postgres::Error::ValueConversionFailed(e) => {
                     v1::postgres::PgError::ValueConversionFailed(e)
                 }
-                spin::postgres::postgres::Error::Other(e) => v1::postgres::PgError::OtherError(e),
+                spin::postgres4_0_0::postgres::Error::Other(e) => {
+                    v1::postgres::PgError::OtherError(e)
+                }
             }
         }
     }
 
-    impl From<spin::postgres::postgres::Error> for v2::rdbms_types::Error {
-        fn from(error: spin::postgres::postgres::Error) -> v2::rdbms_types::Error {
+    impl From<spin::postgres4_0_0::postgres::Error> for v2::rdbms_types::Error {
+        fn from(error: spin::postgres4_0_0::postgres::Error) -> v2::rdbms_types::Error {
             match error {
-                spin::postgres::postgres::Error::ConnectionFailed(e) => {
+                spin::postgres4_0_0::postgres::Error::ConnectionFailed(e) => {
                     v2::rdbms_types::Error::ConnectionFailed(e)
                 }
-                spin::postgres::postgres::Error::BadParameter(e) => {
+                spin::postgres4_0_0::postgres::Error::BadParameter(e) => {
                     v2::rdbms_types::Error::BadParameter(e)
                 }
-                spin::postgres::postgres::Error::QueryFailed(e) => {
+                spin::postgres4_0_0::postgres::Error::QueryFailed(e) => {
                     v2::rdbms_types::Error::QueryFailed(e)
                 }
-                spin::postgres::postgres::Error::ValueConversionFailed(e) => {
+                spin::postgres4_0_0::postgres::Error::ValueConversionFailed(e) => {
                     v2::rdbms_types::Error::ValueConversionFailed(e)
                 }
-                spin::postgres::postgres::Error::Other(e) => v2::rdbms_types::Error::Other(e),
+                spin::postgres4_0_0::postgres::Error::Other(e) => v2::rdbms_types::Error::Other(e),
             }
         }
     }
@@ -377,9 +423,11 @@ mod rdbms_types {
 
 mod postgres {
     use super::*;
+    use spin::postgres3_0_0::postgres as v3;
+    use spin::postgres4_0_0::postgres as v4;
 
-    impl From<spin::postgres::postgres::RowSet> for v1::postgres::RowSet {
-        fn from(value: spin::postgres::postgres::RowSet) -> v1::postgres::RowSet {
+    impl From<spin::postgres4_0_0::postgres::RowSet> for v1::postgres::RowSet {
+        fn from(value: spin::postgres4_0_0::postgres::RowSet) -> v1::postgres::RowSet {
             v1::mysql::RowSet {
                 columns: value.columns.into_iter().map(Into::into).collect(),
                 rows: value
@@ -391,8 +439,8 @@ mod postgres {
         }
     }
 
-    impl From<spin::postgres::postgres::RowSet> for v2::rdbms_types::RowSet {
-        fn from(value: spin::postgres::postgres::RowSet) -> v2::rdbms_types::RowSet {
+    impl From<spin::postgres4_0_0::postgres::RowSet> for v2::rdbms_types::RowSet {
+        fn from(value: spin::postgres4_0_0::postgres::RowSet) -> v2::rdbms_types::RowSet {
             v2::rdbms_types::RowSet {
                 columns: value.columns.into_iter().map(Into::into).collect(),
                 rows: value
@@ -403,6 +451,114 @@ mod postgres {
             }
         }
     }
+
+    impl From<v4::RowSet> for v3::RowSet {
+        fn from(value: v4::RowSet) -> v3::RowSet {
+            v3::RowSet {
+                columns: value.columns.into_iter().map(Into::into).collect(),
+                rows: value
+                    .rows
+                    .into_iter()
+                    .map(|r| r.into_iter().map(Into::into).collect())
+                    .collect(),
+            }
+        }
+    }
+
+    impl From<v4::Column> for v3::Column {
+        fn from(value: v4::Column) -> v3::Column {
+            v3::Column {
+                name: value.name,
+                data_type: value.data_type.into(),
+            }
+        }
+    }
+
+    impl From<v4::DbDataType> for v3::DbDataType {
+        fn from(value: v4::DbDataType) -> v3::DbDataType {
+            match value {
+                v4::DbDataType::Boolean => v3::DbDataType::Boolean,
+                v4::DbDataType::Int8 => v3::DbDataType::Int8,
+                v4::DbDataType::Int16 => v3::DbDataType::Int16,
+                v4::DbDataType::Int32 => v3::DbDataType::Int32,
+                v4::DbDataType::Int64 => v3::DbDataType::Int64,
+                v4::DbDataType::Floating32 => v3::DbDataType::Floating32,
+                v4::DbDataType::Floating64 => v3::DbDataType::Floating64,
+                v4::DbDataType::Str => v3::DbDataType::Str,
+                v4::DbDataType::Binary => v3::DbDataType::Binary,
+                v4::DbDataType::Date => v3::DbDataType::Date,
+                v4::DbDataType::Time => v3::DbDataType::Time,
+                v4::DbDataType::Datetime => v3::DbDataType::Datetime,
+                v4::DbDataType::Timestamp => v3::DbDataType::Timestamp,
+                v4::DbDataType::Uuid
+                | v4::DbDataType::Jsonb
+                | v4::DbDataType::Decimal
+                | v4::DbDataType::ArrayStr
+                | v4::DbDataType::Interval
+                | v4::DbDataType::Other => v3::DbDataType::Other,
+            }
+        }
+    }
+
+    impl From<v4::DbValue> for v3::DbValue {
+        fn from(value: v4::DbValue) -> v3::DbValue {
+            match value {
+                v4::DbValue::Boolean(b) => v3::DbValue::Boolean(b),
+                v4::DbValue::Int8(i) => v3::DbValue::Int8(i),
+                v4::DbValue::Int16(i) => v3::DbValue::Int16(i),
+                v4::DbValue::Int32(i) => v3::DbValue::Int32(i),
+                v4::DbValue::Int64(i) => v3::DbValue::Int64(i),
+                v4::DbValue::Floating32(r) => v3::DbValue::Floating32(r),
+                v4::DbValue::Floating64(r) => v3::DbValue::Floating64(r),
+                v4::DbValue::Str(s) => v3::DbValue::Str(s),
+                v4::DbValue::Binary(b) => v3::DbValue::Binary(b),
+                v4::DbValue::Date(d) => v3::DbValue::Date(d),
+                v4::DbValue::Time(t) => v3::DbValue::Time(t),
+                v4::DbValue::Datetime(dt) => v3::DbValue::Datetime(dt),
+                v4::DbValue::Timestamp(ts) => v3::DbValue::Timestamp(ts),
+                v4::DbValue::DbNull => v3::DbValue::DbNull,
+                v4::DbValue::Uuid(_)
+                | v4::DbValue::Jsonb(_)
+                | v4::DbValue::Decimal(_)
+                | v4::DbValue::ArrayStr(_)
+                | v4::DbValue::Interval(_)
+                | v4::DbValue::Unsupported => v3::DbValue::Unsupported,
+            }
+        }
+    }
+
+    impl From<v3::ParameterValue> for v4::ParameterValue {
+        fn from(value: v3::ParameterValue) -> v4::ParameterValue {
+            match value {
+                v3::ParameterValue::Boolean(b) => v4::ParameterValue::Boolean(b),
+                v3::ParameterValue::Int8(i) => v4::ParameterValue::Int8(i),
+                v3::ParameterValue::Int16(i) => v4::ParameterValue::Int16(i),
+                v3::ParameterValue::Int32(i) => v4::ParameterValue::Int32(i),
+                v3::ParameterValue::Int64(i) => v4::ParameterValue::Int64(i),
+                v3::ParameterValue::Floating32(r) => v4::ParameterValue::Floating32(r),
+                v3::ParameterValue::Floating64(r) => v4::ParameterValue::Floating64(r),
+                v3::ParameterValue::Str(s) => v4::ParameterValue::Str(s),
+                v3::ParameterValue::Binary(b) => v4::ParameterValue::Binary(b),
+                v3::ParameterValue::Date(d) => v4::ParameterValue::Date(d),
+                v3::ParameterValue::Time(t) => v4::ParameterValue::Time(t),
+                v3::ParameterValue::Datetime(dt) => v4::ParameterValue::Datetime(dt),
+                v3::ParameterValue::Timestamp(ts) => v4::ParameterValue::Timestamp(ts),
+                v3::ParameterValue::DbNull => v4::ParameterValue::DbNull,
+            }
+        }
+    }
+
+    impl From<v4::Error> for v3::Error {
+        fn from(error: v4::Error) -> v3::Error {
+            match error {
+                v4::Error::ConnectionFailed(e) => v3::Error::ConnectionFailed(e),
+                v4::Error::BadParameter(e) => v3::Error::BadParameter(e),
+                v4::Error::QueryFailed(e) => v3::Error::QueryFailed(e),
+                v4::Error::ValueConversionFailed(e) => v3::Error::ValueConversionFailed(e),
+                v4::Error::Other(e) => v3::Error::Other(e),
+            }
+        }
+    }
 }
 
 mod mysql {
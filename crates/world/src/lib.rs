@@ -10,6 +10,9 @@ wasmtime::component::bindgen!({
         include fermyon:spin/host;
         include fermyon:spin/platform@2.0.0;
         include fermyon:spin/platform@3.0.0;
+        include fermyon:spin/cron-trigger@3.0.0;
+        include fermyon:spin/mqtt-trigger@3.0.0;
+        include fermyon:spin/redis-trigger@3.0.0;
         include wasi:keyvalue/imports@0.2.0-draft2;
     }
     "#,
@@ -30,12 +33,18 @@ wasmtime::component::bindgen!({
         "fermyon:spin/sqlite@2.0.0/error" => v2::sqlite::Error,
         "fermyon:spin/sqlite/error" => v1::sqlite::Error,
         "fermyon:spin/variables@2.0.0/error" => v2::variables::Error,
-        "spin:postgres/postgres/error" => spin::postgres::postgres::Error,
+        "spin:postgres/postgres@3.0.0/error" => spin::postgres3_0_0::postgres::Error,
+        "spin:postgres/postgres@4.0.0/error" => spin::postgres4_0_0::postgres::Error,
         "wasi:config/store@0.2.0-draft-2024-09-27/error" => wasi::config::store::Error,
         "wasi:keyvalue/store/error" => wasi::keyvalue::store::Error,
         "wasi:keyvalue/atomics/cas-error" => wasi::keyvalue::atomics::CasError,
     },
     trappable_imports: true,
+    with: {
+        "wasi:io/error@0.2.0": wasmtime_wasi::bindings::io::error,
+        "wasi:io/poll@0.2.0": wasmtime_wasi::bindings::io::poll,
+        "wasi:io/streams@0.2.0": wasmtime_wasi::bindings::io::streams,
+    },
 });
 
 pub use fermyon::spin as v1;
@@ -10,6 +10,7 @@ use spin_cli::commands::{
     new::{AddCommand, NewCommand},
     plugins::PluginCommands,
     registry::RegistryCommands,
+    sqlite::SqliteCommands,
     templates::TemplateCommands,
     up::UpCommand,
     watch::WatchCommand,
@@ -18,7 +19,9 @@ use spin_cli::{build_info::*, subprocess::ExitStatusError};
 use spin_runtime_factors::FactorsBuilder;
 use spin_trigger::cli::help::HelpArgsOnlyTrigger;
 use spin_trigger::cli::FactorsTriggerCommand;
+use spin_trigger_cron::CronTrigger;
 use spin_trigger_http::HttpTrigger;
+use spin_trigger_mqtt::MqttTrigger;
 use spin_trigger_redis::RedisTrigger;
 
 #[tokio::main]
@@ -136,12 +139,16 @@ enum SpinApp {
     #[clap(alias = "w")]
     Watch(WatchCommand),
     Doctor(DoctorCommand),
+    #[clap(subcommand)]
+    Sqlite(SqliteCommands),
 }
 
 #[derive(Subcommand)]
 enum TriggerCommands {
     Http(FactorsTriggerCommand<HttpTrigger, FactorsBuilder>),
     Redis(FactorsTriggerCommand<RedisTrigger, FactorsBuilder>),
+    Cron(FactorsTriggerCommand<CronTrigger, FactorsBuilder>),
+    Mqtt(FactorsTriggerCommand<MqttTrigger, FactorsBuilder>),
     #[clap(name = spin_cli::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(FactorsTriggerCommand<HelpArgsOnlyTrigger, FactorsBuilder>),
 }
@@ -160,11 +167,14 @@ impl SpinApp {
             Self::Build(cmd) => cmd.run().await,
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Cron(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Mqtt(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
             Self::Plugins(cmd) => cmd.run().await,
             Self::External(cmd) => execute_external_subcommand(cmd, app).await,
             Self::Watch(cmd) => cmd.run().await,
             Self::Doctor(cmd) => cmd.run().await,
+            Self::Sqlite(cmd) => cmd.run().await,
         }
     }
 }
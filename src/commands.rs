@@ -14,6 +14,8 @@ pub mod new;
 pub mod plugins;
 /// Commands for working with OCI registries.
 pub mod registry;
+/// Commands for working with SQLite databases.
+pub mod sqlite;
 /// Commands for working with templates.
 pub mod templates;
 /// Commands for starting the runtime.
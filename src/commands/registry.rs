@@ -3,7 +3,12 @@ use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use indicatif::{ProgressBar, ProgressStyle};
 use spin_common::arg_parser::parse_kv;
-use spin_oci::{client::InferPredefinedAnnotations, Client};
+use spin_common::ui::quoted_path;
+use spin_oci::{
+    client::InferPredefinedAnnotations,
+    signing::{SigningKey, TrustPolicy},
+    Client,
+};
 use std::{io::Read, path::PathBuf, time::Duration};
 
 /// Commands for working with OCI registries to distribute applications.
@@ -55,8 +60,14 @@ pub struct Push {
 
     /// Reference in the registry of the Spin application.
     /// This is a string whose format is defined by the registry standard, and generally consists of <registry>/<username>/<application-name>:<version>. E.g. ghcr.io/ogghead/spin-test-app:0.1.0
-    #[clap()]
-    pub reference: String,
+    /// When writing to an OCI layout, only the tag is used, to name the application in the layout.
+    #[clap(required_unless_present = TO_OCI_LAYOUT_OPT)]
+    pub reference: Option<String>,
+
+    /// Write the application to an OCI image layout directory instead of pushing it
+    /// to a registry. The directory is created if it does not exist.
+    #[clap(name = TO_OCI_LAYOUT_OPT, long = "to-oci-layout", conflicts_with = "sign")]
+    pub to_oci_layout: Option<PathBuf>,
 
     /// Cache directory for downloaded registry data.
     #[clap(long)]
@@ -66,6 +77,15 @@ pub struct Push {
     /// Any existing value will be overwritten. Can be used multiple times.
     #[clap(long = "annotation", parse(try_from_str = parse_kv))]
     pub annotations: Vec<(String, String)>,
+
+    /// Sign the application with the key given by `--signing-key`, and push the
+    /// signature alongside it.
+    #[clap(long, takes_value = false, requires = SIGNING_KEY_OPT)]
+    pub sign: bool,
+
+    /// Ed25519 private key (PKCS#8 PEM) to sign the application with.
+    #[clap(name = SIGNING_KEY_OPT, long = "signing-key", env = "SPIN_OCI_SIGNING_KEY")]
+    pub signing_key: Option<PathBuf>,
 }
 
 impl Push {
@@ -85,13 +105,42 @@ impl Push {
         };
 
         let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone()).await?;
+        if self.sign {
+            // Required by `--sign`.
+            let key_path = self.signing_key.as_deref().unwrap();
+            client.opts.signing_key = Some(SigningKey::from_pem_file(key_path)?);
+        }
+
+        if let Some(layout_dir) = &self.to_oci_layout {
+            let ref_name = match &self.reference {
+                Some(reference) => spin_oci::client::layout_ref_name(reference)?,
+                None => "latest".to_owned(),
+            };
+            let digest = client
+                .push_to_layout(
+                    &app_file,
+                    layout_dir,
+                    &ref_name,
+                    annotations,
+                    InferPredefinedAnnotations::All,
+                )
+                .await?;
+            println!(
+                "Wrote {ref_name} to {} with digest {digest}",
+                quoted_path(layout_dir)
+            );
+            return Ok(());
+        }
+
+        // Required unless `--to-oci-layout` is set.
+        let reference = self.reference.as_deref().unwrap();
 
         let _spinner = create_dotted_spinner(2000, "Pushing app to the Registry".to_owned());
 
         let digest = client
             .push(
                 &app_file,
-                &self.reference,
+                reference,
                 annotations,
                 InferPredefinedAnnotations::All,
             )
@@ -124,12 +173,18 @@ pub struct Pull {
     /// Cache directory for downloaded registry data.
     #[clap(long)]
     pub cache_dir: Option<PathBuf>,
+
+    /// Trust policy to verify the application's signature against. Defaults to
+    /// the policy in the Spin configuration directory, if there is one.
+    #[clap(long = "trust-policy", env = TRUST_POLICY_ENV)]
+    pub trust_policy: Option<PathBuf>,
 }
 
 impl Pull {
     /// Pull a Spin application from an OCI registry
     pub async fn run(self) -> Result<()> {
         let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone()).await?;
+        client.opts.trust_policy = TrustPolicy::load(self.trust_policy.as_deref())?;
 
         let _spinner = create_dotted_spinner(2000, "Pulling app from the Registry".to_owned());
 
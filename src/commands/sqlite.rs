@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use spin_loader::FilesMountStrategy;
+use spin_runtime_config::ResolvedRuntimeConfig;
+use spin_runtime_factors::TriggerFactorsRuntimeConfig;
+use spin_trigger::cli::{UserProvidedPath, RUNTIME_CONFIG_FILE};
+
+use crate::opts::*;
+
+/// Commands for working with the SQLite databases of an application.
+#[derive(Subcommand, Debug)]
+pub enum SqliteCommands {
+    /// Apply the pending migrations declared for an application's SQLite databases.
+    Migrate(Migrate),
+}
+
+impl SqliteCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            SqliteCommands::Migrate(cmd) => cmd.run().await,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Migrate {
+    /// The application to migrate. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file"
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// Configuration file for config providers and wasmtime config.
+    #[clap(
+        name = RUNTIME_CONFIG_FILE,
+        long = "runtime-config-file",
+        env = RUNTIME_CONFIG_FILE,
+    )]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Set the application state directory path. This is used in the default
+    /// locations for logs, key value stores, etc.
+    ///
+    /// For local apps, this defaults to `.spin/` relative to the `spin.toml` file.
+    #[clap(long)]
+    pub state_dir: Option<String>,
+
+    /// Report the pending migrations without applying them.
+    #[clap(long, takes_value = false)]
+    pub dry_run: bool,
+}
+
+impl Migrate {
+    pub async fn run(self) -> Result<()> {
+        let (manifest_file, _) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        let working_dir = tempfile::tempdir()?;
+        let files_mount_strategy = FilesMountStrategy::Copy(working_dir.path().join("assets"));
+        let locked_app = spin_loader::from_file(&manifest_file, files_mount_strategy, None)
+            .await
+            .with_context(|| format!("failed to load manifest {}", manifest_file.display()))?;
+        let app = spin_app::App::new("migrate", locked_app);
+
+        let state_dir = match &self.state_dir {
+            // Make sure `--state-dir=""` unsets the state dir
+            Some(s) if s.is_empty() => UserProvidedPath::Unset,
+            Some(s) => UserProvidedPath::Provided(PathBuf::from(s)),
+            None => UserProvidedPath::Default,
+        };
+        let local_app_dir = spin_common::paths::parent_dir(&manifest_file)?;
+        let runtime_config = ResolvedRuntimeConfig::<TriggerFactorsRuntimeConfig>::from_file(
+            self.runtime_config_file.as_deref(),
+            Some(local_app_dir),
+            state_dir,
+            UserProvidedPath::Default,
+        )?;
+        let connection_creators = runtime_config
+            .runtime_config
+            .sqlite
+            .unwrap_or_default()
+            .connection_creators;
+        let sqlite = spin_factor_sqlite::AppState::new(Default::default(), connection_creators);
+
+        let reports = spin_factor_sqlite::migrations::migrate(&sqlite, &app, self.dry_run).await?;
+        if reports.is_empty() {
+            println!("No SQLite migrations are declared.");
+        }
+        for report in reports {
+            let verb = if self.dry_run {
+                "Would apply"
+            } else {
+                "Applied"
+            };
+            if report.applied.is_empty() {
+                println!("SQLite database '{}' is up to date.", report.label);
+                continue;
+            }
+            println!(
+                "{verb} {} migration(s) to SQLite database '{}':",
+                report.applied.len(),
+                report.label
+            );
+            for migration in report.applied {
+                println!("  {} {}", migration.version, migration.name);
+            }
+        }
+        Ok(())
+    }
+}
@@ -79,6 +79,20 @@ pub struct UpCommand {
     )]
     pub registry_source: Option<String>,
 
+    /// The application to run, loaded from an OCI image layout directory such as
+    /// one written by `spin registry push --to-oci-layout`.
+    #[clap(
+        name = FROM_OCI_LAYOUT_OPT,
+        long = "from-oci-layout",
+        group = "source",
+    )]
+    pub oci_layout_source: Option<PathBuf>,
+
+    /// The name of the application to run from the OCI image layout, if the layout
+    /// contains more than one.
+    #[clap(long = "oci-layout-ref", requires = FROM_OCI_LAYOUT_OPT)]
+    pub oci_layout_ref: Option<String>,
+
     /// Ignore server certificate errors from a registry
     #[clap(
         name = INSECURE_OPT,
@@ -100,6 +114,13 @@ pub struct UpCommand {
     #[clap(long)]
     pub cache_dir: Option<PathBuf>,
 
+    /// Trust policy to verify the signature of a registry application against.
+    /// Defaults to the policy in the Spin configuration directory, if there is one.
+    /// Applications from OCI layouts cannot be verified, so they are refused if
+    /// a rule of the policy that matches their reference name requires a signature.
+    #[clap(long = "trust-policy", env = TRUST_POLICY_ENV)]
+    pub trust_policy: Option<PathBuf>,
+
     /// For local apps with directory mounts and no excluded files, mount them directly instead of using a temporary
     /// directory.
     ///
@@ -393,11 +414,20 @@ impl UpCommand {
     }
 
     fn app_source(&self) -> AppSource {
-        match (&self.app_source, &self.file_source, &self.registry_source) {
-            (None, None, None) => self.default_manifest_or_none(),
-            (Some(source), None, None) => AppSource::infer_source(source),
-            (None, Some(file), None) => AppSource::infer_file_source(file.to_owned()),
-            (None, None, Some(reference)) => AppSource::OciRegistry(reference.to_owned()),
+        match (
+            &self.app_source,
+            &self.file_source,
+            &self.registry_source,
+            &self.oci_layout_source,
+        ) {
+            (None, None, None, None) => self.default_manifest_or_none(),
+            (Some(source), None, None, None) => AppSource::infer_source(source),
+            (None, Some(file), None, None) => AppSource::infer_file_source(file.to_owned()),
+            (None, None, Some(reference), None) => AppSource::OciRegistry(reference.to_owned()),
+            (None, None, None, Some(dir)) => AppSource::OciLayout {
+                dir: dir.to_owned(),
+                ref_name: self.oci_layout_ref.clone(),
+            },
             _ => AppSource::unresolvable("More than one application source was specified"),
         }
     }
@@ -466,12 +496,26 @@ impl UpCommand {
                 let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone())
                     .await
                     .context("cannot create registry client")?;
+                client.opts.trust_policy =
+                    spin_oci::signing::TrustPolicy::load(self.trust_policy.as_deref())?;
 
                 let locked_app = OciLoader::new(working_dir)
                     .load_app(&mut client, reference)
                     .await?;
                 ResolvedAppSource::OciRegistry { locked_app }
             }
+            AppSource::OciLayout { dir, ref_name } => {
+                let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone())
+                    .await
+                    .context("cannot create registry client")?;
+                client.opts.trust_policy =
+                    spin_oci::signing::TrustPolicy::load(self.trust_policy.as_deref())?;
+
+                let locked_app = OciLoader::new(working_dir)
+                    .load_from_layout(&mut client, dir, ref_name.as_deref())
+                    .await?;
+                ResolvedAppSource::OciRegistry { locked_app }
+            }
             AppSource::BareWasm(path) => ResolvedAppSource::BareWasm {
                 wasm_path: path.clone(),
             },
@@ -671,7 +715,7 @@ fn trigger_commands_for_trigger_types(trigger_types: Vec<&str>) -> Result<Vec<Ve
     trigger_types
         .iter()
         .map(|&t| match t {
-            "http" | "redis" => Ok(trigger_command(t)),
+            "http" | "redis" | "cron" | "mqtt" => Ok(trigger_command(t)),
             _ => {
                 let cmd = resolve_trigger_plugin(t)?;
                 Ok(vec![cmd])
@@ -12,6 +12,10 @@ use spin_manifest::schema::v2::AppManifest;
 pub enum AppSource {
     File(PathBuf),
     OciRegistry(String),
+    OciLayout {
+        dir: PathBuf,
+        ref_name: Option<String>,
+    },
     BareWasm(PathBuf),
     Unresolvable(String),
     None,
@@ -74,6 +78,10 @@ impl std::fmt::Display for AppSource {
         match self {
             Self::File(path) => write!(f, "local app {}", quoted_path(path)),
             Self::OciRegistry(reference) => write!(f, "remote app {reference:?}"),
+            Self::OciLayout { dir, ref_name } => match ref_name {
+                Some(name) => write!(f, "app {name:?} in OCI layout {}", quoted_path(dir)),
+                None => write!(f, "app in OCI layout {}", quoted_path(dir)),
+            },
             Self::BareWasm(path) => write!(f, "Wasm file {}", quoted_path(path)),
             Self::Unresolvable(s) => write!(f, "unknown app source: {s:?}"),
             Self::None => write!(f, "<no source>"),
@@ -9,6 +9,10 @@ pub const PLUGIN_ALL_OPT: &str = "ALL";
 pub const PLUGIN_OVERRIDE_COMPATIBILITY_CHECK_FLAG: &str = "override-compatibility-check";
 pub const HELP_ARGS_ONLY_TRIGGER_TYPE: &str = "provide-help-args-no-app";
 pub const FROM_REGISTRY_OPT: &str = "REGISTRY_REFERENCE";
+pub const FROM_OCI_LAYOUT_OPT: &str = "OCI_LAYOUT";
+pub const TO_OCI_LAYOUT_OPT: &str = "TO_OCI_LAYOUT";
+pub const SIGNING_KEY_OPT: &str = "SIGNING_KEY";
+pub const TRUST_POLICY_ENV: &str = spin_oci::signing::TRUST_POLICY_ENV;
 pub const WATCH_CLEAR_OPT: &str = "CLEAR";
 pub const WATCH_DEBOUNCE_OPT: &str = "DEBOUNCE";
 pub const WATCH_SKIP_BUILD_OPT: &str = "SKIP_BUILD";
@@ -0,0 +1,29 @@
+wit_bindgen::generate!({
+    path: "../../../../wit",
+    world: "mqtt-trigger",
+    exports: {
+        "spin:mqtt/inbound-mqtt@3.0.0": Component,
+    }
+});
+
+use exports::spin::mqtt::inbound_mqtt::{Error, Guest, Message};
+use fermyon::spin::mqtt::Qos;
+
+struct Component;
+
+impl Guest for Component {
+    fn handle_message(message: Message) -> Result<(), Error> {
+        let payload = String::from_utf8(message.payload)
+            .map_err(|_| Error::Other("message is not UTF-8".to_owned()))?;
+        let qos = match message.qos {
+            Qos::AtMostOnce => 0,
+            Qos::AtLeastOnce => 1,
+            Qos::ExactlyOnce => 2,
+        };
+        println!(
+            "Got message on topic '{}' (qos {qos}): '{payload}'",
+            message.topic
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use helper::{ensure, ensure_eq, ensure_matches, ensure_ok};
 
-use bindings::spin::postgres::postgres;
+use bindings::spin::postgres3_0_0::postgres;
 
 helper::define_component!(Component);
 const DB_URL_ENV: &str = "DB_URL";
@@ -2,6 +2,10 @@ use spin_sdk::redis_component;
 
 #[redis_component]
 fn on_message(message: bytes::Bytes) -> anyhow::Result<()> {
+    // Lets tests exercise the handling of failed messages
+    if message.as_ref() == b"fail" {
+        anyhow::bail!("failing as requested");
+    }
     println!(
         "Got message: '{}'",
         std::str::from_utf8(&message).unwrap_or("<MESSAGE NOT UTF8>")
@@ -0,0 +1,28 @@
+wit_bindgen::generate!({
+    path: "../../../../wit",
+    world: "redis-trigger",
+    exports: {
+        "spin:redis/inbound-redis@3.0.0": Component,
+    }
+});
+
+use exports::spin::redis::inbound_redis::{Error, Guest, Message};
+
+struct Component;
+
+impl Guest for Component {
+    fn handle_message(message: Message) -> Result<(), Error> {
+        let payload = String::from_utf8(message.payload)
+            .map_err(|_| Error::Other("message is not UTF-8".to_owned()))?;
+        // Lets tests exercise the handling of failed messages
+        if payload == "fail" {
+            return Err(Error::Other("failing as requested".to_owned()));
+        }
+        println!(
+            "Got message on channel '{}' (pattern '{}'): '{payload}'",
+            message.channel,
+            message.pattern.as_deref().unwrap_or("<none>"),
+        );
+        Ok(())
+    }
+}